@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+
+use open_dota_server::{
+    player::PlayerId,
+    snapshot::{PlayerSnapshot, Snapshot},
+};
+
+use crate::units::LatestSnapshot;
+
+/// Distance per second the free camera pans.
+const CAMERA_PAN_SPEED: f32 = 600.0;
+
+/// How the camera moves while watching a match instead of playing it. Only present in replays
+/// and while spectating.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Panned with the arrow keys.
+    #[default]
+    Free,
+    /// Follows the hero of a player.
+    Following(PlayerId),
+}
+
+impl CameraMode {
+    /// The player being followed, if they are in `snapshot`.
+    pub fn followed<'a>(&self, snapshot: &'a Snapshot) -> Option<&'a PlayerSnapshot> {
+        let Self::Following(player) = self else {
+            return None;
+        };
+        snapshot.players.iter().find(|other| other.id == *player)
+    }
+
+    /// Describes the mode for a HUD.
+    pub fn label(&self, snapshot: &Snapshot) -> String {
+        match (self, self.followed(snapshot)) {
+            (Self::Free, _) => "Free camera".to_string(),
+            (Self::Following(_), Some(player)) => {
+                format!("Following {} ({:?})", player.name, player.team)
+            }
+            (Self::Following(_), None) => "Following nobody".to_string(),
+        }
+    }
+}
+
+/// Picks the player after `current` in `snapshot`, wrapping around, or the first one.
+fn next_player(snapshot: &Snapshot, current: Option<PlayerId>) -> Option<PlayerId> {
+    let players = snapshot
+        .players
+        .iter()
+        .map(|player| player.id)
+        .collect::<Vec<_>>();
+    let index = current
+        .and_then(|current| players.iter().position(|player| *player == current))
+        .map_or(0, |index| (index + 1) % players.len());
+    players.get(index).copied()
+}
+
+fn control_camera(
+    keyboard: Res<Input<KeyCode>>,
+    mut mode: ResMut<CameraMode>,
+    latest_snapshot: Res<LatestSnapshot>,
+    time: Res<Time>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    let snapshot = &latest_snapshot.0;
+    if keyboard.just_pressed(KeyCode::F) {
+        *mode = match *mode {
+            CameraMode::Free => {
+                next_player(snapshot, None).map_or(CameraMode::Free, CameraMode::Following)
+            }
+            CameraMode::Following(_) => CameraMode::Free,
+        };
+    }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        if let CameraMode::Following(player) = *mode {
+            if let Some(player) = next_player(snapshot, Some(player)) {
+                *mode = CameraMode::Following(player);
+            }
+        }
+    }
+
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+    let position = match *mode {
+        CameraMode::Following(player) => {
+            let Some(hero) = snapshot
+                .units
+                .iter()
+                .find(|unit| unit.hero && unit.owner == Some(player))
+            else {
+                return;
+            };
+            hero.position
+        }
+        CameraMode::Free => {
+            let mut direction = Vec2::ZERO;
+            for (key, offset) in [
+                (KeyCode::Left, Vec2::NEG_X),
+                (KeyCode::Right, Vec2::X),
+                (KeyCode::Down, Vec2::NEG_Y),
+                (KeyCode::Up, Vec2::Y),
+            ] {
+                if keyboard.pressed(key) {
+                    direction += offset;
+                }
+            }
+            transform.translation.truncate() + direction * CAMERA_PAN_SPEED * time.delta_seconds()
+        }
+    };
+    transform.translation = position.extend(transform.translation.z);
+}
+
+/// Centers the camera again once nobody controls it anymore.
+fn reset_camera(mut camera_query: Query<&mut Transform, With<Camera>>) {
+    for mut transform in &mut camera_query {
+        transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+    }
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(control_camera.run_if(resource_exists::<CameraMode>()))
+            .add_system(reset_camera.run_if(resource_removed::<CameraMode>()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use open_dota_server::{identity::Identity, player::Team};
+
+    #[test]
+    fn test_next_player_wraps() {
+        let player = |id| PlayerSnapshot {
+            id: PlayerId(id),
+            identity: Identity([id as u8; 32]),
+            name: format!("player{id}"),
+            team: Team::Radiant,
+            connected: true,
+        };
+        let snapshot = Snapshot {
+            players: vec![player(1), player(4)],
+            ..Default::default()
+        };
+        assert_eq!(next_player(&snapshot, None), Some(PlayerId(1)));
+        assert_eq!(next_player(&snapshot, Some(PlayerId(1))), Some(PlayerId(4)));
+        assert_eq!(next_player(&snapshot, Some(PlayerId(4))), Some(PlayerId(1)));
+        assert_eq!(next_player(&Snapshot::default(), None), None);
+    }
+}
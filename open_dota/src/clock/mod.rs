@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use open_dota_server::clock::DayPhase;
+
+use crate::{replay::format_time, units::LatestSnapshot, ClientState, FONT_PATH};
+
+const NIGHT_COLOR: Color = Color::rgb(0.08, 0.08, 0.16);
+
+#[derive(Component)]
+struct ClockHud;
+
+fn phase_color(phase: DayPhase) -> Color {
+    match phase {
+        DayPhase::Day => ClearColor::default().0,
+        DayPhase::Night => NIGHT_COLOR,
+    }
+}
+
+fn update_lighting(latest_snapshot: Res<LatestSnapshot>, mut clear_color: ResMut<ClearColor>) {
+    let color = phase_color(latest_snapshot.0.clock.phase);
+    if clear_color.0 != color {
+        clear_color.0 = color;
+    }
+}
+
+fn update_clock_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    latest_snapshot: Res<LatestSnapshot>,
+    mut hud_query: Query<&mut Text, With<ClockHud>>,
+) {
+    let clock = latest_snapshot.0.clock;
+    let text = format!("{} {:?}", format_time(clock.seconds), clock.phase);
+
+    if let Ok(mut hud) = hud_query.get_single_mut() {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text;
+        }
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size: 24.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                right: Val::Px(20.0),
+                top: Val::Px(20.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        ClockHud,
+    ));
+}
+
+fn cleanup_clock(
+    mut commands: Commands,
+    mut clear_color: ResMut<ClearColor>,
+    hud_query: Query<Entity, With<ClockHud>>,
+) {
+    for entity in &hud_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    clear_color.0 = phase_color(DayPhase::Day);
+}
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            update_lighting
+                .run_if(in_state(ClientState::InGame).or_else(in_state(ClientState::Replay))),
+        )
+        .add_system(
+            update_clock_hud
+                .run_if(in_state(ClientState::InGame).or_else(in_state(ClientState::Replay))),
+        )
+        .add_system(cleanup_clock.in_schedule(OnExit(ClientState::InGame)))
+        .add_system(cleanup_clock.in_schedule(OnExit(ClientState::Replay)));
+    }
+}
@@ -0,0 +1,263 @@
+use bevy::{asset::FileAssetIo, prelude::*, window::PrimaryWindow};
+use bevy_quinnet::client::Client;
+use serde::Deserialize;
+
+use open_dota_server::{
+    communication::{ChatWheelPhrase, PingKind},
+    player::PlayerId,
+    ClientMessage,
+};
+
+use crate::{ClientState, FONT_PATH};
+
+const CHAT_WHEEL_CONFIG_PATH: &str = "assets/config/chat_wheel.ron";
+pub const CHAT_WHEEL_KEY: KeyCode = KeyCode::Y;
+const CHAT_WHEEL_SLOT_KEYS: [KeyCode; 8] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+];
+const PING_MARKER_LIFETIME: f32 = 3.0;
+const PING_MARKER_SIZE: f32 = 24.0;
+
+#[derive(Resource, Debug, Clone, Deserialize)]
+pub struct ChatWheel {
+    pub phrases: Vec<ChatWheelPhrase>,
+}
+
+pub struct PingReceived {
+    pub sender: PlayerId,
+    pub kind: PingKind,
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+struct PingMarker {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct ChatWheelMenu;
+
+impl Default for ChatWheel {
+    fn default() -> Self {
+        Self {
+            phrases: vec![
+                ChatWheelPhrase::Okay,
+                ChatWheelPhrase::Careful,
+                ChatWheelPhrase::GetBack,
+                ChatWheelPhrase::NeedWards,
+                ChatWheelPhrase::Push,
+                ChatWheelPhrase::Help,
+                ChatWheelPhrase::OnMyWay,
+                ChatWheelPhrase::WellPlayed,
+            ],
+        }
+    }
+}
+
+impl ChatWheel {
+    fn load() -> Self {
+        let path = FileAssetIo::get_base_path().join(CHAT_WHEEL_CONFIG_PATH);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("failed to read chat wheel config {path:?}: {err}");
+                return Self::default();
+            }
+        };
+        match ron::from_str(&text) {
+            Ok(chat_wheel) => chat_wheel,
+            Err(err) => {
+                warn!("failed to parse chat wheel config {path:?}: {err}");
+                Self::default()
+            }
+        }
+    }
+}
+
+fn ping_color(kind: PingKind) -> Color {
+    match kind {
+        PingKind::Alert => Color::YELLOW,
+        PingKind::AttackHere => Color::RED,
+        PingKind::Retreat => Color::ORANGE,
+        PingKind::EnemyMissing => Color::FUCHSIA,
+    }
+}
+
+pub fn cursor_world_position(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor_position = window_query.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    camera
+        .viewport_to_world(camera_transform, cursor_position)
+        .map(|ray| ray.origin.truncate())
+}
+
+fn send_pings(
+    client: Res<Client>,
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    if !keyboard.any_pressed([KeyCode::LAlt, KeyCode::RAlt]) {
+        return;
+    }
+    let shift = keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    let kind = if mouse.just_pressed(MouseButton::Left) {
+        if shift {
+            PingKind::Retreat
+        } else {
+            PingKind::Alert
+        }
+    } else if mouse.just_pressed(MouseButton::Right) {
+        if shift {
+            PingKind::EnemyMissing
+        } else {
+            PingKind::AttackHere
+        }
+    } else {
+        return;
+    };
+    let Some(position) = cursor_world_position(&window_query, &camera_query) else {
+        return;
+    };
+    client
+        .connection()
+        .try_send_message(ClientMessage::Ping { kind, position });
+}
+
+fn spawn_ping_markers(mut commands: Commands, mut ping_events: EventReader<PingReceived>) {
+    for event in ping_events.iter() {
+        info!(
+            "Player {:?} pinged '{}' at {}",
+            event.sender,
+            event.kind.label(),
+            event.position
+        );
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: ping_color(event.kind),
+                    custom_size: Some(Vec2::splat(PING_MARKER_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(event.position.extend(10.0)),
+                ..Default::default()
+            },
+            PingMarker {
+                timer: Timer::from_seconds(PING_MARKER_LIFETIME, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn update_ping_markers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut marker_query: Query<(Entity, &mut PingMarker, &mut Sprite)>,
+) {
+    for (entity, mut marker, mut sprite) in &mut marker_query {
+        marker.timer.tick(time.delta());
+        if marker.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(marker.timer.percent_left());
+        }
+    }
+}
+
+fn chat_wheel_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keyboard: Res<Input<KeyCode>>,
+    chat_wheel: Res<ChatWheel>,
+    menu_query: Query<Entity, With<ChatWheelMenu>>,
+) {
+    if keyboard.just_released(CHAT_WHEEL_KEY) {
+        for entity in &menu_query {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    if !keyboard.just_pressed(CHAT_WHEEL_KEY) {
+        return;
+    }
+
+    let text_style = TextStyle {
+        font: asset_server.load(FONT_PATH),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(20.0),
+                        bottom: Val::Px(20.0),
+                        ..Default::default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..Default::default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                ..Default::default()
+            },
+            ChatWheelMenu,
+        ))
+        .with_children(|parent| {
+            for (slot, phrase) in chat_wheel
+                .phrases
+                .iter()
+                .take(CHAT_WHEEL_SLOT_KEYS.len())
+                .enumerate()
+            {
+                parent.spawn(TextBundle::from_section(
+                    format!("{}. {}", slot + 1, phrase.text()),
+                    text_style.clone(),
+                ));
+            }
+        });
+}
+
+fn send_chat_wheel(client: Res<Client>, keyboard: Res<Input<KeyCode>>, chat_wheel: Res<ChatWheel>) {
+    if !keyboard.pressed(CHAT_WHEEL_KEY) {
+        return;
+    }
+    for (key, phrase) in CHAT_WHEEL_SLOT_KEYS.iter().zip(chat_wheel.phrases.iter()) {
+        if keyboard.just_pressed(*key) {
+            client
+                .connection()
+                .try_send_message(ClientMessage::ChatWheel { phrase: *phrase });
+        }
+    }
+}
+
+pub struct CommunicationPlugin;
+
+impl Plugin for CommunicationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChatWheel::load())
+            .add_event::<PingReceived>()
+            .add_systems(
+                (
+                    send_pings,
+                    chat_wheel_menu,
+                    send_chat_wheel,
+                    spawn_ping_markers,
+                    update_ping_markers,
+                )
+                    .in_set(OnUpdate(ClientState::InGame)),
+            );
+    }
+}
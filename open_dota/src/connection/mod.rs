@@ -0,0 +1,358 @@
+use std::{net::SocketAddr, time::Duration};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    window::WindowCloseRequested,
+};
+use bevy_quinnet::{
+    client::{certificate::CertConnectionAbortEvent, connection::ConnectionLostEvent, Client},
+    shared::QuinnetError,
+};
+use futures_lite::future;
+use thiserror::Error;
+
+use open_dota_server::{identity::Challenge, ClientMessage};
+
+use crate::{identity::LocalIdentity, settings::ConnectionSettings, ClientState, FONT_PATH};
+
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 6;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+const LEAVE_FLUSH_DELAY: f32 = 0.2;
+
+#[derive(Resource, Debug)]
+pub struct Reconnect {
+    pub attempt: u32,
+    timer: Timer,
+}
+
+#[derive(Debug, Error)]
+pub enum ConnectError {
+    #[error("could not resolve server address '{0}:{1}'")]
+    UnresolvedAddress(String, u16),
+    #[error(transparent)]
+    Quinnet(#[from] QuinnetError),
+}
+
+/// Why the server turned us away, shown while disconnected.
+#[derive(Resource, Debug, Clone)]
+pub struct DisconnectReason(pub String);
+
+#[derive(Resource, Deref, DerefMut)]
+struct ConnectTimeout(Timer);
+
+/// The server address being looked up, after which the connection is opened.
+#[derive(Resource)]
+struct ResolvingAddress(Task<Option<SocketAddr>>);
+
+#[derive(Resource)]
+struct PendingExit {
+    window: Entity,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct ConnectionStatus;
+
+impl Reconnect {
+    fn new() -> Self {
+        Self {
+            attempt: 0,
+            timer: Timer::new(reconnect_delay(0), TimerMode::Once),
+        }
+    }
+}
+
+/// Doubles the delay with every failed attempt, capped at [`RECONNECT_MAX_DELAY`].
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+/// Looks up the server address off the main thread and opens the connection once it is known,
+/// replacing any open one.
+pub fn open_server_connection(commands: &mut Commands, settings: &ConnectionSettings) {
+    let task = AsyncComputeTaskPool::get().spawn(settings.resolve_server_address());
+    commands.insert_resource(ResolvingAddress(task));
+}
+
+fn connect_to(
+    client: &mut Client,
+    settings: &ConnectionSettings,
+    server_addr: Option<SocketAddr>,
+) -> Result<(), ConnectError> {
+    if client.get_default_connection().is_some() {
+        client.close_all_connections()?;
+    }
+    let server_addr = server_addr.ok_or_else(|| {
+        ConnectError::UnresolvedAddress(settings.server_address.clone(), settings.server_port)
+    })?;
+    client.open_connection(
+        settings.connection_configuration(server_addr),
+        settings.verification_mode(),
+    )?;
+    Ok(())
+}
+
+fn finish_resolving(
+    mut commands: Commands,
+    mut client: ResMut<Client>,
+    settings: Res<ConnectionSettings>,
+    mut resolving: ResMut<ResolvingAddress>,
+    state: Res<State<ClientState>>,
+    mut next_state: ResMut<NextState<ClientState>>,
+) {
+    let Some(server_addr) = future::block_on(future::poll_once(&mut resolving.0)) else {
+        return;
+    };
+    commands.remove_resource::<ResolvingAddress>();
+    if let Err(err) = connect_to(&mut client, &settings, server_addr) {
+        error!("Failed to open connection: {err}");
+        if state.0 == ClientState::Connecting {
+            commands.insert_resource(DisconnectReason(err.to_string()));
+            next_state.set(ClientState::Disconnected);
+        }
+    }
+}
+
+/// Answers the server's challenge, which joins the match or reclaims our slot in it.
+pub fn join_message(
+    settings: &ConnectionSettings,
+    identity: &LocalIdentity,
+    challenge: &Challenge,
+) -> ClientMessage {
+    ClientMessage::Join {
+        name: settings.player_name.clone(),
+        identity: identity.identity,
+        signature: challenge.sign(&identity.key),
+        password: settings.password.clone(),
+        role: settings.role,
+    }
+}
+
+fn start_connecting(mut commands: Commands) {
+    commands.remove_resource::<DisconnectReason>();
+    commands.insert_resource(ConnectTimeout(Timer::new(CONNECT_TIMEOUT, TimerMode::Once)));
+}
+
+fn connect_timeout(
+    mut timeout: ResMut<ConnectTimeout>,
+    mut next_state: ResMut<NextState<ClientState>>,
+    time: Res<Time>,
+) {
+    if timeout.tick(time.delta()).just_finished() {
+        warn!("Timed out connecting to server");
+        next_state.set(ClientState::Disconnected);
+    }
+}
+
+fn stop_connecting(mut commands: Commands) {
+    commands.remove_resource::<ConnectTimeout>();
+}
+
+fn detect_connection_lost(
+    mut connection_lost_events: EventReader<ConnectionLostEvent>,
+    state: Res<State<ClientState>>,
+    mut next_state: ResMut<NextState<ClientState>>,
+    pending_exit: Option<Res<PendingExit>>,
+) {
+    // The server may close the connection once it got our `Leave`.
+    if connection_lost_events.iter().count() == 0 || pending_exit.is_some() {
+        return;
+    }
+    match state.0 {
+        ClientState::InGame => {
+            warn!("Lost connection to server");
+            next_state.set(ClientState::Reconnecting);
+        }
+        ClientState::Connecting => next_state.set(ClientState::Disconnected),
+        ClientState::MainMenu
+        | ClientState::Reconnecting
+        | ClientState::Disconnected
+        | ClientState::Replay => (),
+    }
+}
+
+fn log_certificate_aborts(mut abort_events: EventReader<CertConnectionAbortEvent>) {
+    for event in abort_events.iter() {
+        warn!(
+            "Aborted connection, server certificate is {:?}: {:?}",
+            event.status, event.cert_info
+        );
+    }
+}
+
+fn start_reconnecting(mut commands: Commands) {
+    commands.insert_resource(Reconnect::new());
+}
+
+fn reconnect(
+    mut commands: Commands,
+    settings: Res<ConnectionSettings>,
+    mut reconnect: ResMut<Reconnect>,
+    mut next_state: ResMut<NextState<ClientState>>,
+    time: Res<Time>,
+) {
+    reconnect.timer.tick(time.delta());
+    if !reconnect.timer.finished() {
+        return;
+    }
+    if reconnect.attempt >= MAX_RECONNECT_ATTEMPTS {
+        warn!(
+            "Giving up after {} reconnection attempts",
+            reconnect.attempt
+        );
+        next_state.set(ClientState::Disconnected);
+        return;
+    }
+
+    reconnect.attempt += 1;
+    info!(
+        "Reconnecting to server (attempt {}/{MAX_RECONNECT_ATTEMPTS})",
+        reconnect.attempt
+    );
+    open_server_connection(&mut commands, &settings);
+    let delay = reconnect_delay(reconnect.attempt);
+    reconnect.timer = Timer::new(delay, TimerMode::Once);
+}
+
+fn stop_reconnecting(mut commands: Commands) {
+    commands.remove_resource::<Reconnect>();
+}
+
+fn retry_connection(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    mut client: ResMut<Client>,
+    mut next_state: ResMut<NextState<ClientState>>,
+) {
+    if keyboard.just_pressed(KeyCode::R) {
+        next_state.set(ClientState::Reconnecting);
+    } else if keyboard.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<ResolvingAddress>();
+        if client.get_default_connection().is_some() {
+            client.close_all_connections().ok();
+        }
+        next_state.set(ClientState::MainMenu);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_connection_status(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    state: Res<State<ClientState>>,
+    settings: Res<ConnectionSettings>,
+    reconnect: Option<Res<Reconnect>>,
+    reason: Option<Res<DisconnectReason>>,
+    status_query: Query<Entity, With<ConnectionStatus>>,
+    mut shown: Local<Option<(ClientState, u32, Option<String>)>>,
+) {
+    let attempt = reconnect.map_or(0, |reconnect| reconnect.attempt);
+    let reason = reason.map(|reason| reason.0.clone());
+    let status = (state.0.clone(), attempt, reason.clone());
+    if shown.as_ref() == Some(&status) {
+        return;
+    }
+    *shown = Some(status);
+    for entity in &status_query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let message = match state.0 {
+        ClientState::Connecting => format!(
+            "Connecting to {}:{}...",
+            settings.server_address, settings.server_port
+        ),
+        ClientState::Reconnecting if attempt > 0 => {
+            format!("Connection lost. Reconnecting (attempt {attempt}/{MAX_RECONNECT_ATTEMPTS})...")
+        }
+        ClientState::Reconnecting => "Connection lost. Reconnecting...".to_string(),
+        ClientState::Disconnected => format!(
+            "{}. Press R to retry or Escape for the main menu.",
+            reason.as_deref().unwrap_or("Disconnected from server")
+        ),
+        _ => return,
+    };
+    commands.spawn((
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size: 24.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(20.0),
+                top: Val::Px(20.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        ConnectionStatus,
+    ));
+}
+
+fn leave_on_window_close(
+    mut commands: Commands,
+    mut close_events: EventReader<WindowCloseRequested>,
+    client: Res<Client>,
+    pending_exit: Option<Res<PendingExit>>,
+) {
+    let Some(event) = close_events.iter().last() else {
+        return;
+    };
+    if pending_exit.is_some() {
+        return;
+    }
+    if let Some(connection) = client.get_connection() {
+        connection.try_send_message(ClientMessage::Leave);
+    }
+    // Give the connection a moment to flush `Leave` before closing it and the window, and with it
+    // the app.
+    commands.insert_resource(PendingExit {
+        window: event.window,
+        timer: Timer::from_seconds(LEAVE_FLUSH_DELAY, TimerMode::Once),
+    });
+}
+
+fn close_window(
+    mut commands: Commands,
+    mut client: ResMut<Client>,
+    mut pending_exit: ResMut<PendingExit>,
+    time: Res<Time>,
+) {
+    if !pending_exit.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    if client.get_default_connection().is_some() {
+        client.close_all_connections().ok();
+    }
+    commands.entity(pending_exit.window).despawn();
+}
+
+pub struct ConnectionPlugin;
+
+impl Plugin for ConnectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(detect_connection_lost)
+            .add_system(log_certificate_aborts)
+            .add_system(finish_resolving.run_if(resource_exists::<ResolvingAddress>()))
+            .add_system(update_connection_status)
+            .add_system(leave_on_window_close)
+            .add_system(close_window.run_if(resource_exists::<PendingExit>()))
+            .add_system(start_connecting.in_schedule(OnEnter(ClientState::Connecting)))
+            .add_system(connect_timeout.in_set(OnUpdate(ClientState::Connecting)))
+            .add_system(stop_connecting.in_schedule(OnExit(ClientState::Connecting)))
+            .add_system(start_reconnecting.in_schedule(OnEnter(ClientState::Reconnecting)))
+            .add_system(reconnect.in_set(OnUpdate(ClientState::Reconnecting)))
+            .add_system(stop_reconnecting.in_schedule(OnExit(ClientState::Reconnecting)))
+            .add_system(retry_connection.in_set(OnUpdate(ClientState::Disconnected)));
+    }
+}
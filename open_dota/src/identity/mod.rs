@@ -0,0 +1,74 @@
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+use bevy::prelude::*;
+use open_dota_server::identity::{decode_hex, encode_hex, generate_key, Identity, SigningKey};
+
+pub const IDENTITY_PATH: &str = "identity.key";
+
+/// This client's key pair. The secret key never leaves the machine, the server only ever sees
+/// the public [`Identity`] and signatures made with it.
+#[derive(Resource)]
+pub struct LocalIdentity {
+    pub key: SigningKey,
+    pub identity: Identity,
+}
+
+impl LocalIdentity {
+    /// Loads the secret key stored at `path`, generating and storing a new one if there is none.
+    pub fn load_or_generate(path: &Path) -> Self {
+        let key = match std::fs::read_to_string(path) {
+            Ok(text) => match decode_hex(&text) {
+                Ok(bytes) => Some(SigningKey::from_bytes(&bytes)),
+                Err(err) => {
+                    error!("ignoring malformed identity {path:?}: {err}");
+                    None
+                }
+            },
+            Err(err) => {
+                info!("no identity loaded from {path:?}: {err}");
+                None
+            }
+        };
+        let key = key.unwrap_or_else(|| {
+            let key = generate_key();
+            if path.exists() {
+                warn!("not overwriting {path:?}, the new identity only lasts for this session");
+            } else if let Err(err) = write_secret(path, &encode_hex(&key.to_bytes())) {
+                error!("failed to write identity {path:?}: {err}");
+            }
+            key
+        });
+        let identity = Identity::of(&key);
+        info!("Playing as {identity}");
+        Self { key, identity }
+    }
+}
+
+/// Creates `path` holding `secret`, readable only by the current user where that can be set.
+fn write_secret(path: &Path, secret: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(secret.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_is_kept_and_private() {
+        let path = std::env::temp_dir().join(format!("open_dota_identity_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let identity = LocalIdentity::load_or_generate(&path).identity;
+        assert_eq!(LocalIdentity::load_or_generate(&path).identity, identity);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
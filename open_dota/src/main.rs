@@ -1,54 +1,164 @@
+mod camera;
+mod clock;
+mod communication;
+mod connection;
+mod identity;
 mod main_menu;
+mod replay;
+mod respawn;
+mod runes;
+mod selection;
+mod settings;
+mod spectator;
+mod trees;
+mod units;
 
-use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 
 use bevy::prelude::*;
-use bevy_quinnet::client::{
-    certificate::CertificateVerificationMode, connection::ConnectionConfiguration, Client,
-    QuinnetClientPlugin,
+use bevy_quinnet::client::{Client, QuinnetClientPlugin};
+
+use open_dota_server::{
+    player::{PlayerId, Team},
+    ServerMessage,
 };
 
-use open_dota_server::{ClientMessage, ServerMessage};
+pub const FONT_PATH: &str = "fonts/Roboto-Regular.ttf";
 
 #[derive(States, Debug, Default, PartialEq, Eq, Hash, Clone)]
 pub enum ClientState {
     #[default]
     MainMenu,
+    Connecting,
     InGame,
+    Reconnecting,
+    Disconnected,
+    Replay,
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Session {
+    pub player_id: PlayerId,
+    pub team: Team,
 }
 
 fn main() {
     App::new()
         .add_state::<ClientState>()
-        .add_plugins(DefaultPlugins)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            close_when_requested: false,
+            ..Default::default()
+        }))
         .add_plugin(QuinnetClientPlugin::default())
+        .add_plugin(settings::SettingsPlugin)
+        .add_plugin(connection::ConnectionPlugin)
         .add_plugin(main_menu::MainMenuPlugin)
+        .add_plugin(communication::CommunicationPlugin)
+        .add_plugin(units::UnitsPlugin)
+        .add_plugin(selection::SelectionPlugin)
+        .add_plugin(runes::RunesPlugin)
+        .add_plugin(trees::TreesPlugin)
+        .add_plugin(respawn::RespawnPlugin)
+        .add_plugin(replay::ReplayPlugin)
+        .add_plugin(spectator::SpectatorPlugin)
+        .add_plugin(camera::CameraPlugin)
+        .add_plugin(clock::ClockPlugin)
         .add_startup_system(startup)
         .add_system(handle_server_messages)
         .run();
 }
 
-fn startup(mut commands: Commands, mut client: ResMut<Client>) {
+fn startup(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
-
-    client
-        .open_connection(
-            ConnectionConfiguration::from_ips(
-                IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-                6000,
-                IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-                0,
-            ),
-            CertificateVerificationMode::SkipVerification,
-        )
-        .unwrap();
 }
 
-fn handle_server_messages(mut client: ResMut<Client>) {
-    while let Ok(Some(message)) = client.connection_mut().receive_message::<ServerMessage>() {
+#[allow(clippy::too_many_arguments)]
+fn handle_server_messages(
+    mut commands: Commands,
+    mut client: ResMut<Client>,
+    settings: Res<settings::ConnectionSettings>,
+    identity: Res<identity::LocalIdentity>,
+    mut next_state: ResMut<NextState<ClientState>>,
+    mut snapshot_events: EventWriter<units::SnapshotReceived>,
+    mut ping_events: EventWriter<communication::PingReceived>,
+    mut respawn_timers: ResMut<respawn::RespawnTimers>,
+) {
+    let Some(connection) = client.get_connection_mut() else {
+        return;
+    };
+    while let Ok(Some(message)) = connection.receive_message::<ServerMessage>() {
         match message {
-            ServerMessage::InitClient => info!("Connected to server!"),
+            ServerMessage::Challenge(challenge) => connection
+                .try_send_message(connection::join_message(&settings, &identity, &challenge)),
+            ServerMessage::InitClient { player_id, team } => {
+                info!("Connected to server as {player_id:?} on {team:?}!");
+                commands.insert_resource(Session { player_id, team });
+                next_state.set(ClientState::InGame);
+            }
+            ServerMessage::InitSpectator { delay_secs } => {
+                info!("Spectating the match {delay_secs}s behind");
+                commands.remove_resource::<Session>();
+                commands.insert_resource(spectator::Spectating {
+                    delay: Duration::from_secs(delay_secs),
+                    view: Default::default(),
+                });
+                commands.init_resource::<camera::CameraMode>();
+                next_state.set(ClientState::InGame);
+            }
+            ServerMessage::JoinRejected { reason } => {
+                warn!("Server rejected our join: {reason:?}");
+                commands
+                    .insert_resource(connection::DisconnectReason(reason.message().to_string()));
+                next_state.set(ClientState::Disconnected);
+            }
+            ServerMessage::Kicked { reason } => {
+                warn!("Kicked from the server: {reason}");
+                commands.remove_resource::<Session>();
+                commands.insert_resource(connection::DisconnectReason(reason));
+                next_state.set(ClientState::Disconnected);
+            }
+            ServerMessage::Snapshot(snapshot) => {
+                snapshot_events.send(units::SnapshotReceived(snapshot))
+            }
+            ServerMessage::PlayerDisconnected { player } => {
+                info!("Player {player:?} disconnected")
+            }
+            ServerMessage::PlayerReconnected { player } => {
+                info!("Player {player:?} reconnected")
+            }
+            ServerMessage::PlayerAbandoned { player } => {
+                info!("Player {player:?} abandoned the match")
+            }
+            ServerMessage::BossKilled { killer, team, item } => match (killer, team) {
+                (Some(killer), Some(team)) => {
+                    info!("Player {killer:?} ({team:?}) killed the boss and got {item:?}")
+                }
+                _ => info!("The boss was killed"),
+            },
+            ServerMessage::HeroKilled {
+                player,
+                killer,
+                respawn_at,
+            } => match killer {
+                Some(killer) => info!(
+                    "Player {killer:?} killed the hero of {player:?}, respawning at {respawn_at}s"
+                ),
+                None => info!("The hero of {player:?} died, respawning at {respawn_at}s"),
+            },
+            ServerMessage::RespawnTimers { timers } => respawn_timers.0 = timers,
             ServerMessage::ChatMessage { message } => info!("Chat message: '{message}'"),
+            ServerMessage::ChatWheel { sender, phrase } => {
+                info!("Player {sender:?}: '{}'", phrase.text())
+            }
+            ServerMessage::Ping {
+                sender,
+                kind,
+                position,
+            } => ping_events.send(communication::PingReceived {
+                sender,
+                kind,
+                position,
+            }),
         }
     }
 }
@@ -1,39 +1,339 @@
 use bevy::prelude::*;
 
-use crate::ClientState;
+use open_dota_server::player::JoinRole;
+
+use crate::{
+    connection::open_server_connection,
+    settings::{ConnectionSettings, SettingsPath},
+    ClientState, Session, FONT_PATH,
+};
+
+const FIELD_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+const FOCUSED_FIELD_COLOR: Color = Color::rgb(0.35, 0.35, 0.45);
+const BUTTON_COLOR: Color = Color::rgb(0.15, 0.35, 0.15);
+
+#[derive(Component)]
+struct MainMenu;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum FormField {
+    Name,
+    Address,
+    Port,
+    Password,
+}
+
+#[derive(Component)]
+struct CertificateModeButton;
+
+/// Joins as a player, or as a spectator if it has [`JoinRole::Spectator`].
+#[derive(Component)]
+struct ConnectButton(JoinRole);
+
+#[derive(Component)]
+struct FormError;
+
+#[derive(Resource, Debug)]
+struct ConnectForm {
+    name: String,
+    address: String,
+    port: String,
+    password: String,
+    focused: Option<FormField>,
+    error: Option<String>,
+}
+
+impl ConnectForm {
+    fn field_mut(&mut self, field: FormField) -> &mut String {
+        match field {
+            FormField::Name => &mut self.name,
+            FormField::Address => &mut self.address,
+            FormField::Port => &mut self.port,
+            FormField::Password => &mut self.password,
+        }
+    }
+
+    /// The field's text as shown on screen, with the password masked.
+    fn display(&self, field: FormField) -> String {
+        match field {
+            FormField::Name => self.name.clone(),
+            FormField::Address => self.address.clone(),
+            FormField::Port => self.port.clone(),
+            FormField::Password => "*".repeat(self.password.chars().count()),
+        }
+    }
+}
+
+impl FormField {
+    fn next(self) -> Self {
+        match self {
+            Self::Name => Self::Address,
+            Self::Address => Self::Port,
+            Self::Port => Self::Password,
+            Self::Password => Self::Name,
+        }
+    }
+}
+
+fn setup_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<ConnectionSettings>,
+) {
+    commands.insert_resource(ConnectForm {
+        name: settings.player_name.clone(),
+        address: settings.server_address.clone(),
+        port: settings.server_port.to_string(),
+        password: settings.password.clone().unwrap_or_default(),
+        focused: None,
+        error: None,
+    });
+
+    let text_style = TextStyle {
+        font: asset_server.load(FONT_PATH),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+    let field_style = Style {
+        size: Size::new(Val::Percent(100.0), Val::Px(28.0)),
+        padding: UiRect::horizontal(Val::Px(4.0)),
+        margin: UiRect::bottom(Val::Px(8.0)),
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
 
-fn setup_main_menu(mut commands: Commands) {
     commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::width(Val::Percent(100.0)),
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::width(Val::Percent(100.0)),
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            ..Default::default()
-        })
+            MainMenu,
+        ))
         .with_children(|parent| {
             parent
                 .spawn(NodeBundle {
                     style: Style {
-                        size: Size::width(Val::Px(200.0)),
+                        size: Size::width(Val::Px(280.0)),
                         border: UiRect::all(Val::Px(2.0)),
+                        padding: UiRect::all(Val::Px(12.0)),
+                        flex_direction: FlexDirection::Column,
                         ..Default::default()
                     },
                     background_color: Color::rgb(0.65, 0.65, 0.65).into(),
                     ..Default::default()
                 })
                 .with_children(|parent| {
+                    for (label, field) in [
+                        ("Player name", FormField::Name),
+                        ("Server address", FormField::Address),
+                        ("Port", FormField::Port),
+                        ("Password (optional)", FormField::Password),
+                    ] {
+                        parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: field_style.clone(),
+                                    background_color: FIELD_COLOR.into(),
+                                    ..Default::default()
+                                },
+                                field,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section("", text_style.clone()));
+                            });
+                    }
+
+                    parent.spawn(TextBundle::from_section("Certificate", text_style.clone()));
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: field_style.clone(),
+                                background_color: FIELD_COLOR.into(),
+                                ..Default::default()
+                            },
+                            CertificateModeButton,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("", text_style.clone()));
+                        });
+
+                    for (label, role) in [
+                        ("Connect", JoinRole::Player),
+                        ("Spectate", JoinRole::Spectator),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        justify_content: JustifyContent::Center,
+                                        ..field_style.clone()
+                                    },
+                                    background_color: BUTTON_COLOR.into(),
+                                    ..Default::default()
+                                },
+                                ConnectButton(role),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                            });
+                    }
+
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                color: Color::MAROON,
+                                ..text_style.clone()
+                            },
+                        ),
+                        FormError,
+                    ));
                 });
         });
 }
 
-fn cleanup_main_menu() {}
+fn focus_fields(
+    mut form: ResMut<ConnectForm>,
+    mut settings: ResMut<ConnectionSettings>,
+    field_query: Query<(&Interaction, &FormField), Changed<Interaction>>,
+    certificate_query: Query<&Interaction, (Changed<Interaction>, With<CertificateModeButton>)>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    for (interaction, field) in &field_query {
+        if *interaction == Interaction::Clicked {
+            form.focused = Some(*field);
+        }
+    }
+    for interaction in &certificate_query {
+        if *interaction == Interaction::Clicked {
+            settings.certificate_mode = settings.certificate_mode.next();
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        form.focused = Some(form.focused.map_or(FormField::Name, FormField::next));
+    }
+}
+
+fn edit_fields(
+    mut form: ResMut<ConnectForm>,
+    mut character_events: EventReader<ReceivedCharacter>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let typed = character_events
+        .iter()
+        .map(|event| event.char)
+        .filter(|char| !char.is_control() && !char.is_whitespace())
+        .collect::<String>();
+    let erase = keyboard.just_pressed(KeyCode::Back);
+    let Some(field) = form.focused else {
+        return;
+    };
+    if typed.is_empty() && !erase {
+        return;
+    }
+    let text = form.field_mut(field);
+    text.push_str(&typed);
+    if erase {
+        text.pop();
+    }
+}
+
+fn update_form(
+    form: Res<ConnectForm>,
+    settings: Res<ConnectionSettings>,
+    mut field_query: Query<(&FormField, &mut BackgroundColor, &Children)>,
+    certificate_query: Query<&Children, With<CertificateModeButton>>,
+    mut error_query: Query<&mut Text, With<FormError>>,
+    mut text_query: Query<&mut Text, Without<FormError>>,
+) {
+    if !form.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for (field, mut background_color, children) in &mut field_query {
+        let focused = form.focused == Some(*field);
+        *background_color = if focused {
+            FOCUSED_FIELD_COLOR
+        } else {
+            FIELD_COLOR
+        }
+        .into();
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            let value = form.display(*field);
+            text.sections[0].value = if focused { format!("{value}|") } else { value };
+        }
+    }
+    for children in &certificate_query {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = settings.certificate_mode.label().to_string();
+        }
+    }
+    for mut text in &mut error_query {
+        text.sections[0].value = form.error.clone().unwrap_or_default();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn submit_form(
+    mut commands: Commands,
+    mut form: ResMut<ConnectForm>,
+    mut settings: ResMut<ConnectionSettings>,
+    settings_path: Res<SettingsPath>,
+    mut next_state: ResMut<NextState<ClientState>>,
+    button_query: Query<(&Interaction, &ConnectButton), Changed<Interaction>>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let clicked = button_query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Clicked)
+        .map(|(_, ConnectButton(role))| *role);
+    let Some(role) = clicked.or(keyboard
+        .just_pressed(KeyCode::Return)
+        .then_some(JoinRole::Player))
+    else {
+        return;
+    };
+
+    let Ok(server_port) = form.port.parse::<u16>() else {
+        form.error = Some(format!("'{}' is not a valid port", form.port));
+        return;
+    };
+    if form.name.is_empty() {
+        form.error = Some("Enter a player name".to_string());
+        return;
+    }
+    settings.player_name = form.name.clone();
+    settings.server_address = form.address.trim().to_string();
+    settings.server_port = server_port;
+    settings.password = Some(form.password.clone()).filter(|password| !password.is_empty());
+    settings.role = role;
+    open_server_connection(&mut commands, &settings);
+    settings.save(&settings_path.0);
+    commands.remove_resource::<Session>();
+    next_state.set(ClientState::Connecting);
+}
+
+fn cleanup_main_menu(mut commands: Commands, menu_query: Query<Entity, With<MainMenu>>) {
+    for entity in &menu_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<ConnectForm>();
+}
 
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(setup_main_menu.in_schedule(OnEnter(ClientState::MainMenu)))
+            .add_systems(
+                (focus_fields, edit_fields, update_form, submit_form)
+                    .chain()
+                    .in_set(OnUpdate(ClientState::MainMenu)),
+            )
             .add_system(cleanup_main_menu.in_schedule(OnExit(ClientState::MainMenu)));
     }
 }
@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use open_dota_server::{playback::ReplaySimulation, replay::Replay};
+
+use crate::{
+    camera::CameraMode,
+    units::{LatestSnapshot, SnapshotReceived},
+    ClientState, FONT_PATH,
+};
+
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+const SEEK_STEP_SECS: u64 = 10;
+
+/// Replay file given on the command line, played instead of showing the main menu.
+#[derive(Resource, Debug, Clone)]
+pub struct ReplayFile(pub PathBuf);
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    simulation: ReplaySimulation,
+    playing: bool,
+    speed: usize,
+    /// Match time owed to the simulation, in seconds.
+    pending: f32,
+}
+
+#[derive(Component)]
+struct ReplayHud;
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            simulation: ReplaySimulation::new(replay),
+            playing: true,
+            speed: NORMAL_SPEED,
+            pending: 0.0,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+
+    fn seconds(&self, tick: u64) -> u64 {
+        tick / self.simulation.tick_rate().0 as u64
+    }
+}
+
+pub fn format_time(seconds: u64) -> String {
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn open_replay_file(
+    mut commands: Commands,
+    replay_file: Option<Res<ReplayFile>>,
+    mut next_state: ResMut<NextState<ClientState>>,
+) {
+    let Some(replay_file) = replay_file else {
+        return;
+    };
+    let replay = match Replay::load(&replay_file.0) {
+        Ok(replay) => replay,
+        Err(err) => {
+            error!("Failed to open replay {:?}: {err}", replay_file.0);
+            return;
+        }
+    };
+    let playback = ReplayPlayback::new(replay);
+    info!(
+        "Playing replay {:?} ({})",
+        replay_file.0,
+        format_time(playback.seconds(playback.simulation.end_tick()))
+    );
+    commands.insert_resource(playback);
+    commands.insert_resource(CameraMode::Free);
+    next_state.set(ClientState::Replay);
+}
+
+fn control_playback(
+    keyboard: Res<Input<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut snapshot_events: EventWriter<SnapshotReceived>,
+    mut next_state: ResMut<NextState<ClientState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(ClientState::MainMenu);
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Space) {
+        playback.playing = !playback.playing;
+    }
+    if keyboard.just_pressed(KeyCode::RBracket) {
+        playback.speed = (playback.speed + 1).min(SPEEDS.len() - 1);
+    }
+    if keyboard.just_pressed(KeyCode::LBracket) {
+        playback.speed = playback.speed.saturating_sub(1);
+    }
+
+    let seek_step = SEEK_STEP_SECS * playback.simulation.tick_rate().0 as u64;
+    let tick = playback.simulation.tick();
+    let target = if keyboard.just_pressed(KeyCode::Comma) {
+        Some(tick.saturating_sub(seek_step))
+    } else if keyboard.just_pressed(KeyCode::Period) {
+        Some(tick + seek_step)
+    } else if keyboard.just_pressed(KeyCode::Home) {
+        Some(0)
+    } else {
+        None
+    };
+    if let Some(target) = target {
+        playback.simulation.seek(target);
+        playback.pending = 0.0;
+        snapshot_events.send(SnapshotReceived(playback.simulation.snapshot()));
+    }
+}
+
+fn advance_playback(
+    mut playback: ResMut<ReplayPlayback>,
+    mut snapshot_events: EventWriter<SnapshotReceived>,
+    time: Res<Time>,
+) {
+    if !playback.playing || playback.simulation.is_finished() {
+        return;
+    }
+    let tick_duration = playback.simulation.tick_rate().delta_seconds();
+    playback.pending += time.delta_seconds() * playback.speed();
+    let mut stepped = false;
+    while playback.pending >= tick_duration && !playback.simulation.is_finished() {
+        playback.pending -= tick_duration;
+        playback.simulation.step();
+        stepped = true;
+    }
+    if stepped {
+        snapshot_events.send(SnapshotReceived(playback.simulation.snapshot()));
+    }
+}
+
+fn update_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    playback: Res<ReplayPlayback>,
+    camera_mode: Res<CameraMode>,
+    latest_snapshot: Res<LatestSnapshot>,
+    mut hud_query: Query<&mut Text, With<ReplayHud>>,
+) {
+    let simulation = &playback.simulation;
+    let camera = camera_mode.label(&latest_snapshot.0);
+    let status = if simulation.is_finished() {
+        "Finished"
+    } else if playback.playing {
+        "Playing"
+    } else {
+        "Paused"
+    };
+    let mut text = format!(
+        "Replay {} / {}  {status} at {}x  {camera}\n\
+         Space play/pause, [ ] speed, , . seek, Home restart, F follow players, Tab next player, \
+         arrow keys pan, Escape quit",
+        format_time(playback.seconds(simulation.tick())),
+        format_time(playback.seconds(simulation.end_tick())),
+        playback.speed(),
+    );
+    if let Some(tick) = simulation.diverged_at() {
+        text += &format!(
+            "\nOut of sync with the recorded match since {}",
+            format_time(playback.seconds(tick))
+        );
+    }
+
+    if let Ok(mut hud) = hud_query.get_single_mut() {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text;
+        }
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(20.0),
+                top: Val::Px(20.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        ReplayHud,
+    ));
+}
+
+fn show_first_tick(
+    playback: Res<ReplayPlayback>,
+    mut snapshot_events: EventWriter<SnapshotReceived>,
+) {
+    snapshot_events.send(SnapshotReceived(playback.simulation.snapshot()));
+}
+
+fn close_replay(mut commands: Commands, hud_query: Query<Entity, With<ReplayHud>>) {
+    commands.remove_resource::<ReplayPlayback>();
+    commands.remove_resource::<ReplayFile>();
+    commands.remove_resource::<CameraMode>();
+    for entity in &hud_query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(open_replay_file)
+            .add_system(show_first_tick.in_schedule(OnEnter(ClientState::Replay)))
+            .add_systems(
+                (control_playback, advance_playback, update_hud)
+                    .chain()
+                    .in_set(OnUpdate(ClientState::Replay)),
+            )
+            .add_system(close_replay.in_schedule(OnExit(ClientState::Replay)));
+    }
+}
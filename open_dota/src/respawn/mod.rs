@@ -0,0 +1,172 @@
+//! Respawn timers of dead heroes, shown below the clock. F3 buys the local hero back while it is
+//! dead.
+
+use bevy::prelude::*;
+use bevy_quinnet::client::Client;
+
+use open_dota_server::{
+    death::RespawnTimer, order::Order, player::PlayerId, snapshot::Snapshot, ClientMessage,
+};
+
+use crate::{
+    units::{LatestSnapshot, OwnHero},
+    ClientState, Session, FONT_PATH,
+};
+
+const BUYBACK_KEY: KeyCode = KeyCode::F3;
+
+/// The respawn timers the server sent last.
+#[derive(Resource, Debug, Default)]
+pub struct RespawnTimers(pub Vec<RespawnTimer>);
+
+#[derive(Component)]
+struct RespawnHud;
+
+/// One line per dead hero with the seconds until it respawns.
+fn respawn_lines(timers: &[RespawnTimer], snapshot: &Snapshot, own: Option<PlayerId>) -> String {
+    timers
+        .iter()
+        .map(|timer| {
+            let name = snapshot
+                .players
+                .iter()
+                .find(|player| player.id == timer.player)
+                .map_or("?", |player| player.name.as_str());
+            let seconds = timer.respawn_at.saturating_sub(snapshot.clock.seconds);
+            if Some(timer.player) == own {
+                format!("{name} respawns in {seconds}s ({BUYBACK_KEY:?} to buy back)")
+            } else {
+                format!("{name} respawns in {seconds}s")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn update_respawn_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    timers: Res<RespawnTimers>,
+    latest_snapshot: Res<LatestSnapshot>,
+    session: Option<Res<Session>>,
+    mut hud_query: Query<&mut Text, With<RespawnHud>>,
+) {
+    let own = session.map(|session| session.player_id);
+    let text = respawn_lines(&timers.0, &latest_snapshot.0, own);
+
+    if let Ok(mut hud) = hud_query.get_single_mut() {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text;
+        }
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                right: Val::Px(20.0),
+                top: Val::Px(56.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        RespawnHud,
+    ));
+}
+
+fn send_buyback(
+    client: Res<Client>,
+    keyboard: Res<Input<KeyCode>>,
+    own_hero: Res<OwnHero>,
+    timers: Res<RespawnTimers>,
+    session: Option<Res<Session>>,
+) {
+    if !keyboard.just_pressed(BUYBACK_KEY) {
+        return;
+    }
+    let (Some(session), Some(hero)) = (session, own_hero.0) else {
+        return;
+    };
+    if !timers
+        .0
+        .iter()
+        .any(|timer| timer.player == session.player_id)
+    {
+        return;
+    }
+    client.connection().try_send_message(ClientMessage::Order {
+        unit: hero,
+        order: Order::Buyback,
+    });
+}
+
+fn cleanup_respawns(
+    mut commands: Commands,
+    mut timers: ResMut<RespawnTimers>,
+    hud_query: Query<Entity, With<RespawnHud>>,
+) {
+    for entity in &hud_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    timers.0.clear();
+}
+
+pub struct RespawnPlugin;
+
+impl Plugin for RespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnTimers>()
+            .add_systems((update_respawn_hud, send_buyback).in_set(OnUpdate(ClientState::InGame)))
+            .add_system(cleanup_respawns.in_schedule(OnExit(ClientState::InGame)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use open_dota_server::{
+        clock::GameClock, identity::Identity, player::Team, snapshot::PlayerSnapshot,
+    };
+
+    #[test]
+    fn test_respawn_lines() {
+        let player = |id, name: &str| PlayerSnapshot {
+            id: PlayerId(id),
+            identity: Identity([0; 32]),
+            name: name.to_string(),
+            team: Team::Radiant,
+            connected: true,
+        };
+        let snapshot = Snapshot {
+            players: vec![player(1, "alice"), player(2, "bob")],
+            clock: GameClock {
+                seconds: 30,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let timers = [
+            RespawnTimer {
+                player: PlayerId(1),
+                respawn_at: 42,
+            },
+            RespawnTimer {
+                player: PlayerId(2),
+                respawn_at: 20,
+            },
+        ];
+        assert_eq!(
+            respawn_lines(&timers, &snapshot, Some(PlayerId(2))),
+            "alice respawns in 12s\nbob respawns in 0s (F3 to buy back)"
+        );
+        assert_eq!(respawn_lines(&[], &snapshot, None), "");
+    }
+}
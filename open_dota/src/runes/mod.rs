@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use open_dota_server::rune::{RuneKind, RUNE_SPOTS};
+
+use crate::{units::LatestSnapshot, ClientState};
+
+const RUNE_SIZE: f32 = 14.0;
+
+/// The sprite of the rune at a rune spot.
+#[derive(Component)]
+struct RuneSprite(usize);
+
+/// The rune spot under `position`, if there is a rune on it.
+pub fn rune_at(runes: &[Option<RuneKind>], position: Vec2) -> Option<usize> {
+    RUNE_SPOTS.iter().zip(runes).position(|(spot, rune)| {
+        rune.is_some() && (*spot - position).abs().max_element() <= RUNE_SIZE / 2.0
+    })
+}
+
+fn rune_color(kind: RuneKind) -> Color {
+    match kind {
+        RuneKind::Bounty => Color::GOLD,
+        RuneKind::Haste => Color::ORANGE_RED,
+        RuneKind::DoubleDamage => Color::BLUE,
+        RuneKind::Regeneration => Color::LIME_GREEN,
+        RuneKind::Invisibility => Color::PURPLE,
+        RuneKind::Illusion => Color::YELLOW,
+    }
+}
+
+fn update_runes(
+    mut commands: Commands,
+    latest_snapshot: Res<LatestSnapshot>,
+    mut sprite_query: Query<(Entity, &RuneSprite, &mut Sprite)>,
+) {
+    if !latest_snapshot.is_changed() {
+        return;
+    }
+    let runes = latest_snapshot.0.runes;
+    let mut shown = [false; RUNE_SPOTS.len()];
+    for (entity, RuneSprite(spot), mut sprite) in &mut sprite_query {
+        match runes[*spot] {
+            Some(kind) => {
+                sprite.color = rune_color(kind);
+                shown[*spot] = true;
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    for (spot, kind) in runes.iter().enumerate() {
+        let Some(kind) = kind else {
+            continue;
+        };
+        if shown[spot] {
+            continue;
+        }
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: rune_color(*kind),
+                    custom_size: Some(Vec2::splat(RUNE_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(RUNE_SPOTS[spot].extend(0.5)),
+                ..Default::default()
+            },
+            RuneSprite(spot),
+        ));
+    }
+}
+
+fn cleanup_runes(mut commands: Commands, sprite_query: Query<Entity, With<RuneSprite>>) {
+    for entity in &sprite_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub struct RunesPlugin;
+
+impl Plugin for RunesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            update_runes
+                .run_if(in_state(ClientState::InGame).or_else(in_state(ClientState::Replay))),
+        )
+        .add_system(cleanup_runes.in_schedule(OnExit(ClientState::InGame)))
+        .add_system(cleanup_runes.in_schedule(OnExit(ClientState::Replay)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rune_at() {
+        let mut runes = [None; RUNE_SPOTS.len()];
+        runes[1] = Some(RuneKind::Bounty);
+        assert_eq!(rune_at(&runes, RUNE_SPOTS[1] + Vec2::splat(5.0)), Some(1));
+        assert_eq!(rune_at(&runes, RUNE_SPOTS[1] + Vec2::splat(10.0)), None);
+        assert_eq!(rune_at(&runes, RUNE_SPOTS[0]), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! The units the local player's orders go to. Clicking an own unit selects it, Shift+click adds or
+//! removes it. Ctrl+number saves the selection as a control group and the number recalls it, F1
+//! selects the hero, F2 every own unit and Tab cycles through them one at a time.
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use open_dota_server::{
+    player::{PlayerId, Team},
+    snapshot::Snapshot,
+    unit::UnitId,
+};
+
+use crate::{
+    communication::{cursor_world_position, CHAT_WHEEL_KEY},
+    units::{unit_at, LatestSnapshot, OwnHero},
+    ClientState, Session,
+};
+
+const CONTROL_GROUP_KEYS: [KeyCode; 10] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+];
+const SELECTION_MARKER_SIZE: f32 = 40.0;
+
+/// The selected units, which receive the player's orders.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Selection(pub Vec<UnitId>);
+
+#[derive(Resource, Debug, Default)]
+struct ControlGroups([Vec<UnitId>; CONTROL_GROUP_KEYS.len()]);
+
+/// The outline under a selected unit.
+#[derive(Component)]
+struct SelectionMarker(UnitId);
+
+/// The units in `snapshot` `player` of `team` can order, their hero first. Besides the units they
+/// own, that is their team's courier.
+pub fn controllable_units(snapshot: &Snapshot, player: PlayerId, team: Team) -> Vec<UnitId> {
+    let mut units = snapshot
+        .units
+        .iter()
+        .filter(|unit| unit.owner == Some(player) || unit.courier && unit.team == Some(team))
+        .collect::<Vec<_>>();
+    units.sort_by_key(|unit| !unit.hero);
+    units.into_iter().map(|unit| unit.id).collect()
+}
+
+/// Picks the unit after `current` in `units`, wrapping around, or the first one.
+fn next_unit(units: &[UnitId], current: Option<UnitId>) -> Option<UnitId> {
+    let index = current
+        .and_then(|current| units.iter().position(|unit| *unit == current))
+        .map_or(0, |index| (index + 1) % units.len());
+    units.get(index).copied()
+}
+
+/// Drops units that died or were lost from the selection and the control groups. An empty
+/// selection falls back to the hero.
+fn prune_selection(
+    latest_snapshot: Res<LatestSnapshot>,
+    session: Option<Res<Session>>,
+    own_hero: Res<OwnHero>,
+    mut selection: ResMut<Selection>,
+    mut groups: ResMut<ControlGroups>,
+) {
+    if !latest_snapshot.is_changed() {
+        return;
+    }
+    let Some(session) = session else {
+        return;
+    };
+    let controllable = controllable_units(&latest_snapshot.0, session.player_id, session.team);
+    selection.0.retain(|unit| controllable.contains(unit));
+    for group in &mut groups.0 {
+        group.retain(|unit| controllable.contains(unit));
+    }
+    if selection.0.is_empty() {
+        selection.0.extend(own_hero.0);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn select_units(
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    latest_snapshot: Res<LatestSnapshot>,
+    session: Option<Res<Session>>,
+    own_hero: Res<OwnHero>,
+    mut selection: ResMut<Selection>,
+    mut groups: ResMut<ControlGroups>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    // Alt+click is a ping.
+    if keyboard.any_pressed([KeyCode::LAlt, KeyCode::RAlt]) {
+        return;
+    }
+    let Some(session) = session else {
+        return;
+    };
+    let controllable = controllable_units(&latest_snapshot.0, session.player_id, session.team);
+
+    if mouse.just_pressed(MouseButton::Left) {
+        let clicked = cursor_world_position(&window_query, &camera_query)
+            .and_then(|position| unit_at(&latest_snapshot.0, position, &[]))
+            .filter(|unit| controllable.contains(unit));
+        if let Some(unit) = clicked {
+            if !keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+                selection.0 = vec![unit];
+            } else if let Some(index) = selection.0.iter().position(|other| *other == unit) {
+                selection.0.remove(index);
+            } else {
+                selection.0.push(unit);
+            }
+        }
+    }
+    if keyboard.just_pressed(KeyCode::F1) {
+        selection.0 = own_hero.0.into_iter().collect();
+    }
+    if keyboard.just_pressed(KeyCode::F2) {
+        selection.0 = controllable.clone();
+    }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        let current = selection.0.first().copied();
+        selection.0 = next_unit(&controllable, current).into_iter().collect();
+    }
+
+    // The number keys pick phrases while the chat wheel is open.
+    if keyboard.pressed(CHAT_WHEEL_KEY) {
+        return;
+    }
+    let control = keyboard.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    for (key, group) in CONTROL_GROUP_KEYS.iter().zip(&mut groups.0) {
+        if !keyboard.just_pressed(*key) {
+            continue;
+        }
+        if control {
+            *group = selection.0.clone();
+        } else if !group.is_empty() {
+            selection.0 = group.clone();
+        }
+    }
+}
+
+fn update_selection_markers(
+    mut commands: Commands,
+    selection: Res<Selection>,
+    latest_snapshot: Res<LatestSnapshot>,
+    mut marker_query: Query<(Entity, &SelectionMarker, &mut Transform)>,
+) {
+    let position = |unit_id: UnitId| {
+        latest_snapshot
+            .0
+            .units
+            .iter()
+            .find(|unit| unit.id == unit_id)
+            .map(|unit| unit.position)
+    };
+    let mut marked = Vec::new();
+    for (entity, SelectionMarker(unit), mut transform) in &mut marker_query {
+        match position(*unit).filter(|_| selection.0.contains(unit)) {
+            Some(position) => {
+                transform.translation = position.extend(transform.translation.z);
+                marked.push(*unit);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    for unit in &selection.0 {
+        let Some(position) = position(*unit).filter(|_| !marked.contains(unit)) else {
+            continue;
+        };
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: Some(Vec2::splat(SELECTION_MARKER_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position.extend(0.9)),
+                ..Default::default()
+            },
+            SelectionMarker(*unit),
+        ));
+    }
+}
+
+fn cleanup_selection(
+    mut commands: Commands,
+    mut selection: ResMut<Selection>,
+    mut groups: ResMut<ControlGroups>,
+    marker_query: Query<Entity, With<SelectionMarker>>,
+) {
+    for entity in &marker_query {
+        commands.entity(entity).despawn();
+    }
+    *selection = Selection::default();
+    *groups = ControlGroups::default();
+}
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .init_resource::<ControlGroups>()
+            .add_systems(
+                (prune_selection, select_units, update_selection_markers)
+                    .chain()
+                    .in_set(OnUpdate(ClientState::InGame)),
+            )
+            .add_system(cleanup_selection.in_schedule(OnExit(ClientState::InGame)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use open_dota_server::snapshot::UnitSnapshot;
+
+    #[test]
+    fn test_controllable_units_cycle_from_the_hero() {
+        let unit = |id, owner, hero| UnitSnapshot {
+            id: UnitId(id),
+            owner: Some(PlayerId(owner)),
+            team: Some(Team::Radiant),
+            hero,
+            position: Vec2::ZERO,
+            health: None,
+            inventory: None,
+            stash: None,
+            gold: None,
+            level: None,
+            courier: false,
+        };
+        let courier = |id, team| UnitSnapshot {
+            owner: None,
+            team: Some(team),
+            courier: true,
+            ..unit(id, 0, false)
+        };
+        let snapshot = Snapshot {
+            units: vec![
+                unit(1, 1, false),
+                unit(2, 2, true),
+                unit(3, 1, true),
+                unit(4, 1, false),
+                courier(5, Team::Radiant),
+                courier(6, Team::Dire),
+            ],
+            ..Default::default()
+        };
+        let units = controllable_units(&snapshot, PlayerId(1), Team::Radiant);
+        assert_eq!(units, [UnitId(3), UnitId(1), UnitId(4), UnitId(5)]);
+        assert_eq!(next_unit(&units, None), Some(UnitId(3)));
+        assert_eq!(next_unit(&units, Some(UnitId(3))), Some(UnitId(1)));
+        assert_eq!(next_unit(&units, Some(UnitId(4))), Some(UnitId(5)));
+        assert_eq!(next_unit(&units, Some(UnitId(5))), Some(UnitId(3)));
+        assert_eq!(next_unit(&units, Some(UnitId(2))), Some(UnitId(3)));
+        assert_eq!(next_unit(&[], None), None);
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+};
+
+use bevy::{asset::FileAssetIo, prelude::*};
+use bevy_quinnet::client::{
+    certificate::{
+        CertVerificationStatus, CertVerifierAction, CertVerifierBehaviour,
+        CertificateVerificationMode, KnownHosts, TrustOnFirstUseConfig,
+    },
+    connection::ConnectionConfiguration,
+};
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use open_dota_server::player::JoinRole;
+
+use crate::{
+    identity::{LocalIdentity, IDENTITY_PATH},
+    replay::ReplayFile,
+};
+
+const SETTINGS_PATH: &str = "settings.ron";
+const KNOWN_HOSTS_PATH: &str = "known_hosts";
+pub const DEFAULT_SERVER_PORT: u16 = 6000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum CertificateMode {
+    /// Accept any certificate the server presents.
+    #[default]
+    Skip,
+    /// Trust a server's certificate the first time it is seen and reject it if it changes later.
+    TrustOnFirstUse,
+    /// Only accept certificates signed by a trusted certificate authority.
+    CertificateAuthority,
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionSettings {
+    pub server_address: String,
+    pub server_port: u16,
+    pub certificate_mode: CertificateMode,
+    pub ca_file: Option<PathBuf>,
+    pub player_name: String,
+    /// Never written to the settings file.
+    #[serde(skip)]
+    pub password: Option<String>,
+    /// Picked in the main menu every time the client connects.
+    #[serde(skip)]
+    pub role: JoinRole,
+}
+
+#[derive(Parser, Debug, Default)]
+#[command(about = "Open Dota client")]
+pub struct Args {
+    /// Hostname or IP address of the server to connect to.
+    #[arg(long, env = "OPEN_DOTA_SERVER_ADDRESS")]
+    pub server_address: Option<String>,
+    /// UDP port of the server to connect to.
+    #[arg(long, env = "OPEN_DOTA_SERVER_PORT")]
+    pub server_port: Option<u16>,
+    /// How the server's TLS certificate is verified.
+    #[arg(long, value_enum, env = "OPEN_DOTA_CERTIFICATE_MODE")]
+    pub certificate_mode: Option<CertificateMode>,
+    /// PEM file with the certificate authorities to trust in `certificate-authority` mode.
+    #[arg(long, env = "OPEN_DOTA_CA_FILE")]
+    pub ca_file: Option<PathBuf>,
+    /// Name to join the server under.
+    #[arg(long, env = "OPEN_DOTA_PLAYER_NAME")]
+    pub player_name: Option<String>,
+    /// Password of the server, if it has one.
+    #[arg(long, env = "OPEN_DOTA_SERVER_PASSWORD")]
+    pub password: Option<String>,
+    /// Settings file to load and save connection settings to.
+    #[arg(long, env = "OPEN_DOTA_SETTINGS")]
+    pub settings: Option<PathBuf>,
+    /// File holding this client's secret key. Created on first launch.
+    #[arg(long, env = "OPEN_DOTA_IDENTITY")]
+    pub identity: Option<PathBuf>,
+    /// Replay file to watch instead of joining a server.
+    #[arg(long, env = "OPEN_DOTA_REPLAY")]
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SettingsPath(pub PathBuf);
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        Self {
+            server_address: Ipv4Addr::LOCALHOST.to_string(),
+            server_port: DEFAULT_SERVER_PORT,
+            certificate_mode: Default::default(),
+            ca_file: None,
+            player_name: "Player".to_string(),
+            password: None,
+            role: JoinRole::Player,
+        }
+    }
+}
+
+impl CertificateMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Skip => "Skip verification",
+            Self::TrustOnFirstUse => "Trust on first use",
+            Self::CertificateAuthority => "Certificate authority",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Skip => Self::TrustOnFirstUse,
+            Self::TrustOnFirstUse => Self::CertificateAuthority,
+            Self::CertificateAuthority => Self::Skip,
+        }
+    }
+}
+
+impl ConnectionSettings {
+    pub fn load(path: &Path) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                info!("no settings loaded from {path:?}: {err}");
+                return Self::default();
+            }
+        };
+        match ron::from_str(&text) {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!("failed to parse settings {path:?}: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) {
+        let text = match ron::ser::to_string_pretty(self, Default::default()) {
+            Ok(text) => text,
+            Err(err) => {
+                error!("failed to serialize settings: {err}");
+                return;
+            }
+        };
+        if let Err(err) = std::fs::write(path, text) {
+            error!("failed to write settings {path:?}: {err}");
+        }
+    }
+
+    /// Overrides the loaded settings with anything given on the command line or in the
+    /// environment.
+    pub fn apply_args(&mut self, args: &Args) {
+        if let Some(server_address) = &args.server_address {
+            self.server_address = server_address.clone();
+        }
+        if let Some(server_port) = args.server_port {
+            self.server_port = server_port;
+        }
+        if let Some(certificate_mode) = args.certificate_mode {
+            self.certificate_mode = certificate_mode;
+        }
+        if let Some(ca_file) = &args.ca_file {
+            self.ca_file = Some(ca_file.clone());
+        }
+        if let Some(player_name) = &args.player_name {
+            self.player_name = player_name.clone();
+        }
+        if let Some(password) = &args.password {
+            self.password = Some(password.clone());
+        }
+    }
+
+    /// Looks up the server address, which may block on DNS and so is meant to run on the
+    /// [`AsyncComputeTaskPool`](bevy::tasks::AsyncComputeTaskPool).
+    pub fn resolve_server_address(&self) -> impl Future<Output = Option<SocketAddr>> {
+        let (address, port) = (self.server_address.clone(), self.server_port);
+        async move {
+            if let Ok(ip) = address.parse::<IpAddr>() {
+                return Some(SocketAddr::new(ip, port));
+            }
+            (address.as_str(), port).to_socket_addrs().ok()?.next()
+        }
+    }
+
+    pub fn connection_configuration(&self, server_addr: SocketAddr) -> ConnectionConfiguration {
+        ConnectionConfiguration::from_addrs_with_name(
+            server_addr,
+            self.server_address.clone(),
+            local_bind_addr(server_addr),
+        )
+    }
+
+    pub fn verification_mode(&self) -> CertificateVerificationMode {
+        match self.certificate_mode {
+            CertificateMode::Skip => CertificateVerificationMode::SkipVerification,
+            CertificateMode::TrustOnFirstUse => {
+                let known_hosts = FileAssetIo::get_base_path().join(KNOWN_HOSTS_PATH);
+                CertificateVerificationMode::TrustOnFirstUse(TrustOnFirstUseConfig {
+                    known_hosts: KnownHosts::HostsFile(known_hosts.to_string_lossy().into_owned()),
+                    verifier_behaviour: HashMap::from([
+                        (
+                            CertVerificationStatus::UnknownCertificate,
+                            CertVerifierBehaviour::ImmediateAction(
+                                CertVerifierAction::TrustAndStore,
+                            ),
+                        ),
+                        (
+                            CertVerificationStatus::UntrustedCertificate,
+                            CertVerifierBehaviour::ImmediateAction(
+                                CertVerifierAction::AbortConnection,
+                            ),
+                        ),
+                        (
+                            CertVerificationStatus::TrustedCertificate,
+                            CertVerifierBehaviour::ImmediateAction(CertVerifierAction::TrustOnce),
+                        ),
+                    ]),
+                })
+            }
+            CertificateMode::CertificateAuthority => {
+                CertificateVerificationMode::SignedByCertificateAuthority
+            }
+        }
+    }
+
+    /// Quinnet only verifies against the platform's native roots, which honour `SSL_CERT_FILE`.
+    /// Set once while the app is built, before any connection could be reading the environment.
+    fn trust_ca_file(&self) {
+        if let Some(ca_file) = &self.ca_file {
+            std::env::set_var("SSL_CERT_FILE", ca_file);
+        }
+    }
+}
+
+/// Any local address of the same family as `server_addr`.
+fn local_bind_addr(server_addr: SocketAddr) -> SocketAddr {
+    let ip = match server_addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    SocketAddr::new(ip, 0)
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let args = Args::parse();
+        let path = args
+            .settings
+            .clone()
+            .unwrap_or_else(|| FileAssetIo::get_base_path().join(SETTINGS_PATH));
+        let mut settings = ConnectionSettings::load(&path);
+        settings.apply_args(&args);
+        settings.trust_ca_file();
+        let identity_path = args
+            .identity
+            .clone()
+            .unwrap_or_else(|| FileAssetIo::get_base_path().join(IDENTITY_PATH));
+        app.insert_resource(settings)
+            .insert_resource(SettingsPath(path))
+            .insert_resource(LocalIdentity::load_or_generate(&identity_path));
+        if let Some(replay) = args.replay {
+            app.insert_resource(ReplayFile(replay));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::future;
+
+    fn settings(server_address: &str) -> ConnectionSettings {
+        ConnectionSettings {
+            server_address: server_address.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_ip_addresses() {
+        let v4 = future::block_on(settings("10.0.0.1").resolve_server_address()).unwrap();
+        assert_eq!(v4, "10.0.0.1:6000".parse().unwrap());
+        assert_eq!(local_bind_addr(v4), "0.0.0.0:0".parse().unwrap());
+
+        let v6 = future::block_on(settings("::1").resolve_server_address()).unwrap();
+        assert_eq!(v6, "[::1]:6000".parse().unwrap());
+        assert_eq!(local_bind_addr(v6), "[::]:0".parse().unwrap());
+
+        assert_eq!(
+            future::block_on(settings("").resolve_server_address()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_verification_mode_follows_certificate_mode() {
+        let mut settings = settings("127.0.0.1");
+        assert!(matches!(
+            settings.verification_mode(),
+            CertificateVerificationMode::SkipVerification
+        ));
+        settings.certificate_mode = settings.certificate_mode.next();
+        assert!(matches!(
+            settings.verification_mode(),
+            CertificateVerificationMode::TrustOnFirstUse(_)
+        ));
+        settings.certificate_mode = settings.certificate_mode.next();
+        assert!(matches!(
+            settings.verification_mode(),
+            CertificateVerificationMode::SignedByCertificateAuthority
+        ));
+        assert_eq!(settings.certificate_mode.next(), CertificateMode::Skip);
+    }
+}
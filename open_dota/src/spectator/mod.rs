@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_quinnet::client::Client;
+
+use open_dota_server::{player::Team, snapshot::Snapshot, spectator::SpectatorView, ClientMessage};
+
+use crate::{
+    camera::CameraMode, replay::format_time, units::LatestSnapshot, ClientState, FONT_PATH,
+};
+
+const VIEW_KEY: KeyCode = KeyCode::V;
+
+/// Present while watching a match as a spectator.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Spectating {
+    /// How far behind the match the snapshots are.
+    pub delay: Duration,
+    /// Whose vision the snapshots show.
+    pub view: SpectatorView,
+}
+
+#[derive(Component)]
+struct SpectatorHud;
+
+fn team_roster(snapshot: &Snapshot, team: Team) -> String {
+    let names = snapshot
+        .players
+        .iter()
+        .filter(|player| player.team == team)
+        .map(|player| {
+            if player.connected {
+                player.name.clone()
+            } else {
+                format!("{} (disconnected)", player.name)
+            }
+        })
+        .collect::<Vec<_>>();
+    format!("{team:?}: {}", names.join(", "))
+}
+
+fn view_label(view: SpectatorView) -> String {
+    match view {
+        SpectatorView::Full => "full vision".to_string(),
+        SpectatorView::Team(team) => format!("{team:?} vision"),
+    }
+}
+
+/// The view after `view` in [`SpectatorView::ALL`], wrapping around.
+fn next_view(view: SpectatorView) -> SpectatorView {
+    let index = SpectatorView::ALL
+        .iter()
+        .position(|candidate| *candidate == view)
+        .map_or(0, |index| (index + 1) % SpectatorView::ALL.len());
+    SpectatorView::ALL[index]
+}
+
+fn switch_view(
+    client: Res<Client>,
+    keyboard: Res<Input<KeyCode>>,
+    mut spectating: ResMut<Spectating>,
+) {
+    if !keyboard.just_pressed(VIEW_KEY) {
+        return;
+    }
+    spectating.view = next_view(spectating.view);
+    client
+        .connection()
+        .try_send_message(ClientMessage::SpectatorView {
+            view: spectating.view,
+        });
+}
+
+fn update_spectator_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    spectating: Res<Spectating>,
+    camera_mode: Res<CameraMode>,
+    latest_snapshot: Res<LatestSnapshot>,
+    mut hud_query: Query<&mut Text, With<SpectatorHud>>,
+) {
+    let snapshot = &latest_snapshot.0;
+    let text = format!(
+        "Spectating {} behind the match with {}  {}\n{}\n{}\n\
+         F follow players, Tab next player, arrow keys pan, {VIEW_KEY:?} switch vision",
+        format_time(spectating.delay.as_secs()),
+        view_label(spectating.view),
+        camera_mode.label(snapshot),
+        team_roster(snapshot, Team::Radiant),
+        team_roster(snapshot, Team::Dire),
+    );
+
+    if let Ok(mut hud) = hud_query.get_single_mut() {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text;
+        }
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(20.0),
+                top: Val::Px(20.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        }),
+        SpectatorHud,
+    ));
+}
+
+fn cleanup_spectator_hud(mut commands: Commands, hud_query: Query<Entity, With<SpectatorHud>>) {
+    for entity in &hud_query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn stop_spectating(mut commands: Commands, spectating: Option<Res<Spectating>>) {
+    if spectating.is_some() {
+        commands.remove_resource::<Spectating>();
+        commands.remove_resource::<CameraMode>();
+    }
+}
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                switch_view.run_if(resource_exists::<Spectating>()),
+                update_spectator_hud.run_if(resource_exists::<Spectating>()),
+            )
+                .chain()
+                .in_set(OnUpdate(ClientState::InGame)),
+        )
+        .add_system(cleanup_spectator_hud.in_schedule(OnExit(ClientState::InGame)))
+        .add_system(stop_spectating.in_schedule(OnEnter(ClientState::MainMenu)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_view_wraps() {
+        assert_eq!(
+            next_view(SpectatorView::Full),
+            SpectatorView::Team(Team::Radiant)
+        );
+        assert_eq!(
+            next_view(SpectatorView::Team(Team::Radiant)),
+            SpectatorView::Team(Team::Dire)
+        );
+        assert_eq!(
+            next_view(SpectatorView::Team(Team::Dire)),
+            SpectatorView::Full
+        );
+    }
+}
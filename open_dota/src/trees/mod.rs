@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+use open_dota_server::tree::Forest;
+
+use crate::{units::LatestSnapshot, ClientState};
+
+const TREE_COLOR: Color = Color::rgb(0.1, 0.4, 0.15);
+
+/// The sprite of the tree with this index in the [`Forest`].
+#[derive(Component)]
+struct TreeSprite(usize);
+
+/// Spawns a sprite for every tree of the built-in forest, then shows the ones the latest snapshot
+/// says stand.
+fn update_trees(
+    mut commands: Commands,
+    latest_snapshot: Res<LatestSnapshot>,
+    mut forest: ResMut<Forest>,
+    mut sprite_query: Query<(&TreeSprite, &mut Visibility)>,
+) {
+    if !latest_snapshot.is_changed() {
+        return;
+    }
+    if forest.standing != latest_snapshot.0.trees {
+        forest.standing = latest_snapshot.0.trees.clone();
+    }
+    if sprite_query.is_empty() {
+        for (index, position) in forest.trees.iter().enumerate() {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: TREE_COLOR,
+                        custom_size: Some(Vec2::splat(forest.radius * 2.0)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.4)),
+                    visibility: tree_visibility(forest.standing.get(index)),
+                    ..Default::default()
+                },
+                TreeSprite(index),
+            ));
+        }
+        return;
+    }
+    for (TreeSprite(index), mut visibility) in &mut sprite_query {
+        let standing = tree_visibility(forest.standing.get(*index));
+        if *visibility != standing {
+            *visibility = standing;
+        }
+    }
+}
+
+fn tree_visibility(standing: bool) -> Visibility {
+    if standing {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+fn cleanup_trees(mut commands: Commands, sprite_query: Query<Entity, With<TreeSprite>>) {
+    for entity in &sprite_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub struct TreesPlugin;
+
+impl Plugin for TreesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Forest::builtin())
+            .add_system(
+                update_trees
+                    .run_if(in_state(ClientState::InGame).or_else(in_state(ClientState::Replay))),
+            )
+            .add_system(cleanup_trees.in_schedule(OnExit(ClientState::InGame)))
+            .add_system(cleanup_trees.in_schedule(OnExit(ClientState::Replay)));
+    }
+}
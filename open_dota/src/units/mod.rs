@@ -0,0 +1,304 @@
+use bevy::{prelude::*, utils::HashMap, window::PrimaryWindow};
+use bevy_quinnet::client::Client;
+
+use open_dota_server::{
+    order::Order,
+    player::Team,
+    snapshot::{Snapshot, UnitSnapshot},
+    tree::Forest,
+    unit::UnitId,
+    ClientMessage,
+};
+
+use crate::{
+    communication::cursor_world_position, runes::rune_at, selection::Selection, ClientState,
+    Session,
+};
+
+const HERO_SIZE: f32 = 32.0;
+const UNIT_SIZE: f32 = 20.0;
+const HEALTH_BAR_HEIGHT: f32 = 4.0;
+
+pub struct SnapshotReceived(pub Snapshot);
+
+#[derive(Resource, Debug, Default)]
+pub struct UnitEntities(HashMap<UnitId, Entity>);
+
+/// The hero of the local player, once a snapshot has shown it.
+#[derive(Resource, Debug, Default)]
+pub struct OwnHero(pub Option<UnitId>);
+
+/// The last snapshot applied, from the server or a replay.
+#[derive(Resource, Debug, Default)]
+pub struct LatestSnapshot(pub Snapshot);
+
+#[derive(Component)]
+pub struct NetworkedUnit;
+
+#[derive(Component)]
+struct HealthBar;
+
+fn unit_size(unit: &UnitSnapshot) -> f32 {
+    if unit.hero {
+        HERO_SIZE
+    } else {
+        UNIT_SIZE
+    }
+}
+
+/// The unit drawn under `position`, other than those in `exclude`.
+pub fn unit_at(snapshot: &Snapshot, position: Vec2, exclude: &[UnitId]) -> Option<UnitId> {
+    snapshot
+        .units
+        .iter()
+        .filter(|unit| !exclude.contains(&unit.id))
+        .find(|unit| (unit.position - position).abs().max_element() <= unit_size(unit) / 2.0)
+        .map(|unit| unit.id)
+}
+
+fn unit_color(unit: &UnitSnapshot) -> Color {
+    match unit.team {
+        Some(Team::Radiant) => Color::GREEN,
+        Some(Team::Dire) => Color::RED,
+        None => Color::GRAY,
+    }
+}
+
+fn apply_snapshots(
+    mut commands: Commands,
+    mut snapshot_events: EventReader<SnapshotReceived>,
+    mut unit_entities: ResMut<UnitEntities>,
+    mut own_hero: ResMut<OwnHero>,
+    mut latest_snapshot: ResMut<LatestSnapshot>,
+    session: Option<Res<Session>>,
+    mut transform_query: Query<&mut Transform, With<NetworkedUnit>>,
+) {
+    let Some(SnapshotReceived(snapshot)) = snapshot_events.iter().last() else {
+        return;
+    };
+    latest_snapshot.0 = snapshot.clone();
+
+    own_hero.0 = session.and_then(|session| {
+        snapshot
+            .units
+            .iter()
+            .find(|unit| unit.hero && unit.owner == Some(session.player_id))
+            .map(|unit| unit.id)
+    });
+
+    let mut stale = unit_entities.0.clone();
+    for unit in &snapshot.units {
+        stale.remove(&unit.id);
+        if let Some(entity) = unit_entities.0.get(&unit.id) {
+            if let Ok(mut transform) = transform_query.get_mut(*entity) {
+                transform.translation = unit.position.extend(transform.translation.z);
+            }
+            continue;
+        }
+        let size = unit_size(unit);
+        let mut entity = commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: unit_color(unit),
+                    custom_size: Some(Vec2::splat(size)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(unit.position.extend(1.0)),
+                ..Default::default()
+            },
+            NetworkedUnit,
+        ));
+        if unit.health.is_some() {
+            entity.with_children(|parent| {
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::YELLOW,
+                            custom_size: Some(Vec2::new(size, HEALTH_BAR_HEIGHT)),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(0.0, size / 2.0 + HEALTH_BAR_HEIGHT, 0.1),
+                        ..Default::default()
+                    },
+                    HealthBar,
+                ));
+            });
+        }
+        unit_entities.0.insert(unit.id, entity.id());
+    }
+    for (unit_id, entity) in stale {
+        unit_entities.0.remove(&unit_id);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_health_bars(
+    latest_snapshot: Res<LatestSnapshot>,
+    unit_entities: Res<UnitEntities>,
+    mut bar_query: Query<(&Parent, &mut Transform), With<HealthBar>>,
+) {
+    if !latest_snapshot.is_changed() {
+        return;
+    }
+    let health = latest_snapshot
+        .0
+        .units
+        .iter()
+        .filter_map(|unit| {
+            let (current, max) = unit.health?;
+            Some((*unit_entities.0.get(&unit.id)?, current / max))
+        })
+        .collect::<HashMap<_, _>>();
+    for (parent, mut transform) in &mut bar_query {
+        if let Some(fraction) = health.get(&parent.get()) {
+            transform.scale.x = fraction.clamp(0.0, 1.0);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn send_orders(
+    client: Res<Client>,
+    selection: Res<Selection>,
+    latest_snapshot: Res<LatestSnapshot>,
+    forest: Res<Forest>,
+    session: Option<Res<Session>>,
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    // Alt+click is a ping.
+    if keyboard.any_pressed([KeyCode::LAlt, KeyCode::RAlt]) {
+        return;
+    }
+    // C sends the team's courier off with our items, Shift+C back home, V makes it speed up.
+    if keyboard.any_just_pressed([KeyCode::C, KeyCode::V]) {
+        let Some(session) = session else {
+            return;
+        };
+        let Some(courier) = latest_snapshot
+            .0
+            .units
+            .iter()
+            .find(|unit| unit.courier && unit.team == Some(session.team))
+        else {
+            return;
+        };
+        let order = if keyboard.just_pressed(KeyCode::V) {
+            Order::SpeedBurst
+        } else if keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+            Order::ReturnToBase
+        } else {
+            Order::Deliver
+        };
+        client.connection().try_send_message(ClientMessage::Order {
+            unit: courier.id,
+            order,
+        });
+        return;
+    }
+    if selection.0.is_empty() {
+        return;
+    }
+    let order = if mouse.just_pressed(MouseButton::Right) {
+        let Some(target) = cursor_world_position(&window_query, &camera_query) else {
+            return;
+        };
+        if let Some(target) = unit_at(&latest_snapshot.0, target, &selection.0) {
+            Order::Attack { target }
+        } else if let Some(spot) = rune_at(&latest_snapshot.0.runes, target) {
+            Order::PickUpRune { spot }
+        } else if let Some(tree) = forest.tree_at(target) {
+            Order::CutTree { tree }
+        } else {
+            Order::Move { target }
+        }
+    } else if keyboard.just_pressed(KeyCode::S) {
+        Order::Stop
+    } else if keyboard.just_pressed(KeyCode::B) {
+        Order::UseBottle
+    } else {
+        return;
+    };
+    for unit in &selection.0 {
+        client
+            .connection()
+            .try_send_message(ClientMessage::Order { unit: *unit, order });
+    }
+}
+
+fn cleanup_units(
+    mut commands: Commands,
+    mut unit_entities: ResMut<UnitEntities>,
+    mut own_hero: ResMut<OwnHero>,
+    mut latest_snapshot: ResMut<LatestSnapshot>,
+) {
+    for (_, entity) in unit_entities.0.drain() {
+        commands.entity(entity).despawn_recursive();
+    }
+    own_hero.0 = None;
+    latest_snapshot.0 = Snapshot::default();
+}
+
+pub struct UnitsPlugin;
+
+impl Plugin for UnitsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UnitEntities>()
+            .init_resource::<OwnHero>()
+            .init_resource::<LatestSnapshot>()
+            .add_event::<SnapshotReceived>()
+            .add_system(
+                apply_snapshots
+                    .run_if(in_state(ClientState::InGame).or_else(in_state(ClientState::Replay))),
+            )
+            .add_system(update_health_bars.after(apply_snapshots))
+            .add_system(send_orders.in_set(OnUpdate(ClientState::InGame)))
+            .add_system(cleanup_units.in_schedule(OnExit(ClientState::InGame)))
+            .add_system(cleanup_units.in_schedule(OnExit(ClientState::Replay)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_at() {
+        let unit = |id, hero, position| UnitSnapshot {
+            id: UnitId(id),
+            owner: None,
+            team: None,
+            hero,
+            position,
+            health: None,
+            inventory: None,
+            gold: None,
+            stash: None,
+            level: None,
+            courier: false,
+        };
+        let snapshot = Snapshot {
+            players: Vec::new(),
+            units: vec![
+                unit(1, true, Vec2::ZERO),
+                unit(2, false, Vec2::new(100.0, 0.0)),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            unit_at(&snapshot, Vec2::new(12.0, -12.0), &[]),
+            Some(UnitId(1))
+        );
+        assert_eq!(
+            unit_at(&snapshot, Vec2::new(12.0, -12.0), &[UnitId(1)]),
+            None
+        );
+        assert_eq!(
+            unit_at(&snapshot, Vec2::new(108.0, 0.0), &[]),
+            Some(UnitId(2))
+        );
+        assert_eq!(unit_at(&snapshot, Vec2::new(112.0, 0.0), &[]), None);
+    }
+}
@@ -0,0 +1,377 @@
+mod stats;
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{AppExit, ScheduleRunnerSettings},
+    prelude::*,
+};
+use bevy_quinnet::client::{
+    certificate::CertificateVerificationMode,
+    connection::{ConnectionConfiguration, ConnectionId, ConnectionLostEvent},
+    Client, QuinnetClientPlugin,
+};
+use clap::Parser;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use open_dota_server::{
+    identity::{generate_key, Identity, SigningKey},
+    order::Order,
+    player::{JoinRole, PlayerId},
+    unit::{UnitId, MAP_HALF_SIZE},
+    ClientMessage, ServerMessage,
+};
+
+use stats::Stats;
+
+const TICK: Duration = Duration::from_millis(16);
+/// Share of random orders that are `Stop` rather than `Move`.
+const STOP_CHANCE: f64 = 0.1;
+/// How long a leaving bot keeps its connection open, so that `Leave` reaches the server.
+const LEAVE_FLUSH_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Parser, Debug, Resource)]
+#[command(about = "Simulated clients for load testing open_dota_server")]
+struct Args {
+    /// Address of the server to connect to.
+    #[arg(long, default_value = "127.0.0.1:6000")]
+    server: SocketAddr,
+    /// Number of bots to run.
+    #[arg(long, default_value_t = 10)]
+    clients: usize,
+    /// Seconds between bots connecting.
+    #[arg(long, default_value_t = 0.1)]
+    spawn_interval: f32,
+    /// Seconds a bot waits to be admitted before giving up.
+    #[arg(long, default_value_t = 10.0)]
+    join_timeout: f32,
+    /// Seconds each bot stays in the match before leaving.
+    #[arg(long, default_value_t = 30.0)]
+    lifetime: f32,
+    /// Seconds between chat messages of a bot.
+    #[arg(long, default_value_t = 2.0)]
+    chat_interval: f32,
+    /// Seconds between orders of a bot.
+    #[arg(long, default_value_t = 0.5)]
+    order_interval: f32,
+    #[arg(long)]
+    password: Option<String>,
+    /// Seed for the bots' random orders.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BotState {
+    Connecting {
+        since: Instant,
+    },
+    Joining {
+        since: Instant,
+    },
+    InGame {
+        since: Instant,
+    },
+    /// Waiting for the last messages to flush before closing the connection.
+    Leaving {
+        until: Instant,
+    },
+    Done,
+}
+
+struct Bot {
+    index: usize,
+    key: SigningKey,
+    connection: ConnectionId,
+    state: BotState,
+    player_id: Option<PlayerId>,
+    hero: Option<UnitId>,
+    chat_timer: Timer,
+    order_timer: Timer,
+    next_chat: u32,
+    /// Chat messages sent and not yet echoed back by the server.
+    pending_chats: HashMap<u32, Instant>,
+}
+
+#[derive(Resource)]
+struct Bots {
+    bots: Vec<Bot>,
+    spawn_timer: Timer,
+    rng: StdRng,
+    started: Instant,
+    stats: Stats,
+}
+
+impl Bot {
+    fn chat_tag(&self) -> String {
+        format!("bot-{}#", self.index)
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    App::new()
+        .insert_resource(ScheduleRunnerSettings::run_loop(TICK))
+        .insert_resource(Bots {
+            bots: Vec::with_capacity(args.clients),
+            spawn_timer: Timer::from_seconds(args.spawn_interval, TimerMode::Repeating),
+            rng,
+            started: Instant::now(),
+            stats: Stats::default(),
+        })
+        .insert_resource(args)
+        .add_plugins(MinimalPlugins)
+        .add_plugin(bevy::log::LogPlugin::default())
+        .add_plugin(QuinnetClientPlugin::default())
+        .add_system(spawn_bots)
+        .add_system(receive_messages.after(spawn_bots))
+        .add_system(act.after(receive_messages))
+        .add_system(time_out_joins.after(receive_messages))
+        .add_system(close_connections.after(act).after(time_out_joins))
+        .add_system(detect_connection_lost)
+        .add_system(finish.after(close_connections))
+        .run();
+}
+
+fn spawn_bots(
+    mut client: ResMut<Client>,
+    mut bots: ResMut<Bots>,
+    args: Res<Args>,
+    time: Res<Time>,
+) {
+    if bots.bots.len() >= args.clients || !bots.spawn_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let local_bind_ip = match args.server {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let configuration =
+        ConnectionConfiguration::from_addrs(args.server, SocketAddr::new(local_bind_ip, 0));
+    let connection = match client
+        .open_connection(configuration, CertificateVerificationMode::SkipVerification)
+    {
+        Ok((connection, _)) => connection,
+        Err(err) => {
+            error!("Failed to open connection: {err}");
+            return;
+        }
+    };
+    let index = bots.bots.len();
+    let chat_offset = bots.rng.gen_range(0.0..args.chat_interval);
+    let mut chat_timer = Timer::from_seconds(args.chat_interval, TimerMode::Repeating);
+    chat_timer.tick(Duration::from_secs_f32(chat_offset));
+    bots.bots.push(Bot {
+        index,
+        key: generate_key(),
+        connection,
+        state: BotState::Connecting {
+            since: Instant::now(),
+        },
+        player_id: None,
+        hero: None,
+        chat_timer,
+        order_timer: Timer::from_seconds(args.order_interval, TimerMode::Repeating),
+        next_chat: 0,
+        pending_chats: HashMap::new(),
+    });
+    bots.stats.spawned += 1;
+}
+
+fn receive_messages(mut client: ResMut<Client>, mut bots: ResMut<Bots>, args: Res<Args>) {
+    let Bots { bots, stats, .. } = &mut *bots;
+    for bot in bots
+        .iter_mut()
+        .filter(|bot| !matches!(bot.state, BotState::Leaving { .. } | BotState::Done))
+    {
+        let Some(connection) = client.get_connection_mut_by_id(bot.connection) else {
+            continue;
+        };
+        while let Ok(Some(message)) = connection.receive_message::<ServerMessage>() {
+            stats.messages_received += 1;
+            match message {
+                ServerMessage::Challenge(challenge) => {
+                    connection.try_send_message(ClientMessage::Join {
+                        name: format!("bot-{}", bot.index),
+                        identity: Identity::of(&bot.key),
+                        signature: challenge.sign(&bot.key),
+                        password: args.password.clone(),
+                        role: JoinRole::Player,
+                    });
+                    stats.messages_sent += 1;
+                    bot.state = BotState::Joining {
+                        since: Instant::now(),
+                    };
+                }
+                ServerMessage::InitClient { player_id, .. } => {
+                    if let BotState::Joining { since } = bot.state {
+                        stats.join_latency.push(since.elapsed());
+                    }
+                    stats.joined += 1;
+                    bot.player_id = Some(player_id);
+                    bot.state = BotState::InGame {
+                        since: Instant::now(),
+                    };
+                }
+                ServerMessage::JoinRejected { reason } => {
+                    warn!("bot-{} was rejected: {reason:?}", bot.index);
+                    stats.rejected += 1;
+                    bot.state = leaving();
+                    break;
+                }
+                ServerMessage::Kicked { reason } => {
+                    warn!("bot-{} was kicked: {reason}", bot.index);
+                    bot.state = leaving();
+                    break;
+                }
+                ServerMessage::Snapshot(snapshot) => {
+                    bot.hero = snapshot
+                        .units
+                        .iter()
+                        .find(|unit| unit.hero && unit.owner == bot.player_id)
+                        .map(|unit| unit.id);
+                }
+                ServerMessage::ChatMessage { message } => {
+                    let Some(sequence) = message
+                        .strip_prefix(&bot.chat_tag())
+                        .and_then(|sequence| sequence.parse().ok())
+                    else {
+                        continue;
+                    };
+                    if let Some(sent) = bot.pending_chats.remove(&sequence) {
+                        stats.chat_latency.push(sent.elapsed());
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn time_out_joins(client: Res<Client>, mut bots: ResMut<Bots>, args: Res<Args>) {
+    let Bots { bots, stats, .. } = &mut *bots;
+    let timeout = Duration::from_secs_f32(args.join_timeout);
+    for bot in bots.iter_mut() {
+        let (BotState::Connecting { since } | BotState::Joining { since }) = bot.state else {
+            continue;
+        };
+        if since.elapsed() < timeout {
+            continue;
+        }
+        warn!("bot-{} was not admitted within {timeout:?}", bot.index);
+        stats.timed_out += 1;
+        // In case the server admits it before hearing that it gave up.
+        if let (BotState::Joining { .. }, Some(connection)) =
+            (bot.state, client.get_connection_by_id(bot.connection))
+        {
+            connection.try_send_message(ClientMessage::Leave);
+            stats.messages_sent += 1;
+        }
+        bot.state = leaving();
+    }
+}
+
+fn act(client: Res<Client>, mut bots: ResMut<Bots>, args: Res<Args>, time: Res<Time>) {
+    let Bots {
+        bots, stats, rng, ..
+    } = &mut *bots;
+    let lifetime = Duration::from_secs_f32(args.lifetime);
+    for bot in bots.iter_mut() {
+        let BotState::InGame { since } = bot.state else {
+            continue;
+        };
+        let Some(connection) = client.get_connection_by_id(bot.connection) else {
+            continue;
+        };
+
+        if since.elapsed() >= lifetime {
+            connection.try_send_message(ClientMessage::Leave);
+            stats.messages_sent += 1;
+            bot.state = leaving();
+            continue;
+        }
+
+        if bot.chat_timer.tick(time.delta()).just_finished() {
+            let sequence = bot.next_chat;
+            bot.next_chat += 1;
+            bot.pending_chats.insert(sequence, Instant::now());
+            connection.try_send_message(ClientMessage::ChatMessage {
+                message: format!("{}{sequence}", bot.chat_tag()),
+            });
+            stats.messages_sent += 1;
+        }
+
+        if bot.order_timer.tick(time.delta()).just_finished() {
+            let Some(unit) = bot.hero else {
+                continue;
+            };
+            let order = if rng.gen_bool(STOP_CHANCE) {
+                Order::Stop
+            } else {
+                Order::Move {
+                    target: Vec2::new(
+                        rng.gen_range(-MAP_HALF_SIZE.x..MAP_HALF_SIZE.x),
+                        rng.gen_range(-MAP_HALF_SIZE.y..MAP_HALF_SIZE.y),
+                    ),
+                }
+            };
+            connection.try_send_message(ClientMessage::Order { unit, order });
+            stats.messages_sent += 1;
+        }
+    }
+}
+
+fn leaving() -> BotState {
+    BotState::Leaving {
+        until: Instant::now() + LEAVE_FLUSH_DELAY,
+    }
+}
+
+fn close_connections(mut client: ResMut<Client>, mut bots: ResMut<Bots>) {
+    for bot in &mut bots.bots {
+        if let BotState::Leaving { until } = bot.state {
+            if Instant::now() >= until {
+                client.close_connection(bot.connection).ok();
+                bot.state = BotState::Done;
+            }
+        }
+    }
+}
+
+fn detect_connection_lost(
+    mut connection_lost_events: EventReader<ConnectionLostEvent>,
+    mut bots: ResMut<Bots>,
+) {
+    for event in connection_lost_events.iter() {
+        let Bots { bots, stats, .. } = &mut *bots;
+        let Some(bot) = bots.iter_mut().find(|bot| bot.connection == event.id) else {
+            continue;
+        };
+        match bot.state {
+            BotState::Done => (),
+            // The server may close the connection once it got our `Leave`.
+            BotState::Leaving { .. } => bot.state = BotState::Done,
+            _ => {
+                warn!("bot-{} lost its connection", bot.index);
+                stats.lost += 1;
+                bot.state = BotState::Done;
+            }
+        }
+    }
+}
+
+fn finish(bots: Res<Bots>, args: Res<Args>, mut exit_events: EventWriter<AppExit>) {
+    if bots.bots.len() < args.clients || bots.bots.iter().any(|bot| bot.state != BotState::Done) {
+        return;
+    }
+    println!("{}", bots.stats.report(bots.started.elapsed()));
+    exit_events.send(AppExit);
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub spawned: usize,
+    pub joined: usize,
+    pub rejected: usize,
+    pub timed_out: usize,
+    pub lost: usize,
+    pub join_latency: Vec<Duration>,
+    pub chat_latency: Vec<Duration>,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+}
+
+/// Latency percentiles of a set of samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Percentiles {
+    pub samples: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Percentiles {
+    pub fn of(samples: &[Duration]) -> Option<Self> {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        Some(Self {
+            samples: sorted.len(),
+            p50: percentile(&sorted, 50.0)?,
+            p90: percentile(&sorted, 90.0)?,
+            p99: percentile(&sorted, 99.0)?,
+            max: *sorted.last()?,
+        })
+    }
+}
+
+impl std::fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "p50 {:.1?}, p90 {:.1?}, p99 {:.1?}, max {:.1?} ({} samples)",
+            self.p50, self.p90, self.p99, self.max, self.samples
+        )
+    }
+}
+
+impl Stats {
+    pub fn report(&self, elapsed: Duration) -> String {
+        let latency = |samples: &[Duration]| {
+            Percentiles::of(samples).map_or("no samples".to_string(), |p| p.to_string())
+        };
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        [
+            format!(
+                "Bots: {} spawned, {} joined, {} rejected, {} timed out joining, {} lost their connection",
+                self.spawned, self.joined, self.rejected, self.timed_out, self.lost
+            ),
+            format!("Join latency: {}", latency(&self.join_latency)),
+            format!("Chat round trip: {}", latency(&self.chat_latency)),
+            format!(
+                "Throughput over {elapsed:.1?}: {} messages sent ({:.1}/s), {} received ({:.1}/s)",
+                self.messages_sent,
+                self.messages_sent as f64 / seconds,
+                self.messages_received,
+                self.messages_received as f64 / seconds
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+/// Nearest-rank percentile of already sorted samples.
+pub fn percentile(sorted: &[Duration], percent: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples = (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&samples, 50.0), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&samples, 99.0), Some(Duration::from_millis(99)));
+        assert_eq!(percentile(&samples, 0.0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_percentiles_sort_samples() {
+        let samples = [30, 10, 20].map(Duration::from_millis);
+        let percentiles = Percentiles::of(&samples).unwrap();
+        assert_eq!(percentiles.p50, Duration::from_millis(20));
+        assert_eq!(percentiles.max, Duration::from_millis(30));
+        assert_eq!(percentiles.samples, 3);
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{config::ServerConfig, identity::Identity, player::JoinRejection};
+
+#[derive(Debug, Error)]
+pub enum BanFileError {
+    #[error("failed to read ban file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse ban file {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("failed to serialize bans: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("failed to write ban file {path:?}: {source}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BanRecord {
+    pub identity: Identity,
+    #[serde(default)]
+    pub reason: String,
+    /// Seconds since the Unix epoch.
+    pub banned_at: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BanList {
+    #[serde(default)]
+    bans: Vec<BanRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    Ban { identity: Identity, reason: String },
+    Unban { identity: Identity },
+    Bans,
+    Players,
+    Metrics,
+}
+
+/// Decides who may join the server and keeps the ban list in sync with its file.
+#[derive(Resource, Debug)]
+pub struct AccessControl {
+    password: Option<String>,
+    allowlist: Option<HashSet<Identity>>,
+    admins: HashSet<Identity>,
+    bans: BanList,
+    ban_file: PathBuf,
+}
+
+impl AccessControl {
+    /// Builds the access rules from `config`, loading bans from its ban file if it exists.
+    pub fn load(config: &ServerConfig) -> Result<Self, BanFileError> {
+        let bans = if config.ban_file.exists() {
+            load_bans(&config.ban_file)?
+        } else {
+            BanList::default()
+        };
+        Ok(Self {
+            password: config.password.clone(),
+            allowlist: config
+                .allowlist
+                .as_ref()
+                .map(|allowlist| allowlist.iter().cloned().collect()),
+            admins: config.admins.iter().cloned().collect(),
+            bans,
+            ban_file: config.ban_file.clone(),
+        })
+    }
+
+    pub fn check(&self, identity: &Identity, password: Option<&str>) -> Result<(), JoinRejection> {
+        if self.is_banned(identity) {
+            return Err(JoinRejection::Banned);
+        }
+        if let Some(allowlist) = &self.allowlist {
+            if !allowlist.contains(identity) {
+                return Err(JoinRejection::NotAllowlisted);
+            }
+        }
+        match &self.password {
+            Some(expected) if password != Some(expected.as_str()) => {
+                Err(JoinRejection::WrongPassword)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_admin(&self, identity: &Identity) -> bool {
+        self.admins.contains(identity)
+    }
+
+    pub fn is_banned(&self, identity: &Identity) -> bool {
+        self.bans.bans.iter().any(|ban| &ban.identity == identity)
+    }
+
+    pub fn bans(&self) -> &[BanRecord] {
+        &self.bans.bans
+    }
+
+    /// Returns false if `identity` was already banned.
+    pub fn ban(&mut self, identity: Identity, reason: String, now: SystemTime) -> bool {
+        if self.is_banned(&identity) {
+            return false;
+        }
+        self.bans.bans.push(BanRecord {
+            identity,
+            reason,
+            banned_at: now
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+        });
+        true
+    }
+
+    /// Returns false if `identity` was not banned.
+    pub fn unban(&mut self, identity: &Identity) -> bool {
+        let len = self.bans.bans.len();
+        self.bans.bans.retain(|ban| &ban.identity != identity);
+        self.bans.bans.len() != len
+    }
+
+    pub fn save(&self) -> Result<(), BanFileError> {
+        let text = toml::to_string_pretty(&self.bans)?;
+        std::fs::write(&self.ban_file, text).map_err(|source| BanFileError::Write {
+            path: self.ban_file.clone(),
+            source,
+        })
+    }
+}
+
+impl AdminCommand {
+    /// Parses a console line or a chat message, with or without a leading `/`.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.trim().trim_start_matches('/').split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let identity = words
+            .next()
+            .map(|identity| {
+                identity
+                    .parse::<Identity>()
+                    .map_err(|err| format!("invalid identity: {err}"))
+            })
+            .transpose()?;
+        match (command, identity) {
+            ("ban", Some(identity)) => Ok(Self::Ban {
+                identity,
+                reason: words.collect::<Vec<_>>().join(" "),
+            }),
+            ("unban", Some(identity)) => Ok(Self::Unban { identity }),
+            ("bans", None) => Ok(Self::Bans),
+            ("players", None) => Ok(Self::Players),
+            ("metrics", None) => Ok(Self::Metrics),
+            ("ban", None) => Err("usage: ban <identity> [reason]".to_string()),
+            ("unban", None) => Err("usage: unban <identity>".to_string()),
+            _ => Err(format!(
+                "unknown command '{}', expected ban, unban, bans, players or metrics",
+                line.trim()
+            )),
+        }
+    }
+}
+
+fn load_bans(path: &Path) -> Result<BanList, BanFileError> {
+    let text = std::fs::read_to_string(path).map_err(|source| BanFileError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    toml::from_str(&text).map_err(|source| BanFileError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(n: u8) -> Identity {
+        Identity([n; 32])
+    }
+
+    fn access(config: ServerConfig) -> AccessControl {
+        AccessControl::load(&ServerConfig {
+            ban_file: "missing/bans.toml".into(),
+            ..config
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_password() {
+        let access = access(ServerConfig {
+            password: Some("hunter2".to_string()),
+            ..Default::default()
+        });
+        let alice = identity(1);
+        assert_eq!(
+            access.check(&alice, None),
+            Err(JoinRejection::WrongPassword)
+        );
+        assert_eq!(
+            access.check(&alice, Some("hunter3")),
+            Err(JoinRejection::WrongPassword)
+        );
+        assert_eq!(access.check(&alice, Some("hunter2")), Ok(()));
+    }
+
+    #[test]
+    fn test_check_allowlist_and_bans() {
+        let mut access = access(ServerConfig {
+            allowlist: Some(vec![identity(1), identity(2)]),
+            ..Default::default()
+        });
+        assert_eq!(access.check(&identity(1), None), Ok(()));
+        assert_eq!(
+            access.check(&identity(3), None),
+            Err(JoinRejection::NotAllowlisted)
+        );
+
+        assert!(access.ban(identity(2), "feeding".to_string(), UNIX_EPOCH));
+        assert!(!access.ban(identity(2), String::new(), UNIX_EPOCH));
+        assert_eq!(access.check(&identity(2), None), Err(JoinRejection::Banned));
+        assert!(access.unban(&identity(2)));
+        assert!(!access.unban(&identity(2)));
+        assert_eq!(access.check(&identity(2), None), Ok(()));
+    }
+
+    #[test]
+    fn test_bans_round_trip() {
+        let path = std::env::temp_dir().join(format!("open_dota_bans_{}.toml", std::process::id()));
+        let config = ServerConfig {
+            ban_file: path.clone(),
+            ..Default::default()
+        };
+        let mut access = AccessControl::load(&config).unwrap();
+        access.ban(identity(3), "griefing".to_string(), UNIX_EPOCH);
+        access.save().unwrap();
+
+        let loaded = AccessControl::load(&config).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.bans(), access.bans());
+        assert!(loaded.is_banned(&identity(3)));
+    }
+
+    #[test]
+    fn test_parse_admin_command() {
+        let mallory = identity(3);
+        assert_eq!(
+            AdminCommand::parse(&format!("/ban {mallory} spamming chat")),
+            Ok(AdminCommand::Ban {
+                identity: mallory,
+                reason: "spamming chat".to_string(),
+            })
+        );
+        assert_eq!(
+            AdminCommand::parse(&format!("unban {mallory}")),
+            Ok(AdminCommand::Unban { identity: mallory })
+        );
+        assert_eq!(AdminCommand::parse(" bans "), Ok(AdminCommand::Bans));
+        assert!(AdminCommand::parse("/ban").is_err());
+        assert!(AdminCommand::parse("/ban mallory").is_err());
+        assert!(AdminCommand::parse(&format!("kick {mallory}")).is_err());
+    }
+}
@@ -0,0 +1,445 @@
+//! The boss: a single strong neutral alone in its pit, whose stats are defined in the `[boss]`
+//! section of `data/neutrals.toml`.
+//!
+//! It grows stronger with every minute of the match, fights whoever attacks it from inside its
+//! pit and never follows them out. Killing it gives the killer's hero the [`Item::Aegis`] unless
+//! somebody holds one already, and it comes back at a random time within its respawn window.
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    combat::{Attack, AttackTarget, Damaged, Health, UnitDied},
+    item::{Inventory, Item, Stash},
+    neutral::NeutralTable,
+    simulation::{MatchRng, Tick, TickRate},
+    unit::{Hero, MoveSpeed, MoveTarget, Owner, Position, Unit},
+};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BossStats {
+    pub position: Vec2,
+    /// The boss only fights units within this distance of its position.
+    pub pit_radius: f32,
+    pub health: f32,
+    pub health_per_minute: f32,
+    pub damage: f32,
+    pub damage_per_minute: f32,
+    pub attack_range: f32,
+    /// Seconds between attacks.
+    pub attack_cooldown: f32,
+    pub move_speed: f32,
+    pub respawn_min_secs: u64,
+    pub respawn_max_secs: u64,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Boss {
+    /// The minute of the match the boss' stats are scaled to.
+    pub minute: u64,
+}
+
+/// When the boss comes back, if it is dead. It first spawns as the match starts.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BossPit {
+    pub respawn_at: Option<u64>,
+}
+
+/// The boss was killed by `killer`, and its hero got `item` for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BossKilled {
+    pub killer: Entity,
+    pub item: Option<Item>,
+}
+
+#[derive(Bundle)]
+pub struct BossBundle {
+    pub unit: Unit,
+    pub boss: Boss,
+    pub position: Position,
+    pub move_speed: MoveSpeed,
+    pub move_target: MoveTarget,
+    pub health: Health,
+    pub attack: Attack,
+    pub attack_target: AttackTarget,
+}
+
+impl Default for BossPit {
+    fn default() -> Self {
+        Self {
+            respawn_at: Some(0),
+        }
+    }
+}
+
+impl BossStats {
+    pub fn health_at(&self, minute: u64) -> f32 {
+        self.health + self.health_per_minute * minute as f32
+    }
+
+    pub fn damage_at(&self, minute: u64) -> f32 {
+        self.damage + self.damage_per_minute * minute as f32
+    }
+
+    fn in_pit(&self, position: Vec2) -> bool {
+        position.distance(self.position) <= self.pit_radius
+    }
+}
+
+impl BossBundle {
+    /// The boss as strong as it is at `minute`.
+    pub fn new(stats: &BossStats, minute: u64) -> Self {
+        Self {
+            unit: Unit,
+            boss: Boss { minute },
+            position: Position(stats.position),
+            move_speed: MoveSpeed(stats.move_speed),
+            move_target: Default::default(),
+            health: Health::full(stats.health_at(minute)),
+            attack: Attack::new(
+                stats.damage_at(minute),
+                stats.attack_range,
+                stats.attack_cooldown,
+            ),
+            attack_target: Default::default(),
+        }
+    }
+}
+
+/// The full minutes of match time that passed by `tick`.
+pub fn match_minute(tick: Tick, tick_rate: TickRate) -> u64 {
+    tick.0 / (60 * tick_rate.0 as u64)
+}
+
+pub fn spawn_boss(
+    mut commands: Commands,
+    mut pit: ResMut<BossPit>,
+    table: Res<NeutralTable>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+) {
+    if !pit
+        .respawn_at
+        .is_some_and(|respawn_at| respawn_at <= tick.0)
+    {
+        return;
+    }
+    pit.respawn_at = None;
+    commands.spawn(BossBundle::new(
+        &table.boss,
+        match_minute(*tick, *tick_rate),
+    ));
+}
+
+/// Grows the boss every minute. It keeps the health it lost.
+pub fn scale_boss(
+    table: Res<NeutralTable>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    mut boss_query: Query<(&mut Boss, &mut Health, &mut Attack)>,
+) {
+    let minute = match_minute(*tick, *tick_rate);
+    for (mut boss, mut health, mut attack) in &mut boss_query {
+        if boss.minute == minute {
+            continue;
+        }
+        let max = table.boss.health_at(minute);
+        health.current += max - health.max;
+        health.max = max;
+        attack.damage = table.boss.damage_at(minute);
+        boss.minute = minute;
+    }
+}
+
+/// Fights back against attackers in the pit and walks back to its spot once they leave it.
+pub fn boss_ai(
+    table: Res<NeutralTable>,
+    mut damaged_events: EventReader<Damaged>,
+    mut boss_query: Query<(&mut MoveTarget, &mut AttackTarget), With<Boss>>,
+    position_query: Query<&Position>,
+) {
+    let in_pit = |entity| {
+        position_query
+            .get(entity)
+            .is_ok_and(|position| table.boss.in_pit(position.0))
+    };
+    for event in damaged_events.iter() {
+        let Ok((_, mut attack_target)) = boss_query.get_mut(event.target) else {
+            continue;
+        };
+        if attack_target.0.is_none() && in_pit(event.source) {
+            attack_target.0 = Some(event.source);
+        }
+    }
+    for (mut move_target, mut attack_target) in &mut boss_query {
+        if attack_target.0.is_some_and(|target| !in_pit(target)) {
+            attack_target.0 = None;
+            move_target.0 = Some(table.boss.position);
+        }
+    }
+}
+
+/// The hero that gets the drop for a kill by `killer`: the killer itself if it is a hero, or the
+/// hero of the player owning it, like for illusions. Nobody gets it for kills by other units.
+fn rewarded_hero(
+    killer: Entity,
+    hero_query: &Query<(Entity, &Owner), With<Hero>>,
+    owner_query: &Query<&Owner>,
+) -> Option<Entity> {
+    if hero_query.contains(killer) {
+        return Some(killer);
+    }
+    let owner = owner_query.get(killer).ok()?;
+    hero_query
+        .iter()
+        .find(|(_, hero_owner)| hero_owner.0 == owner.0)
+        .map(|(hero, _)| hero)
+}
+
+/// Hands out the drop for killing the boss and picks when it respawns. The drop goes to the
+/// stash if the hero's inventory is full, and is lost if that is full too.
+#[allow(clippy::too_many_arguments)]
+pub fn reward_boss_kill(
+    mut died_events: EventReader<UnitDied>,
+    mut killed_events: EventWriter<BossKilled>,
+    mut pit: ResMut<BossPit>,
+    mut rng: ResMut<MatchRng>,
+    table: Res<NeutralTable>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    boss_query: Query<(), With<Boss>>,
+    hero_query: Query<(Entity, &Owner), With<Hero>>,
+    owner_query: Query<&Owner>,
+    mut item_query: Query<(&mut Inventory, Option<&mut Stash>)>,
+) {
+    for event in died_events.iter() {
+        if !boss_query.contains(event.unit) {
+            continue;
+        }
+        let delay = rng.gen_range(table.boss.respawn_min_secs..=table.boss.respawn_max_secs);
+        pit.respawn_at = Some(tick.0 + delay * tick_rate.0 as u64);
+
+        let drop = Item::Aegis;
+        let already_held = drop.is_unique()
+            && item_query.iter().any(|(inventory, stash)| {
+                inventory.contains(drop) || stash.is_some_and(|stash| stash.0.contains(drop))
+            });
+        let hero = rewarded_hero(event.killer, &hero_query, &owner_query);
+        let item = (!already_held).then_some(drop).filter(|item| {
+            hero.and_then(|hero| item_query.get_mut(hero).ok())
+                .is_some_and(|(mut inventory, stash)| {
+                    inventory.add(*item) || stash.is_some_and(|mut stash| stash.0.add(*item))
+                })
+        });
+        killed_events.send(BossKilled {
+            killer: event.killer,
+            item,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combat::{apply_damage, remove_dead_units},
+        courier::CourierBundle,
+        death::{kill_heroes, HeroKilled},
+        illusion::IllusionBundle,
+        player::{PlayerId, Team},
+        simulation::MatchSeed,
+        unit::HeroBundle,
+    };
+
+    const TICK_RATE: u32 = 10;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(NeutralTable::builtin());
+        world.insert_resource(TickRate(TICK_RATE));
+        world.insert_resource(MatchRng::new(MatchSeed(42)));
+        world.init_resource::<Tick>();
+        world.init_resource::<BossPit>();
+        world.init_resource::<Events<Damaged>>();
+        world.init_resource::<Events<UnitDied>>();
+        world.init_resource::<Events<BossKilled>>();
+        world.init_resource::<Events<HeroKilled>>();
+        world
+    }
+
+    fn run(world: &mut World, schedule: &mut Schedule) {
+        schedule.run(world);
+        world.resource_mut::<Events<Damaged>>().update();
+        world.resource_mut::<Events<UnitDied>>().update();
+    }
+
+    fn spawn_at(world: &mut World, tick: u64) -> Option<Entity> {
+        world.resource_mut::<Tick>().0 = tick;
+        let mut schedule = Schedule::new();
+        schedule.add_system(spawn_boss);
+        schedule.run(world);
+        world
+            .query_filtered::<Entity, With<Boss>>()
+            .iter(world)
+            .next()
+    }
+
+    fn kill(world: &mut World, schedule: &mut Schedule, boss: Entity, killer: Entity) {
+        world.send_event(Damaged {
+            source: killer,
+            target: boss,
+            amount: f32::MAX,
+        });
+        run(world, schedule);
+    }
+
+    #[test]
+    fn test_boss_grows_stronger() {
+        let mut world = world();
+        let stats = NeutralTable::builtin().boss;
+        let boss = spawn_at(&mut world, 1).unwrap();
+        assert_eq!(world.get::<Health>(boss).unwrap().max, stats.health);
+        world.get_mut::<Health>(boss).unwrap().current -= 100.0;
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(scale_boss);
+        world.resource_mut::<Tick>().0 = 2 * 60 * TICK_RATE as u64;
+        schedule.run(&mut world);
+        let health = *world.get::<Health>(boss).unwrap();
+        assert_eq!(health.max, stats.health_at(2));
+        assert_eq!(health.current, stats.health_at(2) - 100.0);
+        assert_eq!(
+            world.get::<Attack>(boss).unwrap().damage,
+            stats.damage_at(2)
+        );
+
+        let respawned = BossBundle::new(&stats, 3);
+        assert!(respawned.health.max > health.max);
+    }
+
+    #[test]
+    fn test_boss_drops_a_unique_aegis_and_respawns() {
+        let mut world = world();
+        let stats = NeutralTable::builtin().boss;
+        let mut schedule = Schedule::new();
+        schedule.add_systems((apply_damage, reward_boss_kill, remove_dead_units).chain());
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+
+        let boss = spawn_at(&mut world, 1).unwrap();
+        world.resource_mut::<Tick>().0 = 100;
+        kill(&mut world, &mut schedule, boss, hero);
+        assert!(world.get_entity(boss).is_none());
+        assert!(world.get::<Inventory>(hero).unwrap().contains(Item::Aegis));
+        let killed = world
+            .resource_mut::<Events<BossKilled>>()
+            .drain()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            killed,
+            vec![BossKilled {
+                killer: hero,
+                item: Some(Item::Aegis),
+            }]
+        );
+
+        let respawn_at = world.resource::<BossPit>().respawn_at.unwrap();
+        let window = 100 + stats.respawn_min_secs * TICK_RATE as u64
+            ..=100 + stats.respawn_max_secs * TICK_RATE as u64;
+        assert!(window.contains(&respawn_at));
+        assert_eq!(spawn_at(&mut world, respawn_at - 1), None);
+        let boss = spawn_at(&mut world, respawn_at).unwrap();
+        assert_eq!(world.resource::<BossPit>().respawn_at, None);
+
+        // Nobody gets a second Aegis while the first is still around.
+        kill(&mut world, &mut schedule, boss, hero);
+        let inventory = world.get::<Inventory>(hero).unwrap();
+        let aegis_count = inventory
+            .items()
+            .filter(|item| *item == Item::Aegis)
+            .count();
+        assert_eq!(aegis_count, 1);
+        let killed = world.resource_mut::<Events<BossKilled>>().drain().next();
+        assert_eq!(killed.unwrap().item, None);
+    }
+
+    #[test]
+    fn test_aegis_goes_to_the_killers_hero() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((apply_damage, reward_boss_kill, remove_dead_units).chain());
+        let killed_item = |world: &mut World| {
+            world
+                .resource_mut::<Events<BossKilled>>()
+                .drain()
+                .next()
+                .unwrap()
+                .item
+        };
+
+        // Couriers get nothing.
+        let courier = world.spawn(CourierBundle::new(Team::Radiant)).id();
+        let boss = spawn_at(&mut world, 1).unwrap();
+        kill(&mut world, &mut schedule, boss, courier);
+        assert_eq!(killed_item(&mut world), None);
+        assert!(world.get::<Inventory>(courier).unwrap().is_empty());
+
+        // Illusions get it for their hero, into the stash while the hero's inventory is full.
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        let illusion = world
+            .spawn(IllusionBundle::new(
+                PlayerId(1),
+                Team::Radiant,
+                Vec2::ZERO,
+                1000,
+            ))
+            .id();
+        while world
+            .get_mut::<Inventory>(hero)
+            .unwrap()
+            .add(Item::QuellingBlade)
+        {}
+        world.resource_mut::<BossPit>().respawn_at = Some(1);
+        let boss = spawn_at(&mut world, 1).unwrap();
+        kill(&mut world, &mut schedule, boss, illusion);
+        assert_eq!(killed_item(&mut world), Some(Item::Aegis));
+        assert!(!world.get::<Inventory>(hero).unwrap().contains(Item::Aegis));
+        assert!(world.get::<Stash>(hero).unwrap().0.contains(Item::Aegis));
+
+        // The Aegis in the stash is still held.
+        world.resource_mut::<BossPit>().respawn_at = Some(1);
+        let boss = spawn_at(&mut world, 1).unwrap();
+        kill(&mut world, &mut schedule, boss, hero);
+        assert_eq!(killed_item(&mut world), None);
+    }
+
+    #[test]
+    fn test_aegis_revives_in_place() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((apply_damage, kill_heroes, remove_dead_units).chain());
+        let hero = world.spawn(HeroBundle::new(PlayerId(1), Team::Dire)).id();
+        let killer = world
+            .spawn(HeroBundle::new(PlayerId(2), Team::Radiant))
+            .id();
+        world.get_mut::<Inventory>(hero).unwrap().add(Item::Aegis);
+        let died_at = Vec2::new(10.0, 20.0);
+        world.get_mut::<Position>(hero).unwrap().0 = died_at;
+
+        kill(&mut world, &mut schedule, hero, killer);
+        assert_eq!(world.get::<Position>(hero).unwrap().0, died_at);
+        assert!(world.get::<Health>(hero).unwrap().is_full());
+        assert!(!world.get::<Inventory>(hero).unwrap().contains(Item::Aegis));
+
+        kill(&mut world, &mut schedule, hero, killer);
+        assert_eq!(
+            world.get::<Position>(hero).unwrap().0,
+            Team::Dire.fountain()
+        );
+    }
+}
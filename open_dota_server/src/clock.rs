@@ -0,0 +1,58 @@
+//! The game clock. Matches start at daybreak, and day and night take turns every
+//! [`DAY_NIGHT_SECS`].
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::{Tick, TickRate};
+
+/// Seconds each day and each night lasts.
+pub const DAY_NIGHT_SECS: u64 = 300;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DayPhase {
+    #[default]
+    Day,
+    Night,
+}
+
+/// Match time in whole seconds, changed once a second.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameClock {
+    pub seconds: u64,
+    pub phase: DayPhase,
+}
+
+impl GameClock {
+    /// The clock after `tick` ticks have been simulated.
+    pub fn at(tick: Tick, tick_rate: TickRate) -> Self {
+        let seconds = tick.0 / tick_rate.0 as u64;
+        let phase = if (seconds / DAY_NIGHT_SECS).is_multiple_of(2) {
+            DayPhase::Day
+        } else {
+            DayPhase::Night
+        };
+        Self { seconds, phase }
+    }
+}
+
+pub fn update_clock(mut clock: ResMut<GameClock>, tick: Res<Tick>, tick_rate: Res<TickRate>) {
+    clock.set_if_neq(GameClock::at(*tick, *tick_rate));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_and_night_alternate() {
+        let clock = |seconds| GameClock::at(Tick(seconds * 30 + 29), TickRate(30));
+        assert_eq!(clock(0).phase, DayPhase::Day);
+        assert_eq!(clock(0).seconds, 0);
+        assert_eq!(clock(DAY_NIGHT_SECS - 1).phase, DayPhase::Day);
+        assert_eq!(clock(DAY_NIGHT_SECS).phase, DayPhase::Night);
+        assert_eq!(clock(DAY_NIGHT_SECS * 2 - 1).phase, DayPhase::Night);
+        assert_eq!(clock(DAY_NIGHT_SECS * 2).phase, DayPhase::Day);
+        assert_eq!(clock(754).seconds, 754);
+    }
+}
@@ -0,0 +1,292 @@
+use bevy::prelude::*;
+
+use crate::{
+    illusion::{Illusion, ILLUSION_DAMAGE_DEALT, ILLUSION_DAMAGE_TAKEN},
+    rune::{RuneBuff, RuneKind, DOUBLE_DAMAGE_MULTIPLIER},
+    simulation::TickRate,
+    unit::{Hero, MoveTarget, Position},
+    ward::Ward,
+};
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Attack {
+    pub damage: f32,
+    pub range: f32,
+    /// Seconds between attacks.
+    pub cooldown: f32,
+    /// Seconds until the next attack can land.
+    pub ready_in: f32,
+}
+
+/// The unit being attacked, chased until it is in range.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AttackTarget(pub Option<Entity>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Damaged {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnitDied {
+    pub unit: Entity,
+    pub killer: Entity,
+}
+
+impl Health {
+    pub fn full(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current >= self.max
+    }
+}
+
+impl Attack {
+    pub fn new(damage: f32, range: f32, cooldown: f32) -> Self {
+        Self {
+            damage,
+            range,
+            cooldown,
+            ready_in: 0.0,
+        }
+    }
+}
+
+type AttackerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Position,
+        &'static mut MoveTarget,
+        &'static mut Attack,
+        &'static mut AttackTarget,
+        Option<&'static RuneBuff>,
+        Option<&'static Illusion>,
+    ),
+>;
+
+/// Chases attack targets until they are in range and hits them whenever the attack is ready.
+pub fn attack_targets(
+    mut attacker_query: AttackerQuery,
+    target_query: Query<&Position>,
+    tick_rate: Res<TickRate>,
+    mut damaged_events: EventWriter<Damaged>,
+) {
+    for (entity, position, mut move_target, mut attack, mut attack_target, buff, illusion) in
+        &mut attacker_query
+    {
+        attack.ready_in = (attack.ready_in - tick_rate.delta_seconds()).max(0.0);
+        let Some(target) = attack_target.0 else {
+            continue;
+        };
+        let Ok(target_position) = target_query.get(target) else {
+            attack_target.0 = None;
+            move_target.0 = None;
+            continue;
+        };
+        if position.0.distance(target_position.0) > attack.range {
+            move_target.0 = Some(target_position.0);
+            continue;
+        }
+        move_target.0 = None;
+        if attack.ready_in <= 0.0 {
+            attack.ready_in = attack.cooldown;
+            let mut multiplier = match buff {
+                Some(buff) if buff.is(RuneKind::DoubleDamage) => DOUBLE_DAMAGE_MULTIPLIER,
+                _ => 1.0,
+            };
+            if illusion.is_some() {
+                multiplier *= ILLUSION_DAMAGE_DEALT;
+            }
+            damaged_events.send(Damaged {
+                source: entity,
+                target,
+                amount: attack.damage * multiplier,
+            });
+        }
+    }
+}
+
+pub fn apply_damage(
+    mut damaged_events: EventReader<Damaged>,
+    mut died_events: EventWriter<UnitDied>,
+    mut health_query: Query<(&mut Health, Option<&Illusion>, Option<&Ward>)>,
+) {
+    for event in damaged_events.iter() {
+        let Ok((mut health, illusion, ward)) = health_query.get_mut(event.target) else {
+            continue;
+        };
+        if health.current <= 0.0 {
+            continue;
+        }
+        // Wards lose a hit point per hit.
+        let amount = if ward.is_some() {
+            1.0
+        } else if illusion.is_some() {
+            event.amount * ILLUSION_DAMAGE_TAKEN
+        } else {
+            event.amount
+        };
+        health.current = (health.current - amount).max(0.0);
+        if health.current <= 0.0 {
+            died_events.send(UnitDied {
+                unit: event.target,
+                killer: event.source,
+            });
+        }
+    }
+}
+
+/// Despawns units that died and makes everyone stop attacking them. Heroes stay, see
+/// [`kill_heroes`](crate::death::kill_heroes).
+pub fn remove_dead_units(
+    mut commands: Commands,
+    mut died_events: EventReader<UnitDied>,
+    hero_query: Query<(), With<Hero>>,
+    mut attacker_query: Query<&mut AttackTarget>,
+) {
+    for event in died_events.iter() {
+        for mut attack_target in &mut attacker_query {
+            if attack_target.0 == Some(event.unit) {
+                attack_target.0 = None;
+            }
+        }
+        if !hero_query.contains(event.unit) {
+            commands.entity(event.unit).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        death::{kill_heroes, HeroKilled, Respawn},
+        illusion::IllusionBundle,
+        player::{PlayerId, Team},
+        simulation::Tick,
+        unit::{HeroBundle, MoveSpeed, Unit},
+    };
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(TickRate(4));
+        world.init_resource::<Events<Damaged>>();
+        world.init_resource::<Events<UnitDied>>();
+        world
+    }
+
+    fn run(world: &mut World, schedule: &mut Schedule) {
+        schedule.run(world);
+        world.resource_mut::<Events<Damaged>>().update();
+        world.resource_mut::<Events<UnitDied>>().update();
+    }
+
+    fn schedule() -> Schedule {
+        let mut schedule = Schedule::new();
+        schedule.add_systems((attack_targets, apply_damage, remove_dead_units).chain());
+        schedule
+    }
+
+    fn dummy(world: &mut World, position: Vec2, health: f32) -> Entity {
+        world
+            .spawn((
+                Unit,
+                Position(position),
+                MoveSpeed(100.0),
+                MoveTarget::default(),
+                Health::full(health),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn test_attacks_chase_and_respect_cooldown() {
+        let mut world = world();
+        let mut schedule = schedule();
+        let mut hero = HeroBundle::new(PlayerId(1), Team::Radiant);
+        hero.attack = Attack::new(30.0, 50.0, 0.5);
+        let target_position = hero.position.0 + Vec2::new(200.0, 0.0);
+        let hero = world.spawn(hero).id();
+        let target = dummy(&mut world, target_position, 100.0);
+        world.get_mut::<AttackTarget>(hero).unwrap().0 = Some(target);
+
+        run(&mut world, &mut schedule);
+        assert_eq!(
+            world.get::<MoveTarget>(hero).unwrap().0,
+            Some(target_position)
+        );
+        assert!(world.get::<Health>(target).unwrap().is_full());
+
+        world.get_mut::<Position>(hero).unwrap().0 = target_position - Vec2::new(40.0, 0.0);
+        // Hits right away, then every other tick.
+        let mut health = Vec::new();
+        for _ in 0..7 {
+            run(&mut world, &mut schedule);
+            health.push(world.get::<Health>(target).map(|health| health.current));
+        }
+        assert_eq!(health[0], Some(70.0));
+        assert_eq!(health[1], Some(70.0));
+        assert_eq!(health[2], Some(40.0));
+        assert_eq!(health[6], None);
+        assert_eq!(world.get::<AttackTarget>(hero).unwrap().0, None);
+    }
+
+    #[test]
+    fn test_dead_heroes_wait_at_their_fountain() {
+        let mut world = world();
+        world.init_resource::<Tick>();
+        world.init_resource::<Events<HeroKilled>>();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((apply_damage, kill_heroes, remove_dead_units).chain());
+        let hero = world.spawn(HeroBundle::new(PlayerId(1), Team::Dire)).id();
+        let killer = dummy(&mut world, Vec2::ZERO, 100.0);
+        world.get_mut::<Position>(hero).unwrap().0 = Vec2::ZERO;
+        let max = world.get::<Health>(hero).unwrap().max;
+        world.send_event(Damaged {
+            source: killer,
+            target: hero,
+            amount: max + 10.0,
+        });
+        run(&mut world, &mut schedule);
+        assert_eq!(
+            world.get::<Position>(hero).unwrap().0,
+            Team::Dire.fountain()
+        );
+        assert_eq!(world.get::<Health>(hero).unwrap().current, 0.0);
+        assert!(world.get::<Respawn>(hero).unwrap().is_dead());
+    }
+
+    #[test]
+    fn test_illusions_deal_less_and_take_more_damage() {
+        let mut world = world();
+        let mut schedule = schedule();
+        let mut illusion = IllusionBundle::new(PlayerId(1), Team::Radiant, Vec2::ZERO, u64::MAX);
+        illusion.attack = Attack::new(100.0, 50.0, 1.0);
+        let illusion = world.spawn(illusion).id();
+        let target = dummy(&mut world, Vec2::new(40.0, 0.0), 100.0);
+        world.get_mut::<AttackTarget>(illusion).unwrap().0 = Some(target);
+        world.send_event(Damaged {
+            source: target,
+            target: illusion,
+            amount: 10.0,
+        });
+        run(&mut world, &mut schedule);
+
+        let dealt = 100.0 * ILLUSION_DAMAGE_DEALT;
+        assert_eq!(world.get::<Health>(target).unwrap().current, 100.0 - dealt);
+        let health = world.get::<Health>(illusion).unwrap();
+        assert_eq!(health.current, health.max - 10.0 * ILLUSION_DAMAGE_TAKEN);
+    }
+}
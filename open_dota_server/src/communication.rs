@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+pub const PING_COOLDOWN: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PingKind {
+    Alert,
+    AttackHere,
+    Retreat,
+    EnemyMissing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChatWheelPhrase {
+    Okay,
+    Thanks,
+    WellPlayed,
+    GoodLuckHaveFun,
+    Careful,
+    GetBack,
+    NeedWards,
+    Push,
+    Help,
+    OnMyWay,
+    EnemyReturned,
+    GoodGame,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PingCooldown {
+    last_ping: Option<Duration>,
+}
+
+impl PingKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Alert => "Alert",
+            Self::AttackHere => "Attack here",
+            Self::Retreat => "Retreat",
+            Self::EnemyMissing => "Enemy missing",
+        }
+    }
+}
+
+impl ChatWheelPhrase {
+    pub fn text(&self) -> &'static str {
+        match self {
+            Self::Okay => "Okay.",
+            Self::Thanks => "Thanks!",
+            Self::WellPlayed => "Well played!",
+            Self::GoodLuckHaveFun => "Good luck, have fun.",
+            Self::Careful => "Careful!",
+            Self::GetBack => "Get back!",
+            Self::NeedWards => "We need wards.",
+            Self::Push => "Push now!",
+            Self::Help => "Help!",
+            Self::OnMyWay => "On my way.",
+            Self::EnemyReturned => "Enemy returned.",
+            Self::GoodGame => "Good game.",
+        }
+    }
+}
+
+impl PingCooldown {
+    /// Returns `true` and starts the cooldown if the player is allowed to ping at `now`.
+    pub fn try_ping(&mut self, now: Duration) -> bool {
+        match self.last_ping {
+            Some(last_ping) if now < last_ping + PING_COOLDOWN => false,
+            _ => {
+                self.last_ping = Some(now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_cooldown() {
+        let mut cooldown = PingCooldown::default();
+        assert!(cooldown.try_ping(Duration::from_secs(10)));
+        assert!(!cooldown.try_ping(Duration::from_secs(10)));
+        assert!(!cooldown.try_ping(Duration::from_millis(10_500)));
+        assert!(cooldown.try_ping(Duration::from_secs(11)));
+    }
+}
@@ -0,0 +1,435 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_quinnet::server::certificate::CertificateRetrievalMode;
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::identity::Identity;
+
+pub const DEFAULT_CONFIG_PATH: &str = "server.toml";
+pub const DEFAULT_BAN_FILE: &str = "bans.toml";
+pub const MAX_TICK_RATE: u32 = 128;
+pub const MAX_PLAYERS: usize = 24;
+pub const MAX_SPECTATORS: usize = 64;
+pub const MAPS: &[&str] = &["dota"];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse config file {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid value for `{field}`: {reason}")]
+    Invalid { field: &'static str, reason: String },
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    AllPick,
+    AllRandom,
+    CaptainsMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum CertificateConfig {
+    SelfSigned { hostname: String },
+    Files { certificate: PathBuf, key: PathBuf },
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub certificate: CertificateConfig,
+    pub max_players: usize,
+    pub tick_rate: u32,
+    pub game_mode: GameMode,
+    pub map: String,
+    pub password: Option<String>,
+    /// Only these players may join, if set.
+    pub allowlist: Option<Vec<Identity>>,
+    /// Players allowed to run admin commands from chat.
+    pub admins: Vec<Identity>,
+    pub ban_file: PathBuf,
+    /// Seed for the match, random if unset.
+    pub seed: Option<u64>,
+    /// Directory to record a replay of every match to, if set.
+    pub replay_dir: Option<PathBuf>,
+    /// Spectators allowed to watch at once, none if 0.
+    pub max_spectators: usize,
+    /// Seconds spectators trail the match by.
+    pub spectator_delay: u64,
+}
+
+#[derive(Parser, Debug, Default)]
+#[command(about = "Open Dota dedicated server")]
+pub struct ServerArgs {
+    /// TOML config file to load. Defaults to `server.toml` if it exists.
+    #[arg(long, env = "OPEN_DOTA_SERVER_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub bind_address: Option<IpAddr>,
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// PEM certificate chain, requires `--key`.
+    #[arg(long, requires = "key", conflicts_with = "self_signed_hostname")]
+    pub certificate: Option<PathBuf>,
+    /// PEM private key, requires `--certificate`.
+    #[arg(long, requires = "certificate")]
+    pub key: Option<PathBuf>,
+    /// Generate a self-signed certificate for this hostname on startup.
+    #[arg(long)]
+    pub self_signed_hostname: Option<String>,
+    #[arg(long)]
+    pub max_players: Option<usize>,
+    /// Simulation ticks per second.
+    #[arg(long)]
+    pub tick_rate: Option<u32>,
+    #[arg(long, value_enum)]
+    pub game_mode: Option<GameMode>,
+    #[arg(long)]
+    pub map: Option<String>,
+    #[arg(long, env = "OPEN_DOTA_SERVER_PASSWORD")]
+    pub password: Option<String>,
+    /// File bans are loaded from and saved to.
+    #[arg(long)]
+    pub ban_file: Option<PathBuf>,
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Record a replay of the match to this directory.
+    #[arg(long)]
+    pub replay_dir: Option<PathBuf>,
+    #[arg(long)]
+    pub max_spectators: Option<usize>,
+    /// Seconds spectators trail the match by.
+    #[arg(long)]
+    pub spectator_delay: Option<u64>,
+}
+
+impl Default for CertificateConfig {
+    fn default() -> Self {
+        Self::SelfSigned {
+            hostname: Ipv4Addr::LOCALHOST.to_string(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 6000,
+            certificate: Default::default(),
+            max_players: 10,
+            tick_rate: 30,
+            game_mode: Default::default(),
+            map: MAPS[0].to_string(),
+            password: None,
+            allowlist: None,
+            admins: Vec::new(),
+            ban_file: DEFAULT_BAN_FILE.into(),
+            seed: None,
+            replay_dir: None,
+            max_spectators: 4,
+            spectator_delay: 120,
+        }
+    }
+}
+
+impl CertificateConfig {
+    pub fn retrieval_mode(&self) -> CertificateRetrievalMode {
+        match self {
+            Self::SelfSigned { hostname } => CertificateRetrievalMode::GenerateSelfSigned {
+                server_hostname: hostname.clone(),
+            },
+            Self::Files { certificate, key } => CertificateRetrievalMode::LoadFromFile {
+                cert_file: certificate.to_string_lossy().into_owned(),
+                key_file: key.to_string_lossy().into_owned(),
+            },
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Loads the config file named by `args`, applies the remaining arguments on top of it and
+    /// validates the result.
+    pub fn from_args(args: &ServerArgs) -> Result<Self, ConfigError> {
+        let mut config = match &args.config {
+            Some(path) => Self::load(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::load(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Self::default(),
+        };
+        config.apply_args(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn apply_args(&mut self, args: &ServerArgs) {
+        if let Some(bind_address) = args.bind_address {
+            self.bind_address = bind_address;
+        }
+        if let Some(port) = args.port {
+            self.port = port;
+        }
+        if let (Some(certificate), Some(key)) = (&args.certificate, &args.key) {
+            self.certificate = CertificateConfig::Files {
+                certificate: certificate.clone(),
+                key: key.clone(),
+            };
+        }
+        if let Some(hostname) = &args.self_signed_hostname {
+            self.certificate = CertificateConfig::SelfSigned {
+                hostname: hostname.clone(),
+            };
+        }
+        if let Some(max_players) = args.max_players {
+            self.max_players = max_players;
+        }
+        if let Some(tick_rate) = args.tick_rate {
+            self.tick_rate = tick_rate;
+        }
+        if let Some(game_mode) = args.game_mode {
+            self.game_mode = game_mode;
+        }
+        if let Some(map) = &args.map {
+            self.map = map.clone();
+        }
+        if let Some(password) = &args.password {
+            self.password = Some(password.clone());
+        }
+        if let Some(ban_file) = &args.ban_file {
+            self.ban_file = ban_file.clone();
+        }
+        if let Some(seed) = args.seed {
+            self.seed = Some(seed);
+        }
+        if let Some(replay_dir) = &args.replay_dir {
+            self.replay_dir = Some(replay_dir.clone());
+        }
+        if let Some(max_spectators) = args.max_spectators {
+            self.max_spectators = max_spectators;
+        }
+        if let Some(spectator_delay) = args.spectator_delay {
+            self.spectator_delay = spectator_delay;
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.port == 0 {
+            return Err(invalid("port", "must be between 1 and 65535"));
+        }
+        match &self.certificate {
+            CertificateConfig::SelfSigned { hostname } if hostname.trim().is_empty() => {
+                return Err(invalid("certificate.hostname", "must not be empty"));
+            }
+            CertificateConfig::Files { certificate, key } => {
+                if !certificate.is_file() {
+                    return Err(invalid(
+                        "certificate.certificate",
+                        format!("{certificate:?} is not a file"),
+                    ));
+                }
+                if !key.is_file() {
+                    return Err(invalid("certificate.key", format!("{key:?} is not a file")));
+                }
+            }
+            _ => (),
+        }
+        if !(1..=MAX_PLAYERS).contains(&self.max_players) {
+            return Err(invalid(
+                "max_players",
+                format!(
+                    "must be between 1 and {MAX_PLAYERS}, got {}",
+                    self.max_players
+                ),
+            ));
+        }
+        if self.max_spectators > MAX_SPECTATORS {
+            return Err(invalid(
+                "max_spectators",
+                format!(
+                    "must be at most {MAX_SPECTATORS}, got {}",
+                    self.max_spectators
+                ),
+            ));
+        }
+        if !(1..=MAX_TICK_RATE).contains(&self.tick_rate) {
+            return Err(invalid(
+                "tick_rate",
+                format!(
+                    "must be between 1 and {MAX_TICK_RATE}, got {}",
+                    self.tick_rate
+                ),
+            ));
+        }
+        if !MAPS.contains(&self.map.as_str()) {
+            return Err(invalid(
+                "map",
+                format!("unknown map '{}', expected one of {MAPS:?}", self.map),
+            ));
+        }
+        if self.password.as_deref() == Some("") {
+            return Err(invalid("password", "must not be empty, omit it instead"));
+        }
+        Ok(())
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.tick_rate as f64)
+    }
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        field,
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_field(config: &ServerConfig) -> &'static str {
+        match config.validate() {
+            Err(ConfigError::Invalid { field, .. }) => field,
+            other => panic!("expected invalid field, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            port = 7000
+            max_players = 4
+            game_mode = "all_random"
+            password = "hunter2"
+            allowlist = [
+                "0101010101010101010101010101010101010101010101010101010101010101",
+                "0202020202020202020202020202020202020202020202020202020202020202",
+            ]
+            admins = ["0101010101010101010101010101010101010101010101010101010101010101"]
+
+            [certificate]
+            mode = "self_signed"
+            hostname = "play.example.com"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ServerConfig {
+                port: 7000,
+                max_players: 4,
+                game_mode: GameMode::AllRandom,
+                password: Some("hunter2".to_string()),
+                allowlist: Some(vec![Identity([1; 32]), Identity([2; 32])]),
+                admins: vec![Identity([1; 32])],
+                certificate: CertificateConfig::SelfSigned {
+                    hostname: "play.example.com".to_string(),
+                },
+                ..Default::default()
+            }
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        assert!(toml::from_str::<ServerConfig>("tickrate = 30").is_err());
+    }
+
+    #[test]
+    fn test_validate_reports_field() {
+        let config = ServerConfig {
+            port: 0,
+            ..Default::default()
+        };
+        assert_eq!(invalid_field(&config), "port");
+
+        let config = ServerConfig {
+            tick_rate: 0,
+            ..Default::default()
+        };
+        assert_eq!(invalid_field(&config), "tick_rate");
+
+        let config = ServerConfig {
+            max_players: MAX_PLAYERS + 1,
+            ..Default::default()
+        };
+        assert_eq!(invalid_field(&config), "max_players");
+
+        let config = ServerConfig {
+            max_spectators: MAX_SPECTATORS + 1,
+            ..Default::default()
+        };
+        assert_eq!(invalid_field(&config), "max_spectators");
+
+        let config = ServerConfig {
+            map: "nowhere".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(invalid_field(&config), "map");
+
+        let config = ServerConfig {
+            certificate: CertificateConfig::Files {
+                certificate: "missing/cert.pem".into(),
+                key: "missing/key.pem".into(),
+            },
+            ..Default::default()
+        };
+        assert_eq!(invalid_field(&config), "certificate.certificate");
+    }
+
+    #[test]
+    fn test_args_override_config() {
+        let mut config = ServerConfig::default();
+        config.apply_args(&ServerArgs::parse_from([
+            "open_dota_server",
+            "--port",
+            "7001",
+            "--self-signed-hostname",
+            "localhost",
+            "--game-mode",
+            "captains-mode",
+        ]));
+        assert_eq!(config.port, 7001);
+        assert_eq!(config.game_mode, GameMode::CaptainsMode);
+        assert_eq!(
+            config.certificate,
+            CertificateConfig::SelfSigned {
+                hostname: "localhost".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Every team has a courier, which carries items from the players' stashes to their heroes. The
+//! courier belongs to the whole team, any player on it can order it around. It can be killed, and
+//! comes back at the fountain [`COURIER_RESPAWN_SECS`] later without what it carried.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    combat::{Health, UnitDied},
+    item::{Inventory, Stash, STASH_RANGE},
+    player::{PlayerId, Team},
+    simulation::{Tick, TickRate},
+    unit::{Hero, MoveSpeed, MoveTarget, Owner, Position, Unit},
+    vision::Vision,
+};
+
+pub const COURIER_MOVE_SPEED: f32 = 280.0;
+pub const COURIER_HEALTH: f32 = 150.0;
+pub const COURIER_VISION: f32 = 300.0;
+pub const COURIER_BURST_MOVE_SPEED: f32 = 800.0;
+pub const COURIER_BURST_SECS: u64 = 4;
+pub const COURIER_BURST_COOLDOWN_SECS: u64 = 60;
+pub const COURIER_RESPAWN_SECS: u64 = 60;
+/// How close a courier has to be to a hero to hand it items.
+pub const COURIER_TRANSFER_RANGE: f32 = 100.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CourierTask {
+    /// Only does what it is ordered to.
+    #[default]
+    Idle,
+    /// Fetches the player's items from their stash and brings them to their hero.
+    Deliver(PlayerId),
+    /// Goes back to the fountain and puts whatever it carries back in the stash.
+    ReturnToBase,
+}
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Courier {
+    pub task: CourierTask,
+    /// The player whose stash the carried items came from.
+    pub carrying_for: Option<PlayerId>,
+    pub burst_until: u64,
+    pub burst_ready_at: u64,
+}
+
+/// Units any player of their team may order, not only their owner.
+#[derive(Component, Debug, Default)]
+pub struct SharedControl;
+
+/// When each team's courier comes back, for teams without one. Both spawn as the match starts.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct CourierRespawns {
+    pub respawn_at: HashMap<Team, u64>,
+}
+
+#[derive(Bundle)]
+pub struct CourierBundle {
+    pub unit: Unit,
+    pub courier: Courier,
+    pub shared_control: SharedControl,
+    pub team: Team,
+    pub position: Position,
+    pub move_speed: MoveSpeed,
+    pub move_target: MoveTarget,
+    pub health: Health,
+    pub inventory: Inventory,
+    pub vision: Vision,
+}
+
+impl Default for CourierRespawns {
+    fn default() -> Self {
+        Self {
+            respawn_at: HashMap::from_iter([(Team::Radiant, 0), (Team::Dire, 0)]),
+        }
+    }
+}
+
+impl Courier {
+    /// Starts a speed burst, unless the last one is still on cooldown.
+    pub fn burst(&mut self, tick: Tick, tick_rate: TickRate) -> bool {
+        if tick.0 < self.burst_ready_at {
+            return false;
+        }
+        self.burst_until = tick.0 + COURIER_BURST_SECS * tick_rate.0 as u64;
+        self.burst_ready_at = tick.0 + COURIER_BURST_COOLDOWN_SECS * tick_rate.0 as u64;
+        true
+    }
+}
+
+impl CourierBundle {
+    pub fn new(team: Team) -> Self {
+        Self {
+            unit: Unit,
+            courier: Default::default(),
+            shared_control: SharedControl,
+            team,
+            position: Position(team.fountain()),
+            move_speed: MoveSpeed(COURIER_MOVE_SPEED),
+            move_target: Default::default(),
+            health: Health::full(COURIER_HEALTH),
+            inventory: Default::default(),
+            vision: Vision {
+                day: COURIER_VISION,
+                night: COURIER_VISION,
+            },
+        }
+    }
+}
+
+pub fn spawn_couriers(
+    mut commands: Commands,
+    mut respawns: ResMut<CourierRespawns>,
+    tick: Res<Tick>,
+) {
+    for team in [Team::Radiant, Team::Dire] {
+        if respawns
+            .respawn_at
+            .get(&team)
+            .is_some_and(|respawn_at| *respawn_at <= tick.0)
+        {
+            respawns.respawn_at.remove(&team);
+            commands.spawn(CourierBundle::new(team));
+        }
+    }
+}
+
+pub fn schedule_courier_respawns(
+    mut died_events: EventReader<UnitDied>,
+    mut respawns: ResMut<CourierRespawns>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    courier_query: Query<&Team, With<Courier>>,
+) {
+    for event in died_events.iter() {
+        if let Ok(team) = courier_query.get(event.unit) {
+            let respawn_at = tick.0 + COURIER_RESPAWN_SECS * tick_rate.0 as u64;
+            respawns.respawn_at.insert(*team, respawn_at);
+        }
+    }
+}
+
+type CourierQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Courier,
+        &'static Team,
+        &'static Position,
+        &'static mut MoveTarget,
+        &'static mut MoveSpeed,
+        &'static mut Inventory,
+    ),
+    Without<Hero>,
+>;
+
+type StashQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Owner,
+        &'static Position,
+        &'static mut Inventory,
+        &'static mut Stash,
+    ),
+    With<Hero>,
+>;
+
+/// Carries out courier tasks: loading and unloading at the fountain and handing items to heroes.
+pub fn run_couriers(tick: Res<Tick>, mut courier_query: CourierQuery, mut hero_query: StashQuery) {
+    for (mut courier, team, position, mut move_target, mut move_speed, mut inventory) in
+        &mut courier_query
+    {
+        move_speed.0 = if tick.0 < courier.burst_until {
+            COURIER_BURST_MOVE_SPEED
+        } else {
+            COURIER_MOVE_SPEED
+        };
+
+        let fountain = team.fountain();
+        let at_base = position.0.distance(fountain) <= STASH_RANGE;
+        if at_base {
+            // Items carried for someone else than the courier delivers to go back to their stash.
+            if let Some(carrying_for) = courier.carrying_for {
+                if courier.task != CourierTask::Deliver(carrying_for) {
+                    if let Some((.., mut stash)) = hero_query
+                        .iter_mut()
+                        .find(|(owner, ..)| owner.0 == carrying_for)
+                    {
+                        inventory.move_into(&mut stash.0);
+                    }
+                    if inventory.is_empty() {
+                        courier.carrying_for = None;
+                    }
+                }
+            }
+            if let CourierTask::Deliver(player) = courier.task {
+                let free = courier.carrying_for.is_none() || courier.carrying_for == Some(player);
+                if let Some((.., mut stash)) = hero_query
+                    .iter_mut()
+                    .find(|(owner, ..)| free && owner.0 == player)
+                {
+                    stash.0.move_into(&mut inventory);
+                    if !inventory.is_empty() {
+                        courier.carrying_for = Some(player);
+                    }
+                }
+            }
+        }
+
+        match courier.task {
+            CourierTask::Idle => (),
+            CourierTask::ReturnToBase if at_base => {
+                courier.task = CourierTask::Idle;
+                move_target.0 = None;
+            }
+            CourierTask::ReturnToBase => move_target.0 = Some(fountain),
+            CourierTask::Deliver(player) if courier.carrying_for != Some(player) => {
+                if at_base {
+                    // Nothing in the stash to deliver.
+                    courier.task = CourierTask::Idle;
+                    move_target.0 = None;
+                } else {
+                    move_target.0 = Some(fountain);
+                }
+            }
+            CourierTask::Deliver(player) => {
+                let hero = hero_query.iter_mut().find(|(owner, ..)| owner.0 == player);
+                let Some((_, hero_position, mut hero_inventory, _)) = hero else {
+                    courier.task = CourierTask::ReturnToBase;
+                    continue;
+                };
+                if position.0.distance(hero_position.0) > COURIER_TRANSFER_RANGE {
+                    move_target.0 = Some(hero_position.0);
+                    continue;
+                }
+                inventory.move_into(&mut hero_inventory);
+                if inventory.is_empty() {
+                    courier.carrying_for = None;
+                }
+                courier.task = CourierTask::ReturnToBase;
+                move_target.0 = Some(fountain);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{item::Item, order::move_units, tree::Forest, unit::HeroBundle};
+
+    const TICK_RATE: u32 = 10;
+
+    fn run_until(world: &mut World, schedule: &mut Schedule, done: impl Fn(&mut World) -> bool) {
+        for _ in 0..100 * TICK_RATE {
+            world.resource_mut::<Tick>().0 += 1;
+            schedule.run(world);
+            if done(world) {
+                return;
+            }
+        }
+        panic!("the courier never got there");
+    }
+
+    #[test]
+    fn test_courier_delivers_and_returns() {
+        let mut world = World::new();
+        world.insert_resource(TickRate(TICK_RATE));
+        world.init_resource::<Tick>();
+        world.init_resource::<Forest>();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((run_couriers, move_units).chain());
+
+        let courier = world.spawn(CourierBundle::new(Team::Radiant)).id();
+        let [first, second] = [PlayerId(1), PlayerId(2)].map(|player| {
+            let mut hero = HeroBundle::new(player, Team::Radiant);
+            hero.position.0 = Vec2::new(100.0, 100.0);
+            world.spawn(hero).id()
+        });
+        world.get_mut::<Stash>(first).unwrap().0.add(Item::Aegis);
+        world
+            .get_mut::<Stash>(second)
+            .unwrap()
+            .0
+            .add(Item::Bottle(None));
+
+        world.get_mut::<Courier>(courier).unwrap().task = CourierTask::Deliver(PlayerId(1));
+        run_until(&mut world, &mut schedule, |world| {
+            world.get::<Inventory>(first).unwrap().contains(Item::Aegis)
+        });
+        assert!(world.get::<Stash>(first).unwrap().0.is_empty());
+        assert_eq!(
+            world.get::<Courier>(courier).unwrap().task,
+            CourierTask::ReturnToBase
+        );
+
+        // Back at the fountain, then off with the second player's items.
+        run_until(&mut world, &mut schedule, |world| {
+            world.get::<Courier>(courier).unwrap().task == CourierTask::Idle
+        });
+        world.get_mut::<Courier>(courier).unwrap().task = CourierTask::Deliver(PlayerId(2));
+        let bottles = |world: &World| {
+            world
+                .get::<Inventory>(second)
+                .unwrap()
+                .items()
+                .filter(|item| *item == Item::Bottle(None))
+                .count()
+        };
+        run_until(&mut world, &mut schedule, |world| bottles(world) == 2);
+        assert_eq!(world.get::<Courier>(courier).unwrap().carrying_for, None);
+    }
+
+    #[test]
+    fn test_speed_burst_has_a_cooldown() {
+        let mut courier = Courier::default();
+        let tick_rate = TickRate(TICK_RATE);
+        assert!(courier.burst(Tick(5), tick_rate));
+        let ticks = |secs| secs * TICK_RATE as u64;
+        assert_eq!(courier.burst_until, 5 + ticks(COURIER_BURST_SECS));
+        assert!(!courier.burst(Tick(6), tick_rate));
+        assert!(courier.burst(Tick(5 + ticks(COURIER_BURST_COOLDOWN_SECS)), tick_rate));
+    }
+}
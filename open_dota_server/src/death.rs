@@ -0,0 +1,454 @@
+//! Hero deaths. Dead heroes wait at their fountain for a respawn timer that grows with their
+//! level, and lose some of their unreliable gold. The killer gets a bounty and allied heroes close
+//! by get assist gold, while the experience is split between all of them. Dead heroes may buy
+//! back to respawn right away, which has a cooldown.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::{AttackTarget, Health, UnitDied},
+    item::{Inventory, Item},
+    order::{Order, OrderApplied},
+    player::{PlayerId, Team},
+    rune::{RuneBuff, RuneTarget},
+    simulation::{Tick, TickRate},
+    tree::TreeTarget,
+    unit::{Experience, Gold, Hero, MoveTarget, Owner, Position},
+    ward::WardTarget,
+};
+
+pub const RESPAWN_SECS: u64 = 5;
+pub const RESPAWN_SECS_PER_LEVEL: u64 = 2;
+/// Unreliable gold lost on death, per level.
+pub const DEATH_GOLD_LOSS_PER_LEVEL: u32 = 30;
+pub const HERO_BOUNTY_GOLD: u32 = 150;
+pub const HERO_BOUNTY_GOLD_PER_LEVEL: u32 = 10;
+/// Experience for a kill, split between the killer and the assisting heroes.
+pub const HERO_BOUNTY_EXPERIENCE: u32 = 100;
+pub const HERO_BOUNTY_EXPERIENCE_PER_LEVEL: u32 = 40;
+pub const ASSIST_GOLD: u32 = 50;
+pub const ASSIST_GOLD_PER_LEVEL: u32 = 5;
+/// How close to the dead hero allies of the killer have to be to assist.
+pub const ASSIST_RANGE: f32 = 500.0;
+pub const BUYBACK_COST: u32 = 200;
+pub const BUYBACK_COST_PER_LEVEL: u32 = 50;
+pub const BUYBACK_COOLDOWN_SECS: u64 = 240;
+
+/// When a hero comes back to life, and when it may buy back again.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Respawn {
+    /// The tick a dead hero respawns at, `None` while it is alive.
+    pub at: Option<u64>,
+    pub buyback_ready_at: u64,
+}
+
+/// A hero died for good, rather than being revived.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeroKilled {
+    pub hero: Entity,
+    pub killer: Entity,
+    pub team: Team,
+    pub level: u32,
+    /// Where the hero died.
+    pub position: Vec2,
+}
+
+/// The match second a dead hero respawns at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RespawnTimer {
+    pub player: PlayerId,
+    pub respawn_at: u64,
+}
+
+impl Respawn {
+    pub fn is_dead(&self) -> bool {
+        self.at.is_some()
+    }
+}
+
+pub fn respawn_secs(level: u32) -> u64 {
+    RESPAWN_SECS + RESPAWN_SECS_PER_LEVEL * level as u64
+}
+
+pub fn buyback_cost(level: u32) -> u32 {
+    BUYBACK_COST + BUYBACK_COST_PER_LEVEL * level
+}
+
+type DyingHeroQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Team,
+        &'static Experience,
+        &'static mut Position,
+        &'static mut Health,
+        &'static mut MoveTarget,
+        &'static mut AttackTarget,
+        &'static mut Inventory,
+        &'static mut Gold,
+        &'static mut Respawn,
+        &'static mut RuneBuff,
+        Option<&'static mut RuneTarget>,
+        Option<&'static mut WardTarget>,
+        Option<&'static mut TreeTarget>,
+    ),
+    With<Hero>,
+>;
+
+/// Heroes that died are revived where they fell if they hold an item that revives them. Others
+/// are sent to their fountain to wait for their respawn timer, and lose unreliable gold.
+pub fn kill_heroes(
+    mut died_events: EventReader<UnitDied>,
+    mut killed_events: EventWriter<HeroKilled>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    mut hero_query: DyingHeroQuery,
+) {
+    for event in died_events.iter() {
+        let Ok((
+            team,
+            experience,
+            mut position,
+            mut health,
+            mut move_target,
+            mut attack_target,
+            mut inventory,
+            mut gold,
+            mut respawn,
+            mut buff,
+            rune_target,
+            ward_target,
+            tree_target,
+        )) = hero_query.get_mut(event.unit)
+        else {
+            continue;
+        };
+        move_target.0 = None;
+        attack_target.0 = None;
+        if inventory.take(Item::grants_revive).is_some() {
+            health.current = health.max;
+            continue;
+        }
+
+        let level = experience.level();
+        killed_events.send(HeroKilled {
+            hero: event.unit,
+            killer: event.killer,
+            team: *team,
+            level,
+            position: position.0,
+        });
+        position.0 = team.fountain();
+        respawn.at = Some(tick.0 + respawn_secs(level) * tick_rate.0 as u64);
+        gold.unreliable -= gold.unreliable.min(DEATH_GOLD_LOSS_PER_LEVEL * level);
+        buff.0 = None;
+        if let Some(mut rune_target) = rune_target {
+            rune_target.0 = None;
+        }
+        if let Some(mut ward_target) = ward_target {
+            ward_target.0 = None;
+        }
+        if let Some(mut tree_target) = tree_target {
+            tree_target.0 = None;
+        }
+    }
+}
+
+type RewardedHeroQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Owner,
+        &'static Team,
+        &'static Position,
+        &'static Respawn,
+        &'static mut Gold,
+        &'static mut Experience,
+    ),
+    With<Hero>,
+>;
+
+/// Gives the killing player's hero the bounty, and living heroes of its team close to the kill
+/// assist gold. All of them share the experience. Heroes denied by their own team give nothing.
+pub fn reward_hero_kills(
+    mut killed_events: EventReader<HeroKilled>,
+    killer_query: Query<(&Owner, &Team)>,
+    mut hero_query: RewardedHeroQuery,
+) {
+    for event in killed_events.iter() {
+        let Ok((killer, killer_team)) = killer_query.get(event.killer) else {
+            continue;
+        };
+        if *killer_team == event.team {
+            continue;
+        }
+        let mut heroes = hero_query
+            .iter_mut()
+            .filter(|(owner, team, position, respawn, ..)| {
+                *team == killer_team
+                    && (owner.0 == killer.0
+                        || !respawn.is_dead()
+                            && position.0.distance(event.position) <= ASSIST_RANGE)
+            })
+            .collect::<Vec<_>>();
+        if heroes.is_empty() {
+            continue;
+        }
+        let experience = (HERO_BOUNTY_EXPERIENCE + HERO_BOUNTY_EXPERIENCE_PER_LEVEL * event.level)
+            / heroes.len() as u32;
+        for (owner, _, _, _, gold, hero_experience) in &mut heroes {
+            if owner.0 == killer.0 {
+                gold.reliable += HERO_BOUNTY_GOLD + HERO_BOUNTY_GOLD_PER_LEVEL * event.level;
+            } else {
+                gold.reliable += ASSIST_GOLD + ASSIST_GOLD_PER_LEVEL * event.level;
+            }
+            hero_experience.0 += experience;
+        }
+    }
+}
+
+/// Dead heroes ordered to buy back respawn right away, if they can afford it and buyback is off
+/// cooldown.
+pub fn buy_back(
+    mut applied_events: EventReader<OrderApplied>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    mut hero_query: Query<(&Experience, &mut Gold, &mut Health, &mut Respawn), With<Hero>>,
+) {
+    for event in applied_events.iter() {
+        if event.0.order != Order::Buyback {
+            continue;
+        }
+        let Ok((experience, mut gold, mut health, mut respawn)) =
+            hero_query.get_mut(event.0.unit.entity())
+        else {
+            continue;
+        };
+        if !respawn.is_dead() || tick.0 < respawn.buyback_ready_at {
+            continue;
+        }
+        if !gold.spend(buyback_cost(experience.level())) {
+            continue;
+        }
+        respawn.at = None;
+        respawn.buyback_ready_at = tick.0 + BUYBACK_COOLDOWN_SECS * tick_rate.0 as u64;
+        health.current = health.max;
+    }
+}
+
+pub fn respawn_heroes(tick: Res<Tick>, mut hero_query: Query<(&mut Respawn, &mut Health)>) {
+    for (mut respawn, mut health) in &mut hero_query {
+        if respawn.at.is_some_and(|at| tick.0 >= at) {
+            respawn.at = None;
+            health.current = health.max;
+        }
+    }
+}
+
+/// The respawn timers of all dead heroes, by player.
+pub fn respawn_timers<'a>(
+    heroes: impl Iterator<Item = (&'a Owner, &'a Respawn)>,
+    tick_rate: TickRate,
+) -> Vec<RespawnTimer> {
+    let mut timers = heroes
+        .filter_map(|(owner, respawn)| {
+            Some(RespawnTimer {
+                player: owner.0,
+                respawn_at: respawn.at?.div_ceil(tick_rate.0 as u64),
+            })
+        })
+        .collect::<Vec<_>>();
+    timers.sort_by_key(|timer| timer.player);
+    timers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combat::{apply_damage, remove_dead_units, Damaged},
+        order::{apply_orders, OrderIssued},
+        tree::Forest,
+        unit::HeroBundle,
+        vision::TeamVision,
+    };
+
+    const TICK_RATE: u32 = 10;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(TickRate(TICK_RATE));
+        world.insert_resource(Tick(1));
+        world.init_resource::<Events<Damaged>>();
+        world.init_resource::<Events<UnitDied>>();
+        world.init_resource::<Events<HeroKilled>>();
+        world.init_resource::<Events<OrderIssued>>();
+        world.init_resource::<Events<OrderApplied>>();
+        world
+    }
+
+    fn hero(world: &mut World, player: u32, team: Team, position: Vec2) -> Entity {
+        let mut hero = HeroBundle::new(PlayerId(player), team);
+        hero.position.0 = position;
+        world.spawn(hero).id()
+    }
+
+    fn kill(world: &mut World, schedule: &mut Schedule, killer: Entity, target: Entity) {
+        world.send_event(Damaged {
+            source: killer,
+            target,
+            amount: f32::MAX,
+        });
+        schedule.run(world);
+        world.resource_mut::<Events<Damaged>>().update();
+        world.resource_mut::<Events<UnitDied>>().update();
+        world.resource_mut::<Events<HeroKilled>>().update();
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(Experience(0).level(), 1);
+        assert_eq!(Experience(199).level(), 1);
+        assert_eq!(Experience(200).level(), 2);
+        assert_eq!(Experience(u32::MAX).level(), 10);
+    }
+
+    #[test]
+    fn test_hero_kills_give_bounties_and_start_respawn_timers() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_systems(
+            (
+                respawn_heroes,
+                apply_damage,
+                kill_heroes,
+                reward_hero_kills,
+                remove_dead_units,
+            )
+                .chain(),
+        );
+        let victim = hero(&mut world, 1, Team::Radiant, Vec2::ZERO);
+        world.get_mut::<Experience>(victim).unwrap().0 = 500;
+        *world.get_mut::<Gold>(victim).unwrap() = Gold {
+            reliable: 100,
+            unreliable: 100,
+        };
+        let killer = hero(&mut world, 2, Team::Dire, Vec2::new(100.0, 0.0));
+        let assist = hero(&mut world, 3, Team::Dire, Vec2::new(0.0, 400.0));
+        let far = hero(&mut world, 4, Team::Dire, Vec2::new(0.0, 600.0));
+
+        kill(&mut world, &mut schedule, killer, victim);
+        // A level 3 hero: 11s to respawn, 90 unreliable gold lost.
+        let respawn = world.get::<Respawn>(victim).unwrap();
+        assert_eq!(respawn.at, Some(1 + 11 * TICK_RATE as u64));
+        assert_eq!(
+            *world.get::<Gold>(victim).unwrap(),
+            Gold {
+                reliable: 100,
+                unreliable: 10,
+            }
+        );
+        assert_eq!(
+            world.get::<Position>(victim).unwrap().0,
+            Team::Radiant.fountain()
+        );
+        assert_eq!(world.get::<Gold>(killer).unwrap().reliable, 180);
+        assert_eq!(world.get::<Gold>(assist).unwrap().reliable, 65);
+        assert_eq!(world.get::<Gold>(far).unwrap().reliable, 0);
+        assert_eq!(world.get::<Experience>(killer).unwrap().0, 110);
+        assert_eq!(world.get::<Experience>(assist).unwrap().0, 110);
+        assert_eq!(world.get::<Experience>(far).unwrap().0, 0);
+
+        world.insert_resource(Tick(1 + 11 * TICK_RATE as u64));
+        schedule.run(&mut world);
+        assert!(!world.get::<Respawn>(victim).unwrap().is_dead());
+        assert!(world.get::<Health>(victim).unwrap().is_full());
+
+        // Denies give nothing, and the Aegis saves its holder.
+        let ally = hero(&mut world, 5, Team::Radiant, Vec2::ZERO);
+        kill(&mut world, &mut schedule, ally, victim);
+        assert_eq!(world.get::<Gold>(ally).unwrap().reliable, 0);
+        world.get_mut::<Inventory>(ally).unwrap().add(Item::Aegis);
+        kill(&mut world, &mut schedule, killer, ally);
+        assert!(!world.get::<Respawn>(ally).unwrap().is_dead());
+        assert_eq!(world.get::<Gold>(killer).unwrap().reliable, 180);
+    }
+
+    #[test]
+    fn test_buyback_costs_gold_and_has_a_cooldown() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((apply_orders, buy_back).chain());
+        world.init_resource::<Forest>();
+        world.init_resource::<TeamVision>();
+        let hero = hero(&mut world, 1, Team::Radiant, Vec2::ZERO);
+        world.get_mut::<Gold>(hero).unwrap().unreliable = 300;
+        let mut buy_back = |world: &mut World| {
+            world.get_mut::<Respawn>(hero).unwrap().at = Some(1000);
+            world.send_event(OrderIssued {
+                player: PlayerId(1),
+                team: Team::Radiant,
+                unit: hero.into(),
+                order: Order::Buyback,
+            });
+            schedule.run(world);
+            world.resource_mut::<Events<OrderIssued>>().update();
+            world.resource_mut::<Events<OrderApplied>>().update();
+            world.get::<Respawn>(hero).unwrap().is_dead()
+        };
+
+        assert!(!buy_back(&mut world));
+        assert_eq!(world.get::<Gold>(hero).unwrap().total(), 50);
+        assert_eq!(
+            world.get::<Respawn>(hero).unwrap().buyback_ready_at,
+            1 + BUYBACK_COOLDOWN_SECS * TICK_RATE as u64
+        );
+        world.get_mut::<Gold>(hero).unwrap().unreliable = 300;
+        assert!(buy_back(&mut world));
+        world.insert_resource(Tick(1 + BUYBACK_COOLDOWN_SECS * TICK_RATE as u64));
+        assert!(!buy_back(&mut world));
+
+        world.get_mut::<Gold>(hero).unwrap().unreliable = 100;
+        world.insert_resource(Tick(10_000));
+        assert!(buy_back(&mut world));
+    }
+
+    #[test]
+    fn test_respawn_timers_round_up_to_seconds() {
+        let respawns = [
+            (Owner(PlayerId(2)), Respawn::default()),
+            (
+                Owner(PlayerId(3)),
+                Respawn {
+                    at: Some(21),
+                    buyback_ready_at: 0,
+                },
+            ),
+            (
+                Owner(PlayerId(1)),
+                Respawn {
+                    at: Some(50),
+                    buyback_ready_at: 0,
+                },
+            ),
+        ];
+        let timers = respawn_timers(
+            respawns.iter().map(|(owner, respawn)| (owner, respawn)),
+            TickRate(TICK_RATE),
+        );
+        assert_eq!(
+            timers,
+            [
+                RespawnTimer {
+                    player: PlayerId(1),
+                    respawn_at: 5,
+                },
+                RespawnTimer {
+                    player: PlayerId(3),
+                    respawn_at: 3,
+                },
+            ]
+        );
+    }
+}
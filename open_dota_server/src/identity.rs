@@ -0,0 +1,198 @@
+use std::{fmt, str::FromStr};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
+use ed25519_dalek::{Signer, Verifier, VerifyingKey};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+pub use ed25519_dalek::{Signature, SigningKey};
+
+pub const MAX_NAME_LEN: usize = 32;
+const CHALLENGE_CONTEXT: &[u8] = b"open_dota join challenge v1:";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HexError {
+    #[error("expected {expected} hex characters, got {actual}")]
+    Length { expected: usize, actual: usize },
+    #[error("invalid hex digit in '{0}'")]
+    Digit(String),
+}
+
+/// A player's ed25519 public key. Players generate their key pair locally and prove they own it
+/// by signing a [`Challenge`] whenever they join, so it identifies them across sessions.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Identity(pub [u8; 32]);
+
+/// Random bytes the server hands every new connection for the client to sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Challenge(pub [u8; 32]);
+
+/// Challenges issued to connected clients that have not joined yet.
+#[derive(Resource, Debug, Default)]
+pub struct PendingChallenges(HashMap<ClientId, Challenge>);
+
+impl Identity {
+    pub fn of(key: &SigningKey) -> Self {
+        Self(key.verifying_key().to_bytes())
+    }
+
+    pub fn verify(&self, challenge: &Challenge, signature: &Signature) -> bool {
+        VerifyingKey::from_bytes(&self.0)
+            .is_ok_and(|key| key.verify(&challenge.message(), signature).is_ok())
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&encode_hex(&self.0))
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Identity({self})")
+    }
+}
+
+impl FromStr for Identity {
+    type Err = HexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode_hex(s).map(Self)
+    }
+}
+
+// Config and ban files show identities as hex, network messages keep them as raw bytes.
+impl Serialize for Identity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Identity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer)?
+                .parse()
+                .map_err(serde::de::Error::custom)
+        } else {
+            <[u8; 32]>::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
+impl Challenge {
+    pub fn generate() -> Self {
+        Self(rand::random())
+    }
+
+    pub fn sign(&self, key: &SigningKey) -> Signature {
+        key.sign(&self.message())
+    }
+
+    fn message(&self) -> Vec<u8> {
+        [CHALLENGE_CONTEXT, &self.0].concat()
+    }
+}
+
+impl PendingChallenges {
+    pub fn issue(&mut self, client_id: ClientId) -> Challenge {
+        let challenge = Challenge::generate();
+        self.0.insert(client_id, challenge);
+        challenge
+    }
+
+    /// Removes the client's challenge so that every signature is only accepted once.
+    pub fn take(&mut self, client_id: ClientId) -> Option<Challenge> {
+        self.0.remove(&client_id)
+    }
+}
+
+pub fn generate_key() -> SigningKey {
+    SigningKey::generate(&mut rand::rngs::OsRng)
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn decode_hex<const N: usize>(text: &str) -> Result<[u8; N], HexError> {
+    let text = text.trim();
+    if text.len() != N * 2 {
+        return Err(HexError::Length {
+            expected: N * 2,
+            actual: text.len(),
+        });
+    }
+    let mut bytes = [0; N];
+    for (byte, digits) in bytes.iter_mut().zip(text.as_bytes().chunks(2)) {
+        *byte = std::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| HexError::Digit(text.to_string()))?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_challenge() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let identity = Identity::of(&key);
+        let challenge = Challenge([1; 32]);
+        let signature = challenge.sign(&key);
+        assert!(identity.verify(&challenge, &signature));
+        assert!(!identity.verify(&Challenge([2; 32]), &signature));
+        let impostor = Identity::of(&SigningKey::from_bytes(&[8; 32]));
+        assert!(!impostor.verify(&challenge, &signature));
+    }
+
+    #[test]
+    fn test_challenges_are_single_use() {
+        let mut challenges = PendingChallenges::default();
+        let challenge = challenges.issue(1);
+        assert_eq!(challenges.take(1), Some(challenge));
+        assert_eq!(challenges.take(1), None);
+    }
+
+    #[test]
+    fn test_identity_hex() {
+        let identity = Identity::of(&SigningKey::from_bytes(&[7; 32]));
+        assert_eq!(identity.to_string().parse::<Identity>(), Ok(identity));
+        assert_eq!(
+            "abc".parse::<Identity>(),
+            Err(HexError::Length {
+                expected: 64,
+                actual: 3
+            })
+        );
+        assert!(matches!(
+            "zz".repeat(32).parse::<Identity>(),
+            Err(HexError::Digit(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("dark_seer-2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("two words"));
+        assert!(!is_valid_name(&"x".repeat(MAX_NAME_LEN + 1)));
+    }
+}
@@ -0,0 +1,103 @@
+//! Illusions are copies of a hero that fight like it but vanish once their time is up.
+
+use bevy::prelude::*;
+
+use crate::{
+    combat::{Attack, AttackTarget, Health},
+    player::{PlayerId, Team},
+    rune::{RuneActivated, RuneKind},
+    simulation::{Tick, TickRate},
+    unit::{
+        MoveSpeed, MoveTarget, Owner, Position, Unit, HERO_ATTACK_COOLDOWN, HERO_ATTACK_RANGE,
+        HERO_DAMAGE, HERO_DAY_VISION, HERO_HEALTH, HERO_MOVE_SPEED, HERO_NIGHT_VISION,
+        MAP_HALF_SIZE,
+    },
+    vision::Vision,
+};
+
+/// Where the illusions of an illusion rune appear, next to the hero.
+pub const ILLUSION_OFFSETS: [Vec2; 2] = [Vec2::new(-40.0, 0.0), Vec2::new(40.0, 0.0)];
+/// Fraction of their damage illusions' attacks deal.
+pub const ILLUSION_DAMAGE_DEALT: f32 = 0.35;
+/// Multiplier for the damage illusions take.
+pub const ILLUSION_DAMAGE_TAKEN: f32 = 3.0;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Illusion {
+    pub expires_at: u64,
+}
+
+#[derive(Bundle)]
+pub struct IllusionBundle {
+    pub unit: Unit,
+    pub illusion: Illusion,
+    pub owner: Owner,
+    pub team: Team,
+    pub position: Position,
+    pub move_speed: MoveSpeed,
+    pub move_target: MoveTarget,
+    pub health: Health,
+    pub attack: Attack,
+    pub attack_target: AttackTarget,
+    pub vision: Vision,
+}
+
+impl IllusionBundle {
+    pub fn new(owner: PlayerId, team: Team, position: Vec2, expires_at: u64) -> Self {
+        Self {
+            unit: Unit,
+            illusion: Illusion { expires_at },
+            owner: Owner(owner),
+            team,
+            position: Position(position.clamp(-MAP_HALF_SIZE, MAP_HALF_SIZE)),
+            move_speed: MoveSpeed(HERO_MOVE_SPEED),
+            move_target: Default::default(),
+            health: Health::full(HERO_HEALTH),
+            attack: Attack::new(HERO_DAMAGE, HERO_ATTACK_RANGE, HERO_ATTACK_COOLDOWN),
+            attack_target: Default::default(),
+            vision: Vision {
+                day: HERO_DAY_VISION,
+                night: HERO_NIGHT_VISION,
+            },
+        }
+    }
+}
+
+/// Conjures illusions for illusion runes.
+pub fn conjure_illusions(
+    mut commands: Commands,
+    mut activated_events: EventReader<RuneActivated>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    hero_query: Query<(&Owner, &Team, &Position)>,
+) {
+    for event in activated_events.iter() {
+        if event.kind != RuneKind::Illusion {
+            continue;
+        }
+        let Ok((owner, team, position)) = hero_query.get(event.unit) else {
+            continue;
+        };
+        let expires_at = tick.0 + RuneKind::Illusion.duration_secs() * tick_rate.0 as u64;
+        for offset in ILLUSION_OFFSETS {
+            commands.spawn(IllusionBundle::new(
+                owner.0,
+                *team,
+                position.0 + offset,
+                expires_at,
+            ));
+        }
+    }
+}
+
+pub fn expire_illusions(
+    mut commands: Commands,
+    tick: Res<Tick>,
+    illusion_query: Query<(Entity, &Illusion)>,
+) {
+    for (entity, illusion) in &illusion_query {
+        if tick.0 >= illusion.expires_at {
+            commands.entity(entity).despawn();
+        }
+    }
+}
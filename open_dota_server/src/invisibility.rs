@@ -0,0 +1,147 @@
+//! Invisible units are left out of what the enemy team is sent, unless a true sight source of
+//! theirs is close enough. Units fade out over [`INVISIBILITY_FADE_SECS`] first, and attacking
+//! ends their invisibility.
+
+use bevy::prelude::*;
+
+use crate::{
+    combat::Damaged,
+    item::Inventory,
+    rune::{RuneBuff, RuneKind},
+    simulation::{Tick, TickRate},
+};
+
+pub const INVISIBILITY_FADE_SECS: u64 = 1;
+
+/// Whether a unit that can turn invisible currently is.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Invisibility {
+    #[default]
+    Visible,
+    /// Still seen until `invisible_at`.
+    Fading {
+        invisible_at: u64,
+    },
+    Invisible,
+}
+
+/// Reveals invisible enemies within `range`, as long as the team also has vision of them.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct TrueSight {
+    pub range: f32,
+}
+
+impl Invisibility {
+    pub fn is_invisible(&self) -> bool {
+        *self == Self::Invisible
+    }
+}
+
+/// How far a unit with the given [`TrueSight`] and items detects invisible units, if at all.
+pub fn true_sight_range(
+    true_sight: Option<&TrueSight>,
+    inventory: Option<&Inventory>,
+) -> Option<f32> {
+    let items = inventory
+        .into_iter()
+        .flat_map(Inventory::items)
+        .filter_map(|item| item.true_sight_range());
+    true_sight
+        .map(|true_sight| true_sight.range)
+        .into_iter()
+        .chain(items)
+        .reduce(f32::max)
+}
+
+/// Fades units under an invisibility rune out, and ends the rune for those that attacked.
+pub fn update_invisibility(
+    mut damaged_events: EventReader<Damaged>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    mut unit_query: Query<(&mut Invisibility, &mut RuneBuff)>,
+) {
+    for event in damaged_events.iter() {
+        if let Ok((mut invisibility, mut buff)) = unit_query.get_mut(event.source) {
+            if buff.is(RuneKind::Invisibility) {
+                buff.0 = None;
+                *invisibility = Invisibility::Visible;
+            }
+        }
+    }
+
+    for (mut invisibility, buff) in &mut unit_query {
+        let next = match *invisibility {
+            _ if !buff.is(RuneKind::Invisibility) => Invisibility::Visible,
+            Invisibility::Visible => Invisibility::Fading {
+                invisible_at: tick.0 + INVISIBILITY_FADE_SECS * tick_rate.0 as u64,
+            },
+            Invisibility::Fading { invisible_at } if tick.0 >= invisible_at => {
+                Invisibility::Invisible
+            }
+            current => current,
+        };
+        if *invisibility != next {
+            *invisibility = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        item::Item,
+        player::{PlayerId, Team},
+        rune::ActiveRune,
+        unit::HeroBundle,
+    };
+
+    #[test]
+    fn test_invisibility_fades_in_and_breaks_on_attack() {
+        let mut world = World::new();
+        world.insert_resource(TickRate(10));
+        world.init_resource::<Tick>();
+        world.init_resource::<Events<Damaged>>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(update_invisibility);
+
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        world.get_mut::<RuneBuff>(hero).unwrap().0 = Some(ActiveRune {
+            kind: RuneKind::Invisibility,
+            expires_at: u64::MAX,
+        });
+        schedule.run(&mut world);
+        assert_eq!(
+            *world.get::<Invisibility>(hero).unwrap(),
+            Invisibility::Fading { invisible_at: 10 }
+        );
+        world.resource_mut::<Tick>().0 = 10;
+        schedule.run(&mut world);
+        assert!(world.get::<Invisibility>(hero).unwrap().is_invisible());
+
+        world.send_event(Damaged {
+            source: hero,
+            target: hero,
+            amount: 0.0,
+        });
+        schedule.run(&mut world);
+        assert_eq!(
+            *world.get::<Invisibility>(hero).unwrap(),
+            Invisibility::Visible
+        );
+        assert_eq!(*world.get::<RuneBuff>(hero).unwrap(), RuneBuff(None));
+    }
+
+    #[test]
+    fn test_true_sight_range() {
+        let mut inventory = Inventory::default();
+        inventory.add(Item::Gem);
+        assert_eq!(
+            true_sight_range(Some(&TrueSight { range: 900.0 }), Some(&inventory)),
+            Some(900.0)
+        );
+        assert_eq!(true_sight_range(None, None), None);
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    player::Team,
+    rune::RuneKind,
+    unit::{Hero, Position},
+    ward::WardKind,
+};
+
+pub const INVENTORY_SLOTS: usize = 6;
+/// How close to their fountain units have to be to reach the stash.
+pub const STASH_RANGE: f32 = 120.0;
+pub const GEM_TRUE_SIGHT_RANGE: f32 = 500.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Item {
+    /// Dropped by the boss. Brings its holder back to life where they died, once.
+    Aegis,
+    /// Holds a power-up rune to activate later.
+    Bottle(Option<RuneKind>),
+    /// Gives its holder true sight.
+    Gem,
+    /// Cuts down trees.
+    QuellingBlade,
+    Ward(WardKind),
+}
+
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inventory {
+    pub slots: [Option<Item>; INVENTORY_SLOTS],
+}
+
+/// Items of a hero waiting at its fountain, until the hero comes back or a courier picks them up.
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stash(pub Inventory);
+
+impl Item {
+    /// Only one of these may exist in the match at a time.
+    pub fn is_unique(self) -> bool {
+        match self {
+            Self::Aegis => true,
+            Self::Bottle(_) | Self::Gem | Self::QuellingBlade | Self::Ward(_) => false,
+        }
+    }
+
+    pub fn grants_revive(self) -> bool {
+        match self {
+            Self::Aegis => true,
+            Self::Bottle(_) | Self::Gem | Self::QuellingBlade | Self::Ward(_) => false,
+        }
+    }
+
+    /// How far the holder detects invisible units.
+    pub fn true_sight_range(self) -> Option<f32> {
+        match self {
+            Self::Gem => Some(GEM_TRUE_SIGHT_RANGE),
+            Self::Aegis | Self::Bottle(_) | Self::QuellingBlade | Self::Ward(_) => None,
+        }
+    }
+}
+
+impl Inventory {
+    /// Puts `item` in the first free slot, returning whether there was one.
+    pub fn add(&mut self, item: Item) -> bool {
+        let Some(slot) = self.slots.iter_mut().find(|slot| slot.is_none()) else {
+            return false;
+        };
+        *slot = Some(item);
+        true
+    }
+
+    pub fn contains(&self, item: Item) -> bool {
+        self.slots.contains(&Some(item))
+    }
+
+    /// Removes and returns the first item matching `predicate`.
+    pub fn take(&mut self, predicate: impl Fn(Item) -> bool) -> Option<Item> {
+        self.slots
+            .iter_mut()
+            .find(|slot| slot.is_some_and(&predicate))
+            .and_then(Option::take)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = Item> + '_ {
+        self.slots.iter().flatten().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items().next().is_none()
+    }
+
+    /// Moves as many items as fit into `other`, in slot order.
+    pub fn move_into(&mut self, other: &mut Inventory) {
+        for slot in &mut self.slots {
+            let Some(item) = *slot else {
+                continue;
+            };
+            if !other.add(item) {
+                return;
+            }
+            *slot = None;
+        }
+    }
+}
+
+/// Heroes at their fountain take everything that fits out of their stash.
+pub fn collect_stashes(
+    mut hero_query: Query<(&Team, &Position, &mut Inventory, &mut Stash), With<Hero>>,
+) {
+    for (team, position, mut inventory, mut stash) in &mut hero_query {
+        if !stash.0.is_empty() && position.0.distance(team.fountain()) <= STASH_RANGE {
+            stash.0.move_into(&mut inventory);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inventory_slots() {
+        let mut inventory = Inventory::default();
+        for _ in 0..INVENTORY_SLOTS {
+            assert!(inventory.add(Item::Aegis));
+        }
+        assert!(!inventory.add(Item::Aegis));
+        assert_eq!(inventory.take(Item::grants_revive), Some(Item::Aegis));
+        assert_eq!(inventory.items().count(), INVENTORY_SLOTS - 1);
+        assert_eq!(inventory.slots[0], None);
+        assert!(inventory.add(Item::Aegis));
+        assert_eq!(inventory.slots[0], Some(Item::Aegis));
+    }
+
+    #[test]
+    fn test_move_into_keeps_what_does_not_fit() {
+        let mut from = Inventory::default();
+        from.slots[1] = Some(Item::Bottle(None));
+        from.slots[4] = Some(Item::Aegis);
+        let mut to = Inventory::default();
+        for _ in 0..INVENTORY_SLOTS - 1 {
+            to.add(Item::Bottle(None));
+        }
+        from.move_into(&mut to);
+        assert_eq!(to.slots[INVENTORY_SLOTS - 1], Some(Item::Bottle(None)));
+        assert_eq!(from.items().collect::<Vec<_>>(), [Item::Aegis]);
+
+        let mut empty = Inventory::default();
+        from.move_into(&mut empty);
+        assert!(from.is_empty());
+        assert!(empty.contains(Item::Aegis));
+    }
+}
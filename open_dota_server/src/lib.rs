@@ -1,14 +1,134 @@
+pub mod access;
+pub mod boss;
+pub mod clock;
+pub mod combat;
+pub mod communication;
+pub mod config;
+pub mod courier;
+pub mod death;
+pub mod identity;
+pub mod illusion;
+pub mod invisibility;
+pub mod item;
+pub mod limits;
+pub mod neutral;
+pub mod order;
+pub mod playback;
+pub mod player;
+pub mod prd;
+pub mod replay;
+pub mod rune;
+pub mod server;
+pub mod simulation;
+pub mod snapshot;
+pub mod spectator;
+pub mod tree;
+pub mod unit;
+pub mod vision;
+pub mod ward;
+
+use bevy::prelude::Vec2;
 use serde::{Deserialize, Serialize};
 
+use communication::{ChatWheelPhrase, PingKind};
+use death::RespawnTimer;
+use identity::{Challenge, Identity, Signature};
+use item::Item;
+use order::Order;
+use player::{JoinRejection, JoinRole, PlayerId, Team};
+use snapshot::Snapshot;
+use spectator::SpectatorView;
+use unit::UnitId;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ClientMessage {
-    Join,
+    /// Joins the match, or reclaims the slot `identity` already holds. `signature` signs the
+    /// challenge the server sent when the connection opened.
+    Join {
+        name: String,
+        identity: Identity,
+        signature: Signature,
+        password: Option<String>,
+        role: JoinRole,
+    },
     Leave,
-    ChatMessage { message: String },
+    ChatMessage {
+        message: String,
+    },
+    ChatWheel {
+        phrase: ChatWheelPhrase,
+    },
+    Ping {
+        kind: PingKind,
+        position: Vec2,
+    },
+    Order {
+        unit: UnitId,
+        order: Order,
+    },
+    /// Switches whose vision a spectator watches with.
+    SpectatorView {
+        view: SpectatorView,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ServerMessage {
-    InitClient,
-    ChatMessage { message: String },
+    Challenge(Challenge),
+    InitClient {
+        player_id: PlayerId,
+        team: Team,
+    },
+    /// Sent instead of `InitClient` to spectators, whose snapshots trail the match by
+    /// `delay_secs`.
+    InitSpectator {
+        delay_secs: u64,
+    },
+    JoinRejected {
+        reason: JoinRejection,
+    },
+    /// Sent right before the server disconnects the client.
+    Kicked {
+        reason: String,
+    },
+    Snapshot(Snapshot),
+    PlayerDisconnected {
+        player: PlayerId,
+    },
+    PlayerReconnected {
+        player: PlayerId,
+    },
+    PlayerAbandoned {
+        player: PlayerId,
+    },
+    /// Sent to every player when the boss dies. `killer` and `team` are unset if a neutral got
+    /// the last hit.
+    BossKilled {
+        killer: Option<PlayerId>,
+        team: Option<Team>,
+        item: Option<Item>,
+    },
+    /// Sent to every player when a hero dies. `killer` is unset unless an enemy player got the
+    /// kill. `respawn_at` is the match second the hero comes back.
+    HeroKilled {
+        player: PlayerId,
+        killer: Option<PlayerId>,
+        respawn_at: u64,
+    },
+    /// The respawn timers of every dead hero, sent to every player whenever one starts or ends.
+    RespawnTimers {
+        timers: Vec<RespawnTimer>,
+    },
+    ChatMessage {
+        message: String,
+    },
+    ChatWheel {
+        sender: PlayerId,
+        phrase: ChatWheelPhrase,
+    },
+    Ping {
+        sender: PlayerId,
+        kind: PingKind,
+        position: Vec2,
+    },
 }
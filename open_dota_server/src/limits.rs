@@ -0,0 +1,365 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
+
+use crate::ClientMessage;
+
+pub const MAX_PAYLOAD_SIZE: usize = 4 * 1024;
+/// Messages read from one client per tick, anything beyond waits for the next tick.
+pub const MAX_MESSAGES_PER_TICK: usize = 64;
+pub const MAX_CHAT_LEN: usize = 256;
+pub const MAX_PASSWORD_LEN: usize = 128;
+/// Penalty a client may accumulate before it is disconnected, see [`DropReason::penalty`].
+const VIOLATION_BUDGET: RateLimit = RateLimit {
+    burst: 30.0,
+    per_second: 0.5,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessageCategory {
+    Session,
+    Chat,
+    Ping,
+    Order,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DropReason {
+    /// Larger than [`MAX_PAYLOAD_SIZE`].
+    Oversized,
+    /// Not a [`ClientMessage`] at all.
+    Malformed,
+    /// A well-formed message with out of range contents.
+    Invalid,
+    RateLimited,
+}
+
+#[derive(Debug)]
+pub enum Verdict {
+    Accept(ClientMessage),
+    Drop(DropReason),
+    /// The client has run out of violation budget and should be disconnected.
+    Disconnect(DropReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: f32,
+    pub per_second: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f32,
+    updated: Duration,
+}
+
+#[derive(Debug)]
+struct ClientLimits {
+    buckets: HashMap<MessageCategory, TokenBucket>,
+    violations: TokenBucket,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MessageMetrics {
+    pub accepted: BTreeMap<MessageCategory, u64>,
+    /// Dropped messages by category, which is unknown for messages that failed to parse.
+    pub dropped: BTreeMap<(Option<MessageCategory>, DropReason), u64>,
+    pub disconnects: u64,
+}
+
+/// Screens every payload a client sends before the server acts on it.
+#[derive(Resource, Debug, Default)]
+pub struct MessageGuard {
+    clients: HashMap<ClientId, ClientLimits>,
+    pub metrics: MessageMetrics,
+}
+
+impl MessageCategory {
+    pub fn rate_limit(self) -> RateLimit {
+        match self {
+            Self::Session => RateLimit {
+                burst: 3.0,
+                per_second: 0.2,
+            },
+            Self::Chat => RateLimit {
+                burst: 5.0,
+                per_second: 1.0,
+            },
+            Self::Ping => RateLimit {
+                burst: 5.0,
+                per_second: 2.0,
+            },
+            Self::Order => RateLimit {
+                burst: 20.0,
+                per_second: 10.0,
+            },
+        }
+    }
+}
+
+impl DropReason {
+    /// How much of the violation budget a dropped message costs. Rate limiting is mostly
+    /// impatience, garbage on the wire is not something a real client sends.
+    pub fn penalty(self) -> f32 {
+        match self {
+            Self::Oversized => 10.0,
+            Self::Malformed => 5.0,
+            Self::Invalid => 2.0,
+            Self::RateLimited => 1.0,
+        }
+    }
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit, now: Duration) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst,
+            updated: now,
+        }
+    }
+
+    pub fn try_take(&mut self, cost: f32, now: Duration) -> bool {
+        let elapsed = now.saturating_sub(self.updated).as_secs_f32();
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst);
+        self.updated = now;
+        if self.tokens < cost {
+            return false;
+        }
+        self.tokens -= cost;
+        true
+    }
+}
+
+impl ClientLimits {
+    fn new(now: Duration) -> Self {
+        Self {
+            buckets: HashMap::default(),
+            violations: TokenBucket::new(VIOLATION_BUDGET, now),
+        }
+    }
+}
+
+impl ClientMessage {
+    pub fn category(&self) -> MessageCategory {
+        match self {
+            Self::Join { .. } | Self::Leave | Self::SpectatorView { .. } => {
+                MessageCategory::Session
+            }
+            Self::ChatMessage { .. } | Self::ChatWheel { .. } => MessageCategory::Chat,
+            Self::Ping { .. } => MessageCategory::Ping,
+            Self::Order { .. } => MessageCategory::Order,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Join { password, .. } => !password
+                .as_ref()
+                .is_some_and(|password| password.len() > MAX_PASSWORD_LEN),
+            Self::ChatMessage { message } => {
+                !message.trim().is_empty()
+                    && message.chars().count() <= MAX_CHAT_LEN
+                    && !message.chars().any(char::is_control)
+            }
+            Self::Ping { position, .. } => position.is_finite(),
+            Self::Order { order, .. } => order.is_valid(),
+            Self::Leave | Self::ChatWheel { .. } | Self::SpectatorView { .. } => true,
+        }
+    }
+}
+
+impl MessageGuard {
+    pub fn inspect(&mut self, client_id: ClientId, payload: &[u8], now: Duration) -> Verdict {
+        let limits = self
+            .clients
+            .entry(client_id)
+            .or_insert_with(|| ClientLimits::new(now));
+        let (category, result) = check(limits, payload, now);
+        let reason = match result {
+            Ok(message) => {
+                *self.metrics.accepted.entry(category.unwrap()).or_default() += 1;
+                return Verdict::Accept(message);
+            }
+            Err(reason) => reason,
+        };
+        *self.metrics.dropped.entry((category, reason)).or_default() += 1;
+        if limits.violations.try_take(reason.penalty(), now) {
+            return Verdict::Drop(reason);
+        }
+        self.metrics.disconnects += 1;
+        self.clients.remove(&client_id);
+        Verdict::Disconnect(reason)
+    }
+
+    pub fn forget(&mut self, client_id: ClientId) {
+        self.clients.remove(&client_id);
+    }
+}
+
+impl MessageMetrics {
+    pub fn summary(&self) -> String {
+        let accepted = self
+            .accepted
+            .iter()
+            .map(|(category, count)| format!("{category:?} {count}"))
+            .collect::<Vec<_>>();
+        let dropped = self
+            .dropped
+            .iter()
+            .map(|((category, reason), count)| match category {
+                Some(category) => format!("{category:?}/{reason:?} {count}"),
+                None => format!("{reason:?} {count}"),
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "accepted [{}], dropped [{}], {} clients disconnected",
+            accepted.join(", "),
+            dropped.join(", "),
+            self.disconnects
+        )
+    }
+}
+
+fn check(
+    limits: &mut ClientLimits,
+    payload: &[u8],
+    now: Duration,
+) -> (Option<MessageCategory>, Result<ClientMessage, DropReason>) {
+    if payload.len() > MAX_PAYLOAD_SIZE {
+        return (None, Err(DropReason::Oversized));
+    }
+    let Ok(message) = bincode::deserialize::<ClientMessage>(payload) else {
+        return (None, Err(DropReason::Malformed));
+    };
+    let category = message.category();
+    if !message.is_valid() {
+        return (Some(category), Err(DropReason::Invalid));
+    }
+    let bucket = limits
+        .buckets
+        .entry(category)
+        .or_insert_with(|| TokenBucket::new(category.rate_limit(), now));
+    if !bucket.try_take(1.0, now) {
+        return (Some(category), Err(DropReason::RateLimited));
+    }
+    (Some(category), Ok(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(message: &ClientMessage) -> Vec<u8> {
+        bincode::serialize(message).unwrap()
+    }
+
+    fn chat(message: &str) -> Vec<u8> {
+        payload(&ClientMessage::ChatMessage {
+            message: message.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limit = RateLimit {
+            burst: 2.0,
+            per_second: 1.0,
+        };
+        let mut bucket = TokenBucket::new(limit, Duration::ZERO);
+        assert!(bucket.try_take(1.0, Duration::ZERO));
+        assert!(bucket.try_take(1.0, Duration::ZERO));
+        assert!(!bucket.try_take(1.0, Duration::ZERO));
+        assert!(bucket.try_take(1.0, Duration::from_secs(1)));
+        assert!(bucket.try_take(1.0, Duration::from_secs(60)));
+        assert!(bucket.try_take(1.0, Duration::from_secs(60)));
+        assert!(!bucket.try_take(1.0, Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_rate_limit_per_category() {
+        let mut guard = MessageGuard::default();
+        let burst = MessageCategory::Chat.rate_limit().burst as usize;
+        for _ in 0..burst {
+            assert!(matches!(
+                guard.inspect(1, &chat("gg"), Duration::ZERO),
+                Verdict::Accept(_)
+            ));
+        }
+        assert!(matches!(
+            guard.inspect(1, &chat("gg"), Duration::ZERO),
+            Verdict::Drop(DropReason::RateLimited)
+        ));
+        let ping = payload(&ClientMessage::Ping {
+            kind: crate::communication::PingKind::Alert,
+            position: Vec2::ZERO,
+        });
+        assert!(matches!(
+            guard.inspect(1, &ping, Duration::ZERO),
+            Verdict::Accept(_)
+        ));
+        assert!(matches!(
+            guard.inspect(2, &chat("gg"), Duration::ZERO),
+            Verdict::Accept(_)
+        ));
+        assert_eq!(
+            guard.metrics.dropped[&(Some(MessageCategory::Chat), DropReason::RateLimited)],
+            1
+        );
+    }
+
+    #[test]
+    fn test_drops_bad_payloads() {
+        let mut guard = MessageGuard::default();
+        let verdict = |guard: &mut MessageGuard, payload: &[u8]| match guard.inspect(
+            1,
+            payload,
+            Duration::ZERO,
+        ) {
+            Verdict::Drop(reason) => reason,
+            other => panic!("expected a drop, got {other:?}"),
+        };
+        assert_eq!(
+            verdict(&mut guard, &[0xff; MAX_PAYLOAD_SIZE + 1]),
+            DropReason::Oversized
+        );
+        assert_eq!(verdict(&mut guard, &[0xff; 3]), DropReason::Malformed);
+        assert_eq!(verdict(&mut guard, &chat("   ")), DropReason::Invalid);
+        assert_eq!(
+            verdict(&mut guard, &chat(&"a".repeat(MAX_CHAT_LEN + 1))),
+            DropReason::Invalid
+        );
+        let ping = payload(&ClientMessage::Ping {
+            kind: crate::communication::PingKind::Alert,
+            position: Vec2::new(f32::NAN, 0.0),
+        });
+        assert_eq!(verdict(&mut guard, &ping), DropReason::Invalid);
+    }
+
+    #[test]
+    fn test_disconnect_after_budget() {
+        let mut guard = MessageGuard::default();
+        let mut malformed = 0;
+        let reason = loop {
+            match guard.inspect(1, &[0xff; 3], Duration::ZERO) {
+                Verdict::Drop(_) => malformed += 1,
+                Verdict::Disconnect(reason) => break reason,
+                Verdict::Accept(_) => unreachable!(),
+            }
+        };
+        assert_eq!(reason, DropReason::Malformed);
+        assert_eq!(
+            malformed,
+            (VIOLATION_BUDGET.burst / DropReason::Malformed.penalty()) as usize
+        );
+        assert_eq!(guard.metrics.disconnects, 1);
+        // The disconnected client starts over if it comes back with the same id.
+        assert!(matches!(
+            guard.inspect(1, &chat("sorry"), Duration::ZERO),
+            Verdict::Accept(_)
+        ));
+    }
+}
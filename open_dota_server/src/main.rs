@@ -1,51 +1,94 @@
-use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{
+    mpsc::{self, Receiver},
+    Mutex,
+};
+
+use bevy::{
+    app::{ScheduleRunnerPlugin, ScheduleRunnerSettings},
+    prelude::*,
+};
+use bevy_quinnet::server::Server;
+use clap::Parser;
 
-use bevy::{app::ScheduleRunnerPlugin, prelude::*};
-use bevy_quinnet::server::{
-    certificate::CertificateRetrievalMode, QuinnetServerPlugin, Server, ServerConfiguration,
+use open_dota_server::{
+    access::{AccessControl, AdminCommand},
+    config::{ServerArgs, ServerConfig},
+    limits::MessageGuard,
+    player::Players,
+    server::{run_admin_command, ServerPlugin},
+    spectator::Spectators,
 };
 
-use open_dota_server::{ClientMessage, ServerMessage};
+/// Lines typed into the server's terminal, read on a separate thread.
+#[derive(Resource)]
+struct Console(Mutex<Receiver<String>>);
 
 fn main() {
+    let config = match ServerConfig::from_args(&ServerArgs::parse()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+    let access = match AccessControl::load(&config) {
+        Ok(access) => access,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+
     App::default()
+        .insert_resource(ScheduleRunnerSettings::run_loop(config.tick_duration()))
+        .insert_resource(config)
+        .insert_resource(access)
+        .insert_resource(spawn_console())
         .add_plugins(DefaultPlugins)
-        .add_plugin(ScheduleRunnerPlugin::default())
-        .add_plugin(QuinnetServerPlugin::default())
-        .add_startup_system(startup)
-        .add_system(handle_client_messages)
+        .add_plugin(ScheduleRunnerPlugin)
+        .add_plugin(ServerPlugin)
+        .add_system(handle_console_commands)
         .run();
 }
 
-fn startup(mut server: ResMut<Server>) {
-    server
-        .start_endpoint(
-            ServerConfiguration::from_ip(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 6000),
-            CertificateRetrievalMode::GenerateSelfSigned {
-                server_hostname: "127.0.0.1".to_string(),
-            },
-        )
-        .unwrap();
-}
-
-fn handle_client_messages(mut server: ResMut<Server>) {
-    let endpoint = server.endpoint_mut();
-    for client_id in endpoint.clients() {
-        while let Some(message) = endpoint.try_receive_message_from::<ClientMessage>(client_id) {
-            match message {
-                ClientMessage::Join => {
-                    endpoint
-                        .send_message(client_id, ServerMessage::InitClient)
-                        .unwrap();
-                }
-                ClientMessage::Leave => endpoint.disconnect_client(client_id).unwrap(),
-                ClientMessage::ChatMessage { message } => endpoint
-                    .send_group_message(
-                        endpoint.clients().iter(),
-                        ServerMessage::ChatMessage { message },
-                    )
-                    .unwrap(),
+fn spawn_console() -> Console {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
             }
         }
+    });
+    Console(Mutex::new(receiver))
+}
+
+fn handle_console_commands(
+    console: Res<Console>,
+    mut commands: Commands,
+    mut server: ResMut<Server>,
+    mut players: ResMut<Players>,
+    mut spectators: ResMut<Spectators>,
+    mut access: ResMut<AccessControl>,
+    guard: Res<MessageGuard>,
+) {
+    let lines = console.0.lock().unwrap().try_iter().collect::<Vec<_>>();
+    for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+        let response = match AdminCommand::parse(line) {
+            Ok(command) => run_admin_command(
+                command,
+                &mut commands,
+                server.endpoint_mut(),
+                &mut players,
+                &mut spectators,
+                &mut access,
+                &guard.metrics,
+            ),
+            Err(usage) => usage,
+        };
+        info!("{response}");
     }
 }
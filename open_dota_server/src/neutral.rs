@@ -0,0 +1,446 @@
+//! Neutral creeps living in jungle camps. The camps, what spawns in them and how strong it is are
+//! defined in `data/neutrals.toml`.
+//!
+//! Camps respawn on the minute if nothing stands in them, so pulling a camp away right before
+//! the minute stacks a second one on top. Neutrals fight back as a camp when one of them is hit,
+//! and walk back home with full health once they are pulled too far.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    boss::BossStats,
+    combat::{Attack, AttackTarget, Damaged, Health},
+    simulation::{Tick, TickRate},
+    unit::{MoveSpeed, MoveTarget, Position, Unit},
+};
+
+/// The neutral table every match is played with.
+pub const NEUTRAL_TABLE: &str = include_str!("../data/neutrals.toml");
+
+/// Where the creeps of a camp stand, relative to the camp, by their slot in it.
+const SLOT_OFFSETS: [Vec2; 4] = [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(30.0, 0.0),
+    Vec2::new(0.0, 30.0),
+    Vec2::new(30.0, 30.0),
+];
+
+#[derive(Debug, Error)]
+pub enum NeutralTableError {
+    #[error("failed to parse neutral table: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("{0:?} camps don't spawn any creeps")]
+    EmptyCamp(CampKind),
+    #[error("{kind:?} camps spawn unknown creep `{creep}`")]
+    UnknownCreep { kind: CampKind, creep: String },
+    #[error("{kind:?} camps spawn {count} creeps, at most {} fit", SLOT_OFFSETS.len())]
+    CrowdedCamp { kind: CampKind, count: usize },
+    #[error("a spawner places {0:?} camps, which aren't defined")]
+    UnknownCamp(CampKind),
+    #[error("the boss respawns at least {min}s after dying but at most {max}s after")]
+    BossRespawnWindow { min: u64, max: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CampKind {
+    Small,
+    Medium,
+    Large,
+    Ancient,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreepStats {
+    pub health: f32,
+    pub damage: f32,
+    pub attack_range: f32,
+    /// Seconds between attacks.
+    pub attack_cooldown: f32,
+    pub move_speed: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CampType {
+    /// Names of the creeps spawned, one per slot.
+    pub creeps: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CampSpawner {
+    pub kind: CampKind,
+    pub position: Vec2,
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NeutralTable {
+    /// Seconds between camp respawns.
+    pub respawn_interval: u64,
+    /// Half the side of the square around a camp that has to be empty for it to respawn.
+    pub spawn_box_half_size: f32,
+    /// How far neutrals follow an attacker from their camp.
+    pub leash_range: f32,
+    pub creeps: BTreeMap<String, CreepStats>,
+    pub camps: BTreeMap<CampKind, CampType>,
+    pub spawners: Vec<CampSpawner>,
+    pub boss: BossStats,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NeutralState {
+    #[default]
+    Idle,
+    /// Fighting whoever attacked the camp.
+    Aggro,
+    /// Walking back to the camp, ignoring attacks.
+    Returning,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Neutral {
+    /// Index of the camp's spawner in the [`NeutralTable`].
+    pub camp: usize,
+    /// Which of the camp's creeps this is.
+    pub slot: usize,
+    pub home: Vec2,
+    pub state: NeutralState,
+}
+
+#[derive(Bundle)]
+pub struct NeutralBundle {
+    pub unit: Unit,
+    pub neutral: Neutral,
+    pub position: Position,
+    pub move_speed: MoveSpeed,
+    pub move_target: MoveTarget,
+    pub health: Health,
+    pub attack: Attack,
+    pub attack_target: AttackTarget,
+}
+
+impl NeutralTable {
+    pub fn parse(text: &str) -> Result<Self, NeutralTableError> {
+        let table: Self = toml::from_str(text)?;
+        for (kind, camp) in &table.camps {
+            if camp.creeps.is_empty() {
+                return Err(NeutralTableError::EmptyCamp(*kind));
+            }
+            if camp.creeps.len() > SLOT_OFFSETS.len() {
+                return Err(NeutralTableError::CrowdedCamp {
+                    kind: *kind,
+                    count: camp.creeps.len(),
+                });
+            }
+            if let Some(creep) = camp
+                .creeps
+                .iter()
+                .find(|creep| !table.creeps.contains_key(*creep))
+            {
+                return Err(NeutralTableError::UnknownCreep {
+                    kind: *kind,
+                    creep: creep.clone(),
+                });
+            }
+        }
+        if let Some(spawner) = table
+            .spawners
+            .iter()
+            .find(|spawner| !table.camps.contains_key(&spawner.kind))
+        {
+            return Err(NeutralTableError::UnknownCamp(spawner.kind));
+        }
+        if table.boss.respawn_min_secs > table.boss.respawn_max_secs {
+            return Err(NeutralTableError::BossRespawnWindow {
+                min: table.boss.respawn_min_secs,
+                max: table.boss.respawn_max_secs,
+            });
+        }
+        Ok(table)
+    }
+
+    /// The table built into the server, see [`NEUTRAL_TABLE`].
+    pub fn builtin() -> Self {
+        Self::parse(NEUTRAL_TABLE).expect("built-in neutral table is valid")
+    }
+
+    fn camp(&self, camp: usize) -> &CampType {
+        &self.camps[&self.spawners[camp].kind]
+    }
+
+    /// Whether `position` is in the spawn box of `camp`.
+    fn blocks(&self, camp: usize, position: Vec2) -> bool {
+        let offset = (position - self.spawners[camp].position).abs();
+        offset.max_element() <= self.spawn_box_half_size
+    }
+}
+
+impl NeutralBundle {
+    /// The creep in `slot` of the `camp`th spawner of `table`.
+    pub fn new(table: &NeutralTable, camp: usize, slot: usize) -> Self {
+        let stats = &table.creeps[&table.camp(camp).creeps[slot]];
+        let home = table.spawners[camp].position + SLOT_OFFSETS[slot];
+        Self {
+            unit: Unit,
+            neutral: Neutral {
+                camp,
+                slot,
+                home,
+                state: NeutralState::Idle,
+            },
+            position: Position(home),
+            move_speed: MoveSpeed(stats.move_speed),
+            move_target: Default::default(),
+            health: Health::full(stats.health),
+            attack: Attack::new(stats.damage, stats.attack_range, stats.attack_cooldown),
+            attack_target: Default::default(),
+        }
+    }
+}
+
+/// Spawns every camp with nothing in its spawn box, once every respawn interval.
+pub fn respawn_camps(
+    mut commands: Commands,
+    table: Res<NeutralTable>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    unit_query: Query<&Position, With<Unit>>,
+) {
+    if !tick
+        .0
+        .is_multiple_of(table.respawn_interval * tick_rate.0 as u64)
+    {
+        return;
+    }
+    for camp in 0..table.spawners.len() {
+        if unit_query
+            .iter()
+            .any(|position| table.blocks(camp, position.0))
+        {
+            continue;
+        }
+        for slot in 0..table.camp(camp).creeps.len() {
+            commands.spawn(NeutralBundle::new(&table, camp, slot));
+        }
+    }
+}
+
+/// Turns the whole camp on whoever hits one of its creeps, and sends neutrals home once they are
+/// pulled past the leash range or lose their target.
+pub fn neutral_ai(
+    table: Res<NeutralTable>,
+    mut damaged_events: EventReader<Damaged>,
+    mut neutral_query: Query<(
+        &mut Neutral,
+        &Position,
+        &mut MoveTarget,
+        &mut AttackTarget,
+        &mut Health,
+    )>,
+) {
+    let mut attacked_camps = Vec::new();
+    for event in damaged_events.iter() {
+        if let Ok((neutral, ..)) = neutral_query.get(event.target) {
+            if neutral.state != NeutralState::Returning {
+                attacked_camps.push((neutral.camp, event.source));
+            }
+        }
+    }
+
+    for (mut neutral, position, mut move_target, mut attack_target, mut health) in
+        &mut neutral_query
+    {
+        match neutral.state {
+            NeutralState::Idle => {
+                let Some((_, attacker)) = attacked_camps
+                    .iter()
+                    .find(|(camp, _)| *camp == neutral.camp)
+                else {
+                    continue;
+                };
+                neutral.state = NeutralState::Aggro;
+                attack_target.0 = Some(*attacker);
+            }
+            NeutralState::Aggro => {
+                if attack_target.0.is_some()
+                    && position.0.distance(neutral.home) <= table.leash_range
+                {
+                    continue;
+                }
+                neutral.state = NeutralState::Returning;
+                attack_target.0 = None;
+                move_target.0 = Some(neutral.home);
+            }
+            NeutralState::Returning => {
+                if position.0 != neutral.home {
+                    move_target.0 = Some(neutral.home);
+                    continue;
+                }
+                neutral.state = NeutralState::Idle;
+                health.current = health.max;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combat::{apply_damage, attack_targets, remove_dead_units, UnitDied},
+        order::move_units,
+        tree::Forest,
+    };
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(NeutralTable::builtin());
+        world.insert_resource(TickRate(10));
+        world.init_resource::<Tick>();
+        world.insert_resource(Forest::builtin());
+        world.init_resource::<Events<Damaged>>();
+        world.init_resource::<Events<UnitDied>>();
+        world
+    }
+
+    fn neutrals(world: &mut World, camp: usize) -> Vec<Entity> {
+        world
+            .query::<(Entity, &Neutral)>()
+            .iter(world)
+            .filter(|(_, neutral)| neutral.camp == camp)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    #[test]
+    fn test_table_is_checked() {
+        let table = NeutralTable::builtin();
+        assert!(!table.spawners.is_empty());
+        for kind in [
+            CampKind::Small,
+            CampKind::Medium,
+            CampKind::Large,
+            CampKind::Ancient,
+        ] {
+            assert!(table.spawners.iter().any(|spawner| spawner.kind == kind));
+        }
+
+        let broken = NEUTRAL_TABLE.replace("\"kobold_foreman\"]", "\"kobold_boss\"]");
+        assert!(matches!(
+            NeutralTable::parse(&broken),
+            Err(NeutralTableError::UnknownCreep { kind: CampKind::Small, creep }) if creep == "kobold_boss"
+        ));
+    }
+
+    #[test]
+    fn test_camps_respawn_on_the_minute_when_empty() {
+        let mut world = world();
+        let table = NeutralTable::builtin();
+        let interval = table.respawn_interval * 10;
+        let respawn_at = |world: &mut World, tick| {
+            world.resource_mut::<Tick>().0 = tick;
+            let mut schedule = Schedule::new();
+            schedule.add_system(respawn_camps);
+            schedule.run(world);
+        };
+
+        respawn_at(&mut world, interval - 1);
+        assert!(neutrals(&mut world, 0).is_empty());
+        respawn_at(&mut world, interval);
+        let camp = neutrals(&mut world, 0);
+        assert_eq!(camp.len(), table.camp(0).creeps.len());
+        let everything = world.query::<&Neutral>().iter(&world).count();
+
+        // Camps with creeps in them don't respawn.
+        respawn_at(&mut world, interval * 2);
+        assert_eq!(world.query::<&Neutral>().iter(&world).count(), everything);
+
+        // Pulling the camp out of its box stacks a second one.
+        for entity in camp {
+            world.get_mut::<Position>(entity).unwrap().0 += Vec2::new(0.0, 200.0);
+        }
+        respawn_at(&mut world, interval * 3 - 1);
+        assert_eq!(neutrals(&mut world, 0).len(), table.camp(0).creeps.len());
+        respawn_at(&mut world, interval * 3);
+        assert_eq!(
+            neutrals(&mut world, 0).len(),
+            table.camp(0).creeps.len() * 2
+        );
+        assert_eq!(
+            world.query::<&Neutral>().iter(&world).count(),
+            everything + table.camp(0).creeps.len()
+        );
+    }
+
+    #[test]
+    fn test_neutrals_aggro_as_a_camp_and_leash() {
+        let mut world = world();
+        let table = NeutralTable::builtin();
+        let camp = (0..table.camp(0).creeps.len())
+            .map(|slot| world.spawn(NeutralBundle::new(&table, 0, slot)).id())
+            .collect::<Vec<_>>();
+        let home = world.get::<Neutral>(camp[0]).unwrap().home;
+        let attacker = world
+            .spawn((Unit, Position(home + Vec2::new(60.0, 0.0))))
+            .id();
+        world.send_event(Damaged {
+            source: attacker,
+            target: camp[0],
+            amount: 50.0,
+        });
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(
+            (
+                neutral_ai,
+                attack_targets,
+                move_units,
+                apply_damage,
+                remove_dead_units,
+            )
+                .chain(),
+        );
+        let mut run = |world: &mut World, ticks| {
+            for _ in 0..ticks {
+                schedule.run(world);
+                world.resource_mut::<Events<Damaged>>().update();
+                world.resource_mut::<Events<UnitDied>>().update();
+            }
+        };
+
+        run(&mut world, 1);
+        for neutral in &camp {
+            assert_eq!(
+                world.get::<Neutral>(*neutral).unwrap().state,
+                NeutralState::Aggro
+            );
+            assert_eq!(
+                world.get::<AttackTarget>(*neutral).unwrap().0,
+                Some(attacker)
+            );
+        }
+        assert!(!world.get::<Health>(camp[0]).unwrap().is_full());
+
+        // Running away pulls the camp until it leashes.
+        world.get_mut::<Position>(attacker).unwrap().0 = home + Vec2::new(0.0, 400.0);
+        run(&mut world, 20);
+        let neutral = world.get::<Neutral>(camp[0]).unwrap();
+        assert_eq!(neutral.state, NeutralState::Returning);
+        assert_eq!(world.get::<AttackTarget>(camp[0]).unwrap().0, None);
+
+        run(&mut world, 40);
+        for neutral in &camp {
+            let state = *world.get::<Neutral>(*neutral).unwrap();
+            assert_eq!(state.state, NeutralState::Idle);
+            assert_eq!(world.get::<Position>(*neutral).unwrap().0, state.home);
+            assert!(world.get::<Health>(*neutral).unwrap().is_full());
+        }
+    }
+}
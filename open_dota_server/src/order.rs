@@ -0,0 +1,481 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::AttackTarget,
+    courier::{Courier, CourierTask, SharedControl},
+    death::Respawn,
+    player::{PlayerId, Team},
+    rune::{RuneBuff, RuneKind, RuneTarget, HASTE_MOVE_SPEED, RUNE_SPOTS},
+    simulation::{Tick, TickRate},
+    tree::{Forest, TreeTarget},
+    unit::{MoveSpeed, MoveTarget, Owner, Position, Unit, UnitId, MAP_HALF_SIZE},
+    vision::TeamVision,
+    ward::{WardKind, WardTarget},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Order {
+    Move {
+        target: Vec2,
+    },
+    Attack {
+        target: UnitId,
+    },
+    /// Walks to the rune spot with index `spot` in [`RUNE_SPOTS`] and takes the rune there.
+    PickUpRune {
+        spot: usize,
+    },
+    /// Activates the rune held in the unit's bottle.
+    UseBottle,
+    /// Has a courier fetch the ordering player's stash and bring it to their hero.
+    Deliver,
+    ReturnToBase,
+    SpeedBurst,
+    /// Buys a ward from the team's stock.
+    BuyWard {
+        kind: WardKind,
+    },
+    /// Walks to `target` and places a ward there.
+    PlaceWard {
+        kind: WardKind,
+        target: Vec2,
+    },
+    /// Walks to the tree with index `tree` in the [`Forest`] and cuts it down.
+    CutTree {
+        tree: usize,
+    },
+    /// Brings a dead hero back to life right away, for gold.
+    Buyback,
+    Stop,
+}
+
+/// An order a player sent for one of their units, not yet checked for ownership.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderIssued {
+    pub player: PlayerId,
+    /// The team of `player`, who may order units shared with the team.
+    pub team: Team,
+    pub unit: UnitId,
+    pub order: Order,
+}
+
+/// An order that passed the ownership check and was given to the unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderApplied(pub OrderIssued);
+
+impl Order {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Move { target } => target.is_finite(),
+            Self::PickUpRune { spot } => *spot < RUNE_SPOTS.len(),
+            Self::PlaceWard { target, .. } => target.is_finite(),
+            Self::Attack { .. }
+            | Self::BuyWard { .. }
+            | Self::CutTree { .. }
+            | Self::Buyback
+            | Self::UseBottle
+            | Self::Deliver
+            | Self::ReturnToBase
+            | Self::SpeedBurst
+            | Self::Stop => true,
+        }
+    }
+
+    /// The same order with every unit it refers to replaced by `map`.
+    pub fn map_units(self, map: impl Fn(UnitId) -> Option<UnitId>) -> Option<Self> {
+        match self {
+            Self::Attack { target } => map(target).map(|target| Self::Attack { target }),
+            Self::Move { .. }
+            | Self::PickUpRune { .. }
+            | Self::BuyWard { .. }
+            | Self::PlaceWard { .. }
+            | Self::CutTree { .. }
+            | Self::Buyback
+            | Self::UseBottle
+            | Self::Deliver
+            | Self::ReturnToBase
+            | Self::SpeedBurst
+            | Self::Stop => Some(self),
+        }
+    }
+}
+
+type OrderedUnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static Owner>,
+        Option<&'static Team>,
+        Option<&'static SharedControl>,
+        &'static mut MoveTarget,
+        Option<&'static mut AttackTarget>,
+        Option<&'static mut RuneTarget>,
+        Option<&'static mut Courier>,
+        Option<&'static mut WardTarget>,
+        Option<&'static mut TreeTarget>,
+        Option<&'static Respawn>,
+    ),
+>;
+
+/// Gives units the orders of players controlling them: their owner, or anyone on their team for
+/// units with [`SharedControl`]. Dead heroes can only buy wards or buy back.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_orders(
+    mut order_events: EventReader<OrderIssued>,
+    mut applied_events: EventWriter<OrderApplied>,
+    mut unit_query: OrderedUnitQuery,
+    target_query: Query<Option<&Respawn>, With<Unit>>,
+    vision: Res<TeamVision>,
+    forest: Res<Forest>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+) {
+    for event in order_events.iter() {
+        let Ok((
+            owner,
+            team,
+            shared,
+            mut move_target,
+            attack_target,
+            rune_target,
+            courier,
+            ward_target,
+            tree_target,
+            respawn,
+        )) = unit_query.get_mut(event.unit.entity())
+        else {
+            continue;
+        };
+        let owned = owner.is_some_and(|owner| owner.0 == event.player);
+        let shared = shared.is_some() && team == Some(&event.team);
+        if !owned && !shared {
+            continue;
+        }
+        let dead = respawn.is_some_and(Respawn::is_dead);
+        if dead && !matches!(event.order, Order::BuyWard { .. } | Order::Buyback) {
+            continue;
+        }
+        let mut picking_up = None;
+        let mut warding = None;
+        let mut cutting = None;
+        let attacking = match event.order {
+            Order::Move { target } => {
+                move_target.0 = Some(target.clamp(-MAP_HALF_SIZE, MAP_HALF_SIZE));
+                None
+            }
+            Order::PickUpRune { spot } => {
+                if rune_target.is_none() {
+                    continue;
+                }
+                move_target.0 = Some(RUNE_SPOTS[spot]);
+                picking_up = Some(spot);
+                None
+            }
+            Order::PlaceWard { kind, target } => {
+                if ward_target.is_none() {
+                    continue;
+                }
+                let target = target.clamp(-MAP_HALF_SIZE, MAP_HALF_SIZE);
+                move_target.0 = Some(target);
+                warding = Some((kind, target));
+                None
+            }
+            Order::CutTree { tree } => {
+                let Some(position) = forest.trees.get(tree) else {
+                    continue;
+                };
+                if tree_target.is_none() {
+                    continue;
+                }
+                move_target.0 = Some(*position);
+                cutting = Some(tree);
+                None
+            }
+            Order::UseBottle | Order::BuyWard { .. } | Order::Buyback => {
+                applied_events.send(OrderApplied(*event));
+                continue;
+            }
+            Order::Attack { target } => {
+                let alive = target_query
+                    .get(target.entity())
+                    .is_ok_and(|respawn| !respawn.is_some_and(Respawn::is_dead));
+                let seen = vision.sees(event.team, target.entity());
+                if target == event.unit || attack_target.is_none() || !alive || !seen {
+                    continue;
+                }
+                Some(target.entity())
+            }
+            Order::Stop => {
+                move_target.0 = None;
+                None
+            }
+            Order::Deliver | Order::ReturnToBase | Order::SpeedBurst => {
+                let Some(mut courier) = courier else {
+                    continue;
+                };
+                match event.order {
+                    Order::Deliver => courier.task = CourierTask::Deliver(event.player),
+                    Order::ReturnToBase => courier.task = CourierTask::ReturnToBase,
+                    _ if !courier.burst(*tick, *tick_rate) => continue,
+                    _ => (),
+                }
+                applied_events.send(OrderApplied(*event));
+                continue;
+            }
+        };
+        if let Some(mut attack_target) = attack_target {
+            attack_target.0 = attacking;
+        }
+        if let Some(mut rune_target) = rune_target {
+            rune_target.0 = picking_up;
+        }
+        if let Some(mut courier) = courier {
+            courier.task = CourierTask::Idle;
+        }
+        if let Some(mut ward_target) = ward_target {
+            ward_target.0 = warding;
+        }
+        if let Some(mut tree_target) = tree_target {
+            tree_target.0 = cutting;
+        }
+        applied_events.send(OrderApplied(*event));
+    }
+}
+
+/// Moves units towards their targets. Units walking into a tree slide along it if they can.
+pub fn move_units(
+    mut unit_query: Query<(
+        &mut Position,
+        &mut MoveTarget,
+        &MoveSpeed,
+        Option<&RuneBuff>,
+    )>,
+    forest: Res<Forest>,
+    tick_rate: Res<TickRate>,
+) {
+    for (mut position, mut move_target, speed, buff) in &mut unit_query {
+        let Some(target) = move_target.0 else {
+            continue;
+        };
+        let speed = match buff {
+            Some(buff) if buff.is(RuneKind::Haste) => speed.0.max(HASTE_MOVE_SPEED),
+            _ => speed.0,
+        };
+        let (next, arrived) = step_towards(position.0, target, speed * tick_rate.delta_seconds());
+        let slides = [
+            next,
+            Vec2::new(next.x, position.0.y),
+            Vec2::new(position.0.x, next.y),
+        ];
+        let Some(next) = slides
+            .into_iter()
+            .find(|next| forest.tree_at(*next).is_none())
+        else {
+            continue;
+        };
+        position.0 = next;
+        if arrived && next == target {
+            move_target.0 = None;
+        }
+    }
+}
+
+/// Moves at most `distance` from `from` towards `to`, returning the new position and whether it
+/// reached `to`.
+pub fn step_towards(from: Vec2, to: Vec2, distance: f32) -> (Vec2, bool) {
+    let offset = to - from;
+    if offset.length() <= distance {
+        return (to, true);
+    }
+    (from + offset.normalize() * distance, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::GameClock, courier::CourierBundle, tree::TreeBits, unit::HeroBundle,
+        vision::update_vision,
+    };
+
+    #[test]
+    fn test_step_towards() {
+        assert_eq!(
+            step_towards(Vec2::ZERO, Vec2::new(10.0, 0.0), 4.0),
+            (Vec2::new(4.0, 0.0), false)
+        );
+        assert_eq!(
+            step_towards(Vec2::new(8.0, 0.0), Vec2::new(10.0, 0.0), 4.0),
+            (Vec2::new(10.0, 0.0), true)
+        );
+    }
+
+    #[test]
+    fn test_trees_block_movement() {
+        let mut world = World::new();
+        world.insert_resource(TickRate(10));
+        world.insert_resource(Forest {
+            radius: 20.0,
+            trees: vec![Vec2::new(40.0, 0.0), Vec2::new(40.0, 30.0)],
+            standing: TreeBits::new(2),
+            ..Default::default()
+        });
+        let mut schedule = Schedule::new();
+        schedule.add_system(move_units);
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        world.get_mut::<Position>(hero).unwrap().0 = Vec2::ZERO;
+        world.get_mut::<MoveTarget>(hero).unwrap().0 = Some(Vec2::new(100.0, 10.0));
+
+        // The hero can't get through the trees.
+        for _ in 0..20 {
+            schedule.run(&mut world);
+            let position = world.get::<Position>(hero).unwrap().0;
+            assert_eq!(world.resource::<Forest>().tree_at(position), None);
+        }
+        assert!(world.get::<Position>(hero).unwrap().0.x < 20.0);
+
+        world.resource_mut::<Forest>().standing = TreeBits::default();
+        for _ in 0..20 {
+            schedule.run(&mut world);
+        }
+        assert_eq!(world.get::<MoveTarget>(hero).unwrap().0, None);
+    }
+
+    #[test]
+    fn test_orders_need_ownership() {
+        let mut world = World::new();
+        world.insert_resource(TickRate(10));
+        world.init_resource::<Tick>();
+        world.init_resource::<Events<OrderIssued>>();
+        world.init_resource::<Events<OrderApplied>>();
+        world.init_resource::<Forest>();
+        world.init_resource::<TeamVision>();
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        let target = Vec2::new(100.0, 50.0);
+        for player in [PlayerId(2), PlayerId(1)] {
+            world.send_event(OrderIssued {
+                player,
+                team: Team::Radiant,
+                unit: hero.into(),
+                order: Order::Move { target },
+            });
+            let mut schedule = Schedule::new();
+            schedule.add_system(apply_orders);
+            schedule.run(&mut world);
+            let expected = (player == PlayerId(1)).then_some(target);
+            assert_eq!(world.get::<MoveTarget>(hero).unwrap().0, expected);
+        }
+
+        world.send_event(OrderIssued {
+            player: PlayerId(1),
+            team: Team::Radiant,
+            unit: hero.into(),
+            order: Order::Move {
+                target: Vec2::splat(1e6),
+            },
+        });
+        let mut schedule = Schedule::new();
+        schedule.add_system(apply_orders);
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<MoveTarget>(hero).unwrap().0,
+            Some(MAP_HALF_SIZE)
+        );
+    }
+
+    #[test]
+    fn test_attack_orders_need_a_target() {
+        let mut world = World::new();
+        world.insert_resource(TickRate(10));
+        world.init_resource::<Tick>();
+        world.init_resource::<Events<OrderIssued>>();
+        world.init_resource::<Events<OrderApplied>>();
+        world.init_resource::<Forest>();
+        world.init_resource::<TeamVision>();
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        world.init_resource::<GameClock>();
+        let enemy = world.spawn(HeroBundle::new(PlayerId(2), Team::Dire)).id();
+        let not_a_unit = world.spawn(Position(Vec2::ZERO)).id();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((update_vision, apply_orders).chain());
+        // The enemy starts out at its fountain, hidden in the fog.
+        let cases = [
+            (not_a_unit, None, Team::Dire.fountain()),
+            (hero, None, Team::Dire.fountain()),
+            (enemy, None, Team::Dire.fountain()),
+            (enemy, Some(enemy), Team::Radiant.fountain()),
+        ];
+        for (target, expected, enemy_position) in cases {
+            world.get_mut::<Position>(enemy).unwrap().0 = enemy_position;
+            world.send_event(OrderIssued {
+                player: PlayerId(1),
+                team: Team::Radiant,
+                unit: hero.into(),
+                order: Order::Attack {
+                    target: target.into(),
+                },
+            });
+            schedule.run(&mut world);
+            world.resource_mut::<Events<OrderIssued>>().update();
+            assert_eq!(world.get::<AttackTarget>(hero).unwrap().0, expected);
+        }
+
+        world.send_event(OrderIssued {
+            player: PlayerId(1),
+            team: Team::Radiant,
+            unit: hero.into(),
+            order: Order::Move { target: Vec2::ZERO },
+        });
+        schedule.run(&mut world);
+        assert_eq!(world.get::<AttackTarget>(hero).unwrap().0, None);
+    }
+
+    #[test]
+    fn test_couriers_obey_their_team() {
+        let mut world = World::new();
+        world.insert_resource(TickRate(10));
+        world.init_resource::<Tick>();
+        world.init_resource::<Events<OrderIssued>>();
+        world.init_resource::<Events<OrderApplied>>();
+        world.init_resource::<Forest>();
+        world.init_resource::<TeamVision>();
+        let courier = world.spawn(CourierBundle::new(Team::Radiant)).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(apply_orders);
+        let mut order = |player, team, order| {
+            world.send_event(OrderIssued {
+                player,
+                team,
+                unit: courier.into(),
+                order,
+            });
+            schedule.run(&mut world);
+            world.resource_mut::<Events<OrderIssued>>().update();
+            world.get::<Courier>(courier).unwrap().task
+        };
+
+        assert_eq!(
+            order(PlayerId(2), Team::Dire, Order::Deliver),
+            CourierTask::Idle
+        );
+        assert_eq!(
+            order(PlayerId(3), Team::Radiant, Order::Deliver),
+            CourierTask::Deliver(PlayerId(3))
+        );
+        assert_eq!(
+            order(PlayerId(1), Team::Radiant, Order::ReturnToBase),
+            CourierTask::ReturnToBase
+        );
+        assert_eq!(
+            order(PlayerId(1), Team::Radiant, Order::Stop),
+            CourierTask::Idle
+        );
+    }
+}
@@ -0,0 +1,741 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    boss::{match_minute, Boss, BossBundle, BossKilled, BossPit},
+    clock::GameClock,
+    combat::{Attack, AttackTarget, Damaged, Health, UnitDied},
+    courier::{Courier, CourierBundle, CourierRespawns},
+    death::{HeroKilled, Respawn},
+    identity::Identity,
+    illusion::{Illusion, IllusionBundle},
+    invisibility::Invisibility,
+    item::{Inventory, Stash},
+    neutral::{Neutral, NeutralBundle, NeutralTable},
+    order::{OrderApplied, OrderIssued},
+    player::{PlayerId, Team},
+    replay::{Replay, ReplayEvent},
+    rune::{RuneActivated, RuneBuff, RuneTarget, Runes},
+    simulation::{
+        add_simulation_systems, state_hash, MatchRng, MatchSeed, Tick, TickRate, UnitState,
+    },
+    snapshot::{PlayerSnapshot, Snapshot, UnitSnapshot},
+    tree::{FellTree, Forest, Tree, TreeBundle, TreeTarget},
+    unit::{Experience, Gold, Hero, HeroBundle, MoveTarget, Owner, Position, UnitId},
+    vision::TeamVision,
+    ward::{Ward, WardBundle, WardPlaced, WardStock, WardTarget},
+};
+
+/// Seconds of match time between keyframes.
+pub const KEYFRAME_INTERVAL_SECS: u64 = 10;
+
+#[derive(Debug, Clone)]
+struct ReplayPlayer {
+    identity: Identity,
+    name: String,
+    team: Team,
+}
+
+#[derive(Debug, Clone)]
+enum KeyframeKind {
+    Hero {
+        owner: PlayerId,
+        team: Team,
+    },
+    Neutral(Neutral),
+    Boss(Boss),
+    Illusion {
+        owner: PlayerId,
+        team: Team,
+        illusion: Illusion,
+    },
+    Courier {
+        team: Team,
+        courier: Courier,
+    },
+    Ward {
+        team: Team,
+        ward: Ward,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct KeyframeUnit {
+    id: UnitId,
+    kind: KeyframeKind,
+    position: Vec2,
+    move_target: Option<Vec2>,
+    health: Health,
+    attack: Option<Attack>,
+    attack_target: Option<UnitId>,
+    inventory: Option<Inventory>,
+    stash: Option<Stash>,
+    gold: Option<Gold>,
+    experience: Option<Experience>,
+    respawn: Option<Respawn>,
+    rune_target: Option<RuneTarget>,
+    rune_buff: Option<RuneBuff>,
+    invisibility: Option<Invisibility>,
+    ward_target: Option<WardTarget>,
+    tree_target: Option<TreeTarget>,
+}
+
+/// Everything needed to resume the simulation after `tick`.
+#[derive(Debug, Clone)]
+struct Keyframe {
+    tick: u64,
+    next_frame: usize,
+    players: BTreeMap<PlayerId, ReplayPlayer>,
+    units: Vec<KeyframeUnit>,
+    rng: MatchRng,
+    boss_pit: BossPit,
+    runes: Runes,
+    courier_respawns: CourierRespawns,
+    ward_stock: WardStock,
+    forest: Forest,
+    /// When each felled tree regrows, by index in the forest.
+    trees: Vec<Option<u64>>,
+}
+
+/// Simulates a recorded match again, one tick at a time, with the server's own systems.
+///
+/// Units keep the ids they had on the server, so snapshots of a replay look exactly like the ones
+/// the server sent while the match was live.
+pub struct ReplaySimulation {
+    replay: Replay,
+    world: World,
+    schedule: Schedule,
+    tick: u64,
+    next_frame: usize,
+    players: BTreeMap<PlayerId, ReplayPlayer>,
+    /// Recorded unit ids and the entities simulating them.
+    units: HashMap<UnitId, Entity>,
+    /// The tree entities, by index in the [`Forest`].
+    trees: Vec<Entity>,
+    /// Keyframes taken every [`KEYFRAME_INTERVAL_SECS`], in order. Taken the first time
+    /// playback passes them.
+    keyframes: Vec<Keyframe>,
+    /// First tick whose state hash didn't match the recorded one.
+    diverged_at: Option<u64>,
+}
+
+impl ReplaySimulation {
+    pub fn new(replay: Replay) -> Self {
+        let mut world = World::new();
+        world.insert_resource(TickRate(replay.header.tick_rate));
+        world.init_resource::<Tick>();
+        world.init_resource::<BossPit>();
+        world.init_resource::<Runes>();
+        world.init_resource::<CourierRespawns>();
+        world.init_resource::<WardStock>();
+        world.init_resource::<TeamVision>();
+        world.insert_resource(MatchRng::new(MatchSeed(replay.header.seed)));
+        world.init_resource::<Events<OrderIssued>>();
+        world.init_resource::<Events<OrderApplied>>();
+        world.init_resource::<Events<Damaged>>();
+        world.init_resource::<Events<UnitDied>>();
+        world.init_resource::<Events<BossKilled>>();
+        world.init_resource::<Events<RuneActivated>>();
+        world.init_resource::<Events<WardPlaced>>();
+        world.init_resource::<Events<FellTree>>();
+        world.init_resource::<Events<HeroKilled>>();
+        world.insert_resource(NeutralTable::builtin());
+        let forest = Forest::builtin();
+        let trees = (0..forest.trees.len())
+            .map(|index| world.spawn(TreeBundle::new(&forest, index)).id())
+            .collect();
+        world.insert_resource(forest);
+        let mut schedule = Schedule::new();
+        add_simulation_systems(&mut schedule);
+        let mut simulation = Self {
+            replay,
+            world,
+            schedule,
+            tick: 0,
+            next_frame: 0,
+            players: BTreeMap::new(),
+            units: HashMap::default(),
+            trees,
+            keyframes: Vec::new(),
+            diverged_at: None,
+        };
+        simulation.keyframes.push(simulation.keyframe());
+        simulation
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn end_tick(&self) -> u64 {
+        self.replay.last_tick()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.end_tick()
+    }
+
+    /// The first tick that simulated differently than it did on the server, if any so far.
+    pub fn diverged_at(&self) -> Option<u64> {
+        self.diverged_at
+    }
+
+    pub fn tick_rate(&self) -> TickRate {
+        TickRate(self.replay.header.tick_rate)
+    }
+
+    fn keyframe_interval(&self) -> u64 {
+        KEYFRAME_INTERVAL_SECS * self.replay.header.tick_rate as u64
+    }
+
+    /// Simulates the next tick the same way the server did: orders, then movement and fights,
+    /// then units spawning and heroes leaving.
+    pub fn step(&mut self) {
+        self.tick += 1;
+        let (events, recorded_hash) = match self.replay.frames.get(self.next_frame) {
+            Some(frame) if frame.tick == self.tick => {
+                self.next_frame += 1;
+                (frame.events.clone(), frame.state_hash)
+            }
+            _ => (Vec::new(), None),
+        };
+        self.world.insert_resource(Tick(self.tick));
+        self.world
+            .insert_resource(GameClock::at(Tick(self.tick), self.tick_rate()));
+
+        for event in &events {
+            let ReplayEvent::Order {
+                player,
+                unit,
+                order,
+            } = event
+            else {
+                continue;
+            };
+            let local = |unit| self.units.get(&unit).map(|entity| UnitId::from(*entity));
+            let (Some(unit), Some(order)) = (local(*unit), order.map_units(local)) else {
+                continue;
+            };
+            let Some(team) = self.players.get(player).map(|player| player.team) else {
+                continue;
+            };
+            self.world.send_event(OrderIssued {
+                player: *player,
+                team,
+                unit,
+                order,
+            });
+        }
+        self.schedule.run(&mut self.world);
+        self.world.resource_mut::<Events<OrderIssued>>().update();
+        self.world.resource_mut::<Events<OrderApplied>>().update();
+        self.world.resource_mut::<Events<Damaged>>().update();
+        self.world.resource_mut::<Events<UnitDied>>().update();
+        self.world.resource_mut::<Events<BossKilled>>().update();
+        self.world.resource_mut::<Events<RuneActivated>>().update();
+        self.world.resource_mut::<Events<WardPlaced>>().update();
+        self.world.resource_mut::<Events<FellTree>>().update();
+        self.world.resource_mut::<Events<HeroKilled>>().update();
+        let world = &self.world;
+        self.units
+            .retain(|_, entity| world.get_entity(*entity).is_some());
+
+        for event in events {
+            match event {
+                ReplayEvent::HeroSpawned {
+                    player,
+                    identity,
+                    name,
+                    team,
+                    unit,
+                } => {
+                    let entity = self.world.spawn(HeroBundle::new(player, team)).id();
+                    self.units.insert(unit, entity);
+                    self.players.insert(
+                        player,
+                        ReplayPlayer {
+                            identity,
+                            name,
+                            team,
+                        },
+                    );
+                }
+                ReplayEvent::NeutralSpawned { unit, camp, slot } => {
+                    let neutral =
+                        NeutralBundle::new(self.world.resource::<NeutralTable>(), camp, slot);
+                    self.units.insert(unit, self.world.spawn(neutral).id());
+                }
+                ReplayEvent::BossSpawned { unit } => {
+                    let minute = match_minute(Tick(self.tick), self.tick_rate());
+                    let boss = BossBundle::new(&self.world.resource::<NeutralTable>().boss, minute);
+                    self.units.insert(unit, self.world.spawn(boss).id());
+                }
+                ReplayEvent::IllusionSpawned {
+                    unit,
+                    owner,
+                    team,
+                    position,
+                    expires_at,
+                } => {
+                    let illusion = IllusionBundle::new(owner, team, position, expires_at);
+                    self.units.insert(unit, self.world.spawn(illusion).id());
+                }
+                ReplayEvent::CourierSpawned { unit, team } => {
+                    let courier = CourierBundle::new(team);
+                    self.units.insert(unit, self.world.spawn(courier).id());
+                }
+                ReplayEvent::WardSpawned {
+                    unit,
+                    team,
+                    kind,
+                    position,
+                    expires_at,
+                } => {
+                    let mut ward = self
+                        .world
+                        .spawn(WardBundle::new(team, kind, position, expires_at));
+                    if let Some(true_sight) = kind.true_sight() {
+                        ward.insert(true_sight);
+                    }
+                    self.units.insert(unit, ward.id());
+                }
+                ReplayEvent::HeroDespawned { unit } => {
+                    if let Some(entity) = self.units.remove(&unit) {
+                        self.world.despawn(entity);
+                    }
+                }
+                ReplayEvent::Order { .. } => (),
+            }
+        }
+
+        if let Some(recorded_hash) = recorded_hash {
+            if self.diverged_at.is_none() && self.state_hash() != recorded_hash {
+                self.diverged_at = Some(self.tick);
+            }
+        }
+
+        let next_keyframe = self.keyframes.last().unwrap().tick + self.keyframe_interval();
+        if self.tick == next_keyframe {
+            self.keyframes.push(self.keyframe());
+        }
+    }
+
+    /// Jumps to `tick`, resuming from the closest keyframe before it.
+    pub fn seek(&mut self, tick: u64) {
+        let tick = tick.min(self.end_tick());
+        let keyframe = self
+            .keyframes
+            .iter()
+            .rev()
+            .find(|keyframe| keyframe.tick <= tick)
+            .unwrap();
+        if tick < self.tick || keyframe.tick > self.tick {
+            self.restore(keyframe.clone());
+        }
+        while self.tick < tick {
+            self.step();
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let players = self
+            .players
+            .iter()
+            .map(|(player_id, player)| PlayerSnapshot {
+                id: *player_id,
+                identity: player.identity,
+                name: player.name.clone(),
+                team: player.team,
+                connected: true,
+            })
+            .collect();
+        let mut units = self
+            .units
+            .iter()
+            .filter_map(|(unit_id, entity)| {
+                let entity = self.world.get_entity(*entity)?;
+                Some(UnitSnapshot {
+                    id: *unit_id,
+                    owner: entity.get::<Owner>().map(|owner| owner.0),
+                    team: entity.get::<Team>().copied(),
+                    hero: entity.contains::<Hero>(),
+                    position: entity.get::<Position>()?.0,
+                    health: entity
+                        .get::<Health>()
+                        .map(|health| (health.current, health.max)),
+                    inventory: entity.get::<Inventory>().cloned(),
+                    stash: entity.get::<Stash>().map(|stash| stash.0.clone()),
+                    gold: entity.get::<Gold>().copied(),
+                    level: entity
+                        .get::<Experience>()
+                        .map(|experience| experience.level()),
+                    courier: entity.contains::<Courier>(),
+                })
+            })
+            .collect::<Vec<_>>();
+        units.sort_by_key(|unit| unit.id.0);
+        Snapshot {
+            players,
+            units,
+            clock: GameClock::at(Tick(self.tick), self.tick_rate()),
+            runes: self.world.resource::<Runes>().spots,
+            trees: self.world.resource::<Forest>().standing.clone(),
+        }
+    }
+
+    /// The [`state_hash`] of the current tick, comparable to the one the server had.
+    pub fn state_hash(&self) -> u64 {
+        let units = self
+            .units
+            .iter()
+            .filter_map(|(unit_id, entity)| {
+                let entity = self.world.get_entity(*entity)?;
+                Some(UnitState {
+                    id: *unit_id,
+                    owner: entity.get::<Owner>().map(|owner| owner.0),
+                    team: entity.get::<Team>().copied(),
+                    position: entity.get::<Position>()?.0,
+                    move_target: entity
+                        .get::<MoveTarget>()
+                        .and_then(|move_target| move_target.0),
+                    health: entity.get::<Health>().map(|health| health.current),
+                    inventory: entity.get::<Inventory>().cloned(),
+                    stash: entity.get::<Stash>().map(|stash| stash.0.clone()),
+                    gold: entity.get::<Gold>().copied(),
+                    experience: entity.get::<Experience>().copied(),
+                })
+            })
+            .collect();
+        let trees = &self.world.resource::<Forest>().standing;
+        state_hash(self.tick, self.world.resource::<MatchRng>(), units, trees)
+    }
+
+    fn keyframe(&self) -> Keyframe {
+        let ids = self
+            .units
+            .iter()
+            .map(|(unit_id, entity)| (*entity, *unit_id))
+            .collect::<HashMap<_, _>>();
+        let units = self
+            .units
+            .iter()
+            .filter_map(|(unit_id, entity)| {
+                let entity = self.world.get_entity(*entity)?;
+                let kind = if let Some(neutral) = entity.get::<Neutral>() {
+                    KeyframeKind::Neutral(*neutral)
+                } else if let Some(boss) = entity.get::<Boss>() {
+                    KeyframeKind::Boss(*boss)
+                } else if let Some(courier) = entity.get::<Courier>() {
+                    KeyframeKind::Courier {
+                        team: *entity.get::<Team>()?,
+                        courier: *courier,
+                    }
+                } else if let Some(ward) = entity.get::<Ward>() {
+                    KeyframeKind::Ward {
+                        team: *entity.get::<Team>()?,
+                        ward: *ward,
+                    }
+                } else if let Some(illusion) = entity.get::<Illusion>() {
+                    KeyframeKind::Illusion {
+                        owner: entity.get::<Owner>()?.0,
+                        team: *entity.get::<Team>()?,
+                        illusion: *illusion,
+                    }
+                } else {
+                    KeyframeKind::Hero {
+                        owner: entity.get::<Owner>()?.0,
+                        team: *entity.get::<Team>()?,
+                    }
+                };
+                Some(KeyframeUnit {
+                    id: *unit_id,
+                    kind,
+                    position: entity.get::<Position>()?.0,
+                    move_target: entity
+                        .get::<MoveTarget>()
+                        .and_then(|move_target| move_target.0),
+                    health: *entity.get::<Health>()?,
+                    attack: entity.get::<Attack>().copied(),
+                    attack_target: entity
+                        .get::<AttackTarget>()
+                        .and_then(|attack_target| attack_target.0)
+                        .and_then(|target| ids.get(&target).copied()),
+                    inventory: entity.get::<Inventory>().cloned(),
+                    stash: entity.get::<Stash>().cloned(),
+                    gold: entity.get::<Gold>().copied(),
+                    experience: entity.get::<Experience>().copied(),
+                    respawn: entity.get::<Respawn>().copied(),
+                    rune_target: entity.get::<RuneTarget>().copied(),
+                    rune_buff: entity.get::<RuneBuff>().copied(),
+                    invisibility: entity.get::<Invisibility>().copied(),
+                    ward_target: entity.get::<WardTarget>().copied(),
+                    tree_target: entity.get::<TreeTarget>().copied(),
+                })
+            })
+            .collect();
+        Keyframe {
+            tick: self.tick,
+            next_frame: self.next_frame,
+            players: self.players.clone(),
+            units,
+            rng: self.world.resource::<MatchRng>().clone(),
+            boss_pit: *self.world.resource::<BossPit>(),
+            runes: *self.world.resource::<Runes>(),
+            courier_respawns: self.world.resource::<CourierRespawns>().clone(),
+            ward_stock: self.world.resource::<WardStock>().clone(),
+            forest: self.world.resource::<Forest>().clone(),
+            trees: self
+                .trees
+                .iter()
+                .map(|entity| self.world.get::<Tree>(*entity).unwrap().regrows_at)
+                .collect(),
+        }
+    }
+
+    fn restore(&mut self, keyframe: Keyframe) {
+        for (_, entity) in self.units.drain() {
+            self.world.despawn(entity);
+        }
+        for unit in &keyframe.units {
+            let mut entity = match unit.kind {
+                KeyframeKind::Hero { owner, team } => {
+                    self.world.spawn(HeroBundle::new(owner, team))
+                }
+                KeyframeKind::Neutral(neutral) => {
+                    let mut bundle = NeutralBundle::new(
+                        self.world.resource::<NeutralTable>(),
+                        neutral.camp,
+                        neutral.slot,
+                    );
+                    bundle.neutral = neutral;
+                    self.world.spawn(bundle)
+                }
+                KeyframeKind::Boss(boss) => {
+                    let stats = &self.world.resource::<NeutralTable>().boss;
+                    let mut bundle = BossBundle::new(stats, boss.minute);
+                    bundle.boss = boss;
+                    self.world.spawn(bundle)
+                }
+                KeyframeKind::Illusion {
+                    owner,
+                    team,
+                    illusion,
+                } => self.world.spawn(IllusionBundle::new(
+                    owner,
+                    team,
+                    unit.position,
+                    illusion.expires_at,
+                )),
+                KeyframeKind::Courier { team, courier } => {
+                    let mut bundle = CourierBundle::new(team);
+                    bundle.courier = courier;
+                    self.world.spawn(bundle)
+                }
+                KeyframeKind::Ward { team, ward } => {
+                    let mut entity = self.world.spawn(WardBundle::new(
+                        team,
+                        ward.kind,
+                        unit.position,
+                        ward.expires_at,
+                    ));
+                    if let Some(true_sight) = ward.kind.true_sight() {
+                        entity.insert(true_sight);
+                    }
+                    entity
+                }
+            };
+            if let Some(inventory) = &unit.inventory {
+                entity.insert(inventory.clone());
+            }
+            if let Some(stash) = &unit.stash {
+                entity.insert(stash.clone());
+            }
+            if let Some(attack) = unit.attack {
+                entity.insert(attack);
+            }
+            if let Some(gold) = unit.gold {
+                entity.insert(gold);
+            }
+            if let Some(experience) = unit.experience {
+                entity.insert(experience);
+            }
+            if let Some(respawn) = unit.respawn {
+                entity.insert(respawn);
+            }
+            if let Some(rune_target) = unit.rune_target {
+                entity.insert(rune_target);
+            }
+            if let Some(rune_buff) = unit.rune_buff {
+                entity.insert(rune_buff);
+            }
+            if let Some(invisibility) = unit.invisibility {
+                entity.insert(invisibility);
+            }
+            if let Some(ward_target) = unit.ward_target {
+                entity.insert(ward_target);
+            }
+            if let Some(tree_target) = unit.tree_target {
+                entity.insert(tree_target);
+            }
+            if entity.contains::<MoveTarget>() {
+                entity.insert(MoveTarget(unit.move_target));
+            }
+            entity.insert((Position(unit.position), unit.health));
+            self.units.insert(unit.id, entity.id());
+        }
+        for unit in &keyframe.units {
+            let mut entity = self.world.entity_mut(self.units[&unit.id]);
+            if entity.contains::<AttackTarget>() {
+                let target = unit
+                    .attack_target
+                    .and_then(|target| self.units.get(&target).copied());
+                entity.insert(AttackTarget(target));
+            }
+        }
+        self.tick = keyframe.tick;
+        self.next_frame = keyframe.next_frame;
+        self.players = keyframe.players;
+        self.world.insert_resource(keyframe.rng);
+        self.world.insert_resource(keyframe.boss_pit);
+        self.world.insert_resource(keyframe.runes);
+        self.world.insert_resource(keyframe.courier_respawns);
+        self.world.insert_resource(keyframe.ward_stock);
+        self.world.insert_resource(keyframe.forest);
+        for (entity, regrows_at) in self.trees.iter().zip(keyframe.trees) {
+            self.world.get_mut::<Tree>(*entity).unwrap().regrows_at = regrows_at;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::{
+        config::ServerConfig,
+        order::Order,
+        replay::{ReplayFrame, ReplayHeader},
+        unit::HERO_HEALTH,
+    };
+
+    fn replay() -> Replay {
+        let header = ReplayHeader::new(&ServerConfig::default(), 1, UNIX_EPOCH);
+        let hero = UnitId(100);
+        let kobold = UnitId(200);
+        let order = |tick, order| ReplayFrame {
+            tick,
+            events: vec![ReplayEvent::Order {
+                player: PlayerId(1),
+                unit: hero,
+                order,
+            }],
+            state_hash: None,
+        };
+        let move_to = |tick, target| order(tick, Order::Move { target });
+        let frames = vec![
+            ReplayFrame {
+                tick: 1,
+                events: vec![ReplayEvent::HeroSpawned {
+                    player: PlayerId(1),
+                    identity: Identity([1; 32]),
+                    name: "alice".to_string(),
+                    team: Team::Radiant,
+                    unit: hero,
+                }],
+                state_hash: None,
+            },
+            move_to(2, Vec2::new(400.0, 400.0)),
+            ReplayFrame {
+                tick: 5,
+                events: vec![ReplayEvent::NeutralSpawned {
+                    unit: kobold,
+                    camp: 0,
+                    slot: 0,
+                }],
+                state_hash: None,
+            },
+            move_to(400, Vec2::new(-400.0, 0.0)),
+            order(600, Order::Attack { target: kobold }),
+            ReplayFrame {
+                tick: 900,
+                events: vec![ReplayEvent::HeroDespawned { unit: hero }],
+                state_hash: None,
+            },
+        ];
+        Replay { header, frames }
+    }
+
+    #[test]
+    fn test_simulates_orders() {
+        let mut simulation = ReplaySimulation::new(replay());
+        simulation.step();
+        let snapshot = simulation.snapshot();
+        assert_eq!(snapshot.players[0].name, "alice");
+        assert_eq!(snapshot.units[0].id, UnitId(100));
+        assert_eq!(snapshot.units[0].position, Team::Radiant.fountain());
+
+        simulation.seek(3);
+        let moved = simulation.snapshot().units[0].position;
+        assert!(moved.x > Team::Radiant.fountain().x);
+
+        // The hero kills the kobold it was sent to attack.
+        simulation.seek(600);
+        assert_eq!(simulation.snapshot().units.len(), 2);
+        simulation.seek(899);
+        let snapshot = simulation.snapshot();
+        assert_eq!(snapshot.units.len(), 1);
+        assert!(snapshot.units[0].health.unwrap().0 < HERO_HEALTH);
+
+        simulation.seek(simulation.end_tick());
+        assert!(simulation.is_finished());
+        assert!(simulation.snapshot().units.is_empty());
+    }
+
+    #[test]
+    fn test_seeking_matches_playing() {
+        let mut played = ReplaySimulation::new(replay());
+        let mut positions = Vec::new();
+        while !played.is_finished() {
+            played.step();
+            positions.push(played.snapshot());
+        }
+        assert!(played.keyframes.len() > 2);
+
+        let mut seeking = ReplaySimulation::new(replay());
+        for tick in [850, 20, 600, 301, 899, 1, 650] {
+            seeking.seek(tick);
+            assert_eq!(seeking.tick(), tick);
+            assert_eq!(seeking.snapshot(), positions[tick as usize - 1]);
+        }
+    }
+
+    #[test]
+    fn test_detects_divergence() {
+        let mut simulation = ReplaySimulation::new(replay());
+        simulation.seek(450);
+        let hash = simulation.state_hash();
+
+        let with_hash = |state_hash| {
+            let mut replay = replay();
+            replay.frames.insert(
+                4,
+                ReplayFrame {
+                    tick: 450,
+                    events: Vec::new(),
+                    state_hash: Some(state_hash),
+                },
+            );
+            let mut simulation = ReplaySimulation::new(replay);
+            simulation.seek(simulation.end_tick());
+            simulation.diverged_at()
+        };
+        assert_eq!(with_hash(hash), None);
+        assert_eq!(with_hash(hash ^ 1), Some(450));
+    }
+}
@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::shared::ClientId;
+use serde::{Deserialize, Serialize};
+
+use crate::{communication::PingCooldown, identity::Identity};
+
+pub const RECONNECT_GRACE_PERIOD: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PlayerId(pub u32);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Team {
+    Radiant,
+    Dire,
+}
+
+/// Whether a client joins to play or to watch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JoinRole {
+    #[default]
+    Player,
+    Spectator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JoinRejection {
+    ServerFull,
+    SpectatorsFull,
+    InvalidName,
+    InvalidSignature,
+    WrongPassword,
+    NotAllowlisted,
+    Banned,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerConnection {
+    Connected(ClientId),
+    Disconnected { since: Duration },
+}
+
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub identity: Identity,
+    pub name: String,
+    pub team: Team,
+    pub connection: PlayerConnection,
+    pub hero: Option<Entity>,
+    pub ping_cooldown: PingCooldown,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Players {
+    players: HashMap<PlayerId, Player>,
+    last_player_id: u32,
+}
+
+impl Team {
+    pub fn opponent(self) -> Self {
+        match self {
+            Self::Radiant => Self::Dire,
+            Self::Dire => Self::Radiant,
+        }
+    }
+
+    pub fn fountain(self) -> Vec2 {
+        match self {
+            Self::Radiant => Vec2::new(-400.0, -250.0),
+            Self::Dire => Vec2::new(400.0, 250.0),
+        }
+    }
+}
+
+impl JoinRejection {
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::ServerFull => "The server is full",
+            Self::SpectatorsFull => "The server does not accept any more spectators",
+            Self::InvalidName => "Player names may only contain letters, digits, '-' and '_'",
+            Self::InvalidSignature => "The server could not verify your identity",
+            Self::WrongPassword => "Wrong server password",
+            Self::NotAllowlisted => "You are not on this server's allowlist",
+            Self::Banned => "You are banned from this server",
+        }
+    }
+}
+
+impl Player {
+    pub fn new(identity: Identity, name: String, team: Team, client_id: ClientId) -> Self {
+        Self {
+            identity,
+            name,
+            team,
+            connection: PlayerConnection::Connected(client_id),
+            hero: None,
+            ping_cooldown: Default::default(),
+        }
+    }
+
+    pub fn client_id(&self) -> Option<ClientId> {
+        match self.connection {
+            PlayerConnection::Connected(client_id) => Some(client_id),
+            PlayerConnection::Disconnected { .. } => None,
+        }
+    }
+}
+
+impl Players {
+    /// Adds a player to whichever team currently has the fewest members.
+    pub fn join(&mut self, client_id: ClientId, identity: Identity, name: String) -> PlayerId {
+        if let Some(player_id) = self.player_id(client_id) {
+            return player_id;
+        }
+        let team = if self.team_size(Team::Dire) < self.team_size(Team::Radiant) {
+            Team::Dire
+        } else {
+            Team::Radiant
+        };
+        self.last_player_id += 1;
+        let player_id = PlayerId(self.last_player_id);
+        self.players
+            .insert(player_id, Player::new(identity, name, team, client_id));
+        player_id
+    }
+
+    /// Hands the slot owned by `identity` over to `client_id`.
+    ///
+    /// Returns the reclaimed player and the client previously holding the slot, if the server had
+    /// not noticed that connection dropping yet.
+    pub fn reconnect(
+        &mut self,
+        client_id: ClientId,
+        identity: &Identity,
+    ) -> Option<(PlayerId, Option<ClientId>)> {
+        let (player_id, player) = self
+            .players
+            .iter_mut()
+            .find(|(_, player)| &player.identity == identity)?;
+        let replaced = player.client_id();
+        player.connection = PlayerConnection::Connected(client_id);
+        Some((*player_id, replaced))
+    }
+
+    pub fn disconnect(&mut self, client_id: ClientId, now: Duration) -> Option<PlayerId> {
+        let player_id = self.player_id(client_id)?;
+        self.players.get_mut(&player_id)?.connection =
+            PlayerConnection::Disconnected { since: now };
+        Some(player_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    pub fn leave(&mut self, player_id: PlayerId) -> Option<Player> {
+        self.players.remove(&player_id)
+    }
+
+    /// Removes and returns every player that has been disconnected for longer than the grace
+    /// period.
+    pub fn remove_abandoned(&mut self, now: Duration) -> Vec<(PlayerId, Player)> {
+        let abandoned = self
+            .players
+            .iter()
+            .filter(|(_, player)| match player.connection {
+                PlayerConnection::Disconnected { since } => now >= since + RECONNECT_GRACE_PERIOD,
+                PlayerConnection::Connected(_) => false,
+            })
+            .map(|(player_id, _)| *player_id)
+            .collect::<Vec<_>>();
+        abandoned
+            .into_iter()
+            .filter_map(|player_id| Some((player_id, self.players.remove(&player_id)?)))
+            .collect()
+    }
+
+    pub fn get(&self, player_id: PlayerId) -> Option<&Player> {
+        self.players.get(&player_id)
+    }
+
+    pub fn get_mut(&mut self, player_id: PlayerId) -> Option<&mut Player> {
+        self.players.get_mut(&player_id)
+    }
+
+    pub fn player_id(&self, client_id: ClientId) -> Option<PlayerId> {
+        self.players
+            .iter()
+            .find(|(_, player)| player.client_id() == Some(client_id))
+            .map(|(player_id, _)| *player_id)
+    }
+
+    pub fn find(&self, identity: &Identity) -> Option<PlayerId> {
+        self.players
+            .iter()
+            .find(|(_, player)| &player.identity == identity)
+            .map(|(player_id, _)| *player_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PlayerId, &Player)> {
+        self.players.iter()
+    }
+
+    /// Returns the clients of all connected players.
+    pub fn clients(&self) -> Vec<ClientId> {
+        self.players
+            .values()
+            .filter_map(|player| player.client_id())
+            .collect()
+    }
+
+    pub fn team_size(&self, team: Team) -> usize {
+        self.players
+            .values()
+            .filter(|player| player.team == team)
+            .count()
+    }
+
+    /// Returns the clients of all connected players on `team`.
+    pub fn teammates(&self, team: Team) -> Vec<ClientId> {
+        self.players
+            .values()
+            .filter(|player| player.team == team)
+            .filter_map(|player| player.client_id())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(n: u64) -> Identity {
+        Identity([n as u8; 32])
+    }
+
+    fn join(players: &mut Players, client_id: ClientId) -> PlayerId {
+        players.join(client_id, identity(client_id), format!("player{client_id}"))
+    }
+
+    #[test]
+    fn test_join_balances_teams() {
+        let mut players = Players::default();
+        let first = join(&mut players, 1);
+        let second = join(&mut players, 2);
+        let third = join(&mut players, 3);
+        assert_eq!(players.get(first).unwrap().team, Team::Radiant);
+        assert_eq!(players.get(second).unwrap().team, Team::Dire);
+        assert_eq!(players.get(third).unwrap().team, Team::Radiant);
+        players.leave(first);
+        players.leave(third);
+        let fourth = join(&mut players, 4);
+        assert_eq!(players.get(fourth).unwrap().team, Team::Radiant);
+    }
+
+    #[test]
+    fn test_rejoin_keeps_player() {
+        let mut players = Players::default();
+        let player_id = join(&mut players, 1);
+        assert_eq!(join(&mut players, 1), player_id);
+        assert_eq!(players.team_size(Team::Radiant), 1);
+    }
+
+    #[test]
+    fn test_teammates() {
+        let mut players = Players::default();
+        for client_id in 1..=4 {
+            join(&mut players, client_id);
+        }
+        players.disconnect(3, Duration::ZERO);
+        assert_eq!(players.teammates(Team::Radiant), vec![1]);
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let mut players = Players::default();
+        let player_id = join(&mut players, 1);
+        assert_eq!(players.disconnect(1, Duration::ZERO), Some(player_id));
+        assert_eq!(players.player_id(1), None);
+
+        assert_eq!(players.reconnect(2, &identity(2)), None);
+        assert_eq!(players.reconnect(2, &identity(1)), Some((player_id, None)));
+        assert_eq!(players.player_id(2), Some(player_id));
+        assert_eq!(
+            players.reconnect(3, &identity(1)),
+            Some((player_id, Some(2)))
+        );
+    }
+
+    #[test]
+    fn test_remove_abandoned() {
+        let mut players = Players::default();
+        let stays = join(&mut players, 1);
+        let leaves = join(&mut players, 2);
+        players.disconnect(1, Duration::from_secs(10));
+        players.disconnect(2, Duration::ZERO);
+
+        let abandoned = players.remove_abandoned(RECONNECT_GRACE_PERIOD);
+        assert_eq!(abandoned.len(), 1);
+        assert_eq!(abandoned[0].0, leaves);
+        assert!(players.get(stays).is_some());
+        assert!(players.get(leaves).is_none());
+    }
+}
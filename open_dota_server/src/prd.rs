@@ -0,0 +1,188 @@
+//! Pseudo-random distribution for chance-based effects. Instead of rolling the nominal chance
+//! every time, the `n`th attempt since the last proc succeeds with chance `C * n`, where the
+//! constant `C` is picked so that procs still happen at the nominal rate on average. Long streaks
+//! with or without procs become much rarer than with independent rolls.
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::MatchRng;
+
+/// `C` for nominal chances in steps of 5%, from 5% to 95%. From 70% on, `C` is `(2p - 1) / p`.
+pub const PRD_C_TABLE: [(f64, f64); 19] = [
+    (0.05, 0.003801658303553135),
+    (0.10, 0.014745844781072673),
+    (0.15, 0.03222091437308766),
+    (0.20, 0.05570404294978186),
+    (0.25, 0.08474409185231696),
+    (0.30, 0.118949192725404),
+    (0.35, 0.157983098125747),
+    (0.40, 0.20154741360775402),
+    (0.45, 0.2493069984401633),
+    (0.50, 0.3021030253487419),
+    (0.55, 0.3603978509331688),
+    (0.60, 0.42264973081037416),
+    (0.65, 0.4811254783372291),
+    (0.70, 0.5714285714285714),
+    (0.75, 0.6666666666666666),
+    (0.80, 0.75),
+    (0.85, 0.8235294117647058),
+    (0.90, 0.8888888888888888),
+    (0.95, 0.9473684210526315),
+];
+
+/// Effects that proc by chance. Every unit keeps a separate streak for each of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProcEffect {
+    CriticalStrike,
+    Bash,
+    Evasion,
+}
+
+/// A nominal chance along with its PRD constant, which is costly to find for chances not in
+/// [`PRD_C_TABLE`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcChance {
+    pub chance: f64,
+    pub constant: f64,
+}
+
+/// Attempts since the last proc of each effect of a unit.
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProcCounters(HashMap<ProcEffect, u32>);
+
+/// The average chance to proc with constant `c`.
+fn chance_of_constant(c: f64) -> f64 {
+    let mut expected_attempts = 0.0;
+    let mut no_proc_yet = 1.0;
+    let mut attempt = 1;
+    loop {
+        let chance = (c * attempt as f64).min(1.0);
+        expected_attempts += attempt as f64 * no_proc_yet * chance;
+        no_proc_yet *= 1.0 - chance;
+        if chance >= 1.0 {
+            return 1.0 / expected_attempts;
+        }
+        attempt += 1;
+    }
+}
+
+/// Finds `C` for a nominal `chance` by bisection, the average chance grows with `C`.
+fn solve_constant(chance: f64) -> f64 {
+    let (mut low, mut high) = (0.0, chance);
+    for _ in 0..64 {
+        let middle = (low + high) / 2.0;
+        if chance_of_constant(middle) < chance {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// The constant `C` for a nominal `chance` between 0 and 1.
+pub fn prd_constant(chance: f64) -> f64 {
+    if chance <= 0.0 || chance >= 1.0 {
+        return chance.clamp(0.0, 1.0);
+    }
+    PRD_C_TABLE
+        .iter()
+        .find(|(nominal, _)| (nominal - chance).abs() < 1e-9)
+        .map_or_else(|| solve_constant(chance), |(_, c)| *c)
+}
+
+impl ProcChance {
+    pub fn new(chance: f64) -> Self {
+        Self {
+            chance,
+            constant: prd_constant(chance),
+        }
+    }
+}
+
+impl ProcCounters {
+    /// Rolls `effect`, returning whether it procs.
+    pub fn roll(&mut self, effect: ProcEffect, chance: ProcChance, rng: &mut MatchRng) -> bool {
+        let attempts = self.0.entry(effect).or_default();
+        *attempts += 1;
+        let procs = rng.gen_bool((chance.constant * *attempts as f64).min(1.0));
+        if procs {
+            *attempts = 0;
+        }
+        procs
+    }
+
+    /// Attempts since `effect` last procced.
+    pub fn attempts(&self, effect: ProcEffect) -> u32 {
+        self.0.get(&effect).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::MatchSeed;
+
+    const ROLLS: u32 = 200_000;
+
+    #[test]
+    fn test_table_matches_solved_constants() {
+        for (chance, c) in PRD_C_TABLE {
+            assert!(
+                (solve_constant(chance) - c).abs() < 1e-6,
+                "C for {chance} should be {}, table has {c}",
+                solve_constant(chance)
+            );
+            assert!((chance_of_constant(c) - chance).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_proc_frequency_matches_chance() {
+        let mut rng = MatchRng::new(MatchSeed(40));
+        for chance in [0.05, 0.17, 0.25, 0.5, 0.8] {
+            let chance = ProcChance::new(chance);
+            let mut counters = ProcCounters::default();
+            let procs = (0..ROLLS)
+                .filter(|_| counters.roll(ProcEffect::CriticalStrike, chance, &mut rng))
+                .count();
+            let frequency = procs as f64 / ROLLS as f64;
+            assert!(
+                (frequency - chance.chance).abs() < 0.005,
+                "{} procced with frequency {frequency}",
+                chance.chance
+            );
+        }
+    }
+
+    #[test]
+    fn test_streaks_are_bounded() {
+        let mut rng = MatchRng::new(MatchSeed(41));
+        let mut counters = ProcCounters::default();
+        let chance = ProcChance::new(0.25);
+        let longest_possible = (1.0 / chance.constant).ceil() as u32;
+        let mut longest = 0;
+        for _ in 0..ROLLS {
+            if !counters.roll(ProcEffect::Bash, chance, &mut rng) {
+                longest = longest.max(counters.attempts(ProcEffect::Bash));
+            }
+        }
+        assert!(longest < longest_possible);
+    }
+
+    #[test]
+    fn test_effects_are_counted_separately() {
+        let mut rng = MatchRng::new(MatchSeed(42));
+        let mut counters = ProcCounters::default();
+        // Nothing procs with no chance, but the other effect's streak isn't touched.
+        counters.roll(ProcEffect::Bash, ProcChance::new(0.0), &mut rng);
+        counters.roll(ProcEffect::Bash, ProcChance::new(0.0), &mut rng);
+        assert_eq!(counters.attempts(ProcEffect::Bash), 2);
+        assert_eq!(counters.attempts(ProcEffect::Evasion), 0);
+        assert!(counters.roll(ProcEffect::Evasion, ProcChance::new(1.0), &mut rng));
+        assert_eq!(counters.attempts(ProcEffect::Evasion), 0);
+        assert_eq!(counters.attempts(ProcEffect::Bash), 2);
+    }
+}
@@ -0,0 +1,566 @@
+//! Replays record what a match needs to be simulated again: its seed and rules, and every
+//! applied order and hero spawn or despawn along with the tick it happened on. Once a second they
+//! also record the [`StateHash`] of the match, so that playback can tell when it diverges. Units
+//! spawned by systems outside the simulation, like illusions and wards, are recorded like any
+//! other spawn.
+//!
+//! A replay file starts with [`REPLAY_MAGIC`] and the format version, followed by a
+//! [`ReplayHeader`] and a [`ReplayFrame`] for every tick anything happened or a hash was recorded
+//! on, all encoded with varint bincode. Frames are flushed as they are written, so the file stays
+//! readable if the server is killed.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    boss::Boss,
+    clock::DAY_NIGHT_SECS,
+    config::{GameMode, ServerConfig},
+    courier::{
+        Courier, COURIER_BURST_COOLDOWN_SECS, COURIER_BURST_MOVE_SPEED, COURIER_BURST_SECS,
+        COURIER_HEALTH, COURIER_MOVE_SPEED, COURIER_RESPAWN_SECS, COURIER_TRANSFER_RANGE,
+        COURIER_VISION,
+    },
+    death::{
+        ASSIST_GOLD, ASSIST_GOLD_PER_LEVEL, ASSIST_RANGE, BUYBACK_COOLDOWN_SECS, BUYBACK_COST,
+        BUYBACK_COST_PER_LEVEL, DEATH_GOLD_LOSS_PER_LEVEL, HERO_BOUNTY_EXPERIENCE,
+        HERO_BOUNTY_EXPERIENCE_PER_LEVEL, HERO_BOUNTY_GOLD, HERO_BOUNTY_GOLD_PER_LEVEL,
+        RESPAWN_SECS, RESPAWN_SECS_PER_LEVEL,
+    },
+    identity::{encode_hex, Identity},
+    illusion::{Illusion, ILLUSION_DAMAGE_DEALT, ILLUSION_DAMAGE_TAKEN, ILLUSION_OFFSETS},
+    invisibility::INVISIBILITY_FADE_SECS,
+    item::{GEM_TRUE_SIGHT_RANGE, STASH_RANGE},
+    neutral::{Neutral, NEUTRAL_TABLE},
+    order::{Order, OrderApplied},
+    player::{PlayerId, Players, Team},
+    rune::{
+        BOUNTY_RUNE_GOLD, BOUNTY_RUNE_INTERVAL_SECS, BOUNTY_SPOT_COUNT, DOUBLE_DAMAGE_MULTIPLIER,
+        HASTE_MOVE_SPEED, POWER_UPS, POWER_UP_RUNE_INTERVAL_SECS, REGENERATION_PER_SEC,
+        RUNE_PICKUP_RANGE, RUNE_SPOTS,
+    },
+    simulation::{MatchSeed, StateHash, Tick, TickRate},
+    tree::{TREE_CUT_RANGE, TREE_LAYOUT},
+    unit::{
+        Hero, Owner, Position, UnitId, HERO_ATTACK_COOLDOWN, HERO_ATTACK_RANGE, HERO_DAMAGE,
+        HERO_DAY_VISION, HERO_HEALTH, HERO_MOVE_SPEED, HERO_NIGHT_VISION, LEVEL_EXPERIENCE,
+        MAP_HALF_SIZE,
+    },
+    ward::{
+        Ward, WardKind, OBSERVER_WARD_VISION, SENTRY_WARD_TRUE_SIGHT, SENTRY_WARD_VISION,
+        WARD_BOUNTY_GOLD, WARD_HITS, WARD_KINDS, WARD_PLACE_RANGE,
+    },
+};
+
+pub const REPLAY_MAGIC: &[u8; 4] = b"ODRP";
+/// Bumped whenever the encoding of the header or the frames changes, including the [`Order`]s
+/// they carry, since bincode encodes enum variants by their index.
+pub const REPLAY_VERSION: u32 = 4;
+pub const REPLAY_EXTENSION: &str = "odr";
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("replay I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("malformed replay: {0}")]
+    Encoding(#[from] bincode::Error),
+    #[error("not a replay file")]
+    NotAReplay,
+    #[error("replay format version {0} is not supported, expected {REPLAY_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("replay was recorded with different game content: {}", .0.join(", "))]
+    ContentMismatch(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub seed: u64,
+    pub tick_rate: u32,
+    pub game_mode: GameMode,
+    pub map: String,
+    /// Hashes of the game content the match was played with, see [`content_hashes`].
+    pub content: BTreeMap<String, String>,
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+}
+
+/// Within a tick, orders were applied before units moved and fought, and units were spawned and
+/// despawned after.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplayEvent {
+    HeroSpawned {
+        player: PlayerId,
+        identity: Identity,
+        name: String,
+        team: Team,
+        unit: UnitId,
+    },
+    HeroDespawned {
+        unit: UnitId,
+    },
+    /// A neutral creep spawned in `slot` of the `camp`th spawner of the
+    /// [`NeutralTable`](crate::neutral::NeutralTable).
+    NeutralSpawned {
+        unit: UnitId,
+        camp: usize,
+        slot: usize,
+    },
+    BossSpawned {
+        unit: UnitId,
+    },
+    CourierSpawned {
+        unit: UnitId,
+        team: Team,
+    },
+    IllusionSpawned {
+        unit: UnitId,
+        owner: PlayerId,
+        team: Team,
+        position: Vec2,
+        expires_at: u64,
+    },
+    WardSpawned {
+        unit: UnitId,
+        team: Team,
+        kind: WardKind,
+        position: Vec2,
+        expires_at: u64,
+    },
+    Order {
+        player: PlayerId,
+        unit: UnitId,
+        order: Order,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub tick: u64,
+    pub events: Vec<ReplayEvent>,
+    /// The [`StateHash`] at the end of the tick, recorded once a second.
+    pub state_hash: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub frames: Vec<ReplayFrame>,
+}
+
+pub struct ReplayWriter<W: Write> {
+    writer: W,
+}
+
+/// Records the running match, only present if the server was asked to record replays.
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    path: PathBuf,
+    writer: ReplayWriter<BufWriter<File>>,
+}
+
+fn encoding() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+impl ReplayHeader {
+    pub fn new(config: &ServerConfig, seed: u64, now: SystemTime) -> Self {
+        Self {
+            seed,
+            tick_rate: config.tick_rate,
+            game_mode: config.game_mode,
+            map: config.map.clone(),
+            content: content_hashes(),
+            recorded_at: now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        }
+    }
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read_from(mut reader: impl BufRead) -> Result<Self, ReplayError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != REPLAY_MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+        let version: u32 = encoding().deserialize_from(&mut reader)?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let header: ReplayHeader = encoding().deserialize_from(&mut reader)?;
+        let content = content_hashes();
+        let mismatched: Vec<String> = content
+            .keys()
+            .chain(header.content.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|name| content.get(*name) != header.content.get(*name))
+            .cloned()
+            .collect();
+        if !mismatched.is_empty() {
+            return Err(ReplayError::ContentMismatch(mismatched));
+        }
+        let mut frames = Vec::new();
+        while !reader.fill_buf()?.is_empty() {
+            frames.push(encoding().deserialize_from(&mut reader)?);
+        }
+        Ok(Self { header, frames })
+    }
+
+    /// The last tick anything happened on.
+    pub fn last_tick(&self) -> u64 {
+        self.frames.last().map_or(0, |frame| frame.tick)
+    }
+}
+
+impl<W: Write> ReplayWriter<W> {
+    pub fn new(mut writer: W, header: &ReplayHeader) -> Result<Self, ReplayError> {
+        writer.write_all(REPLAY_MAGIC)?;
+        encoding().serialize_into(&mut writer, &REPLAY_VERSION)?;
+        encoding().serialize_into(&mut writer, header)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_frame(&mut self, frame: &ReplayFrame) -> Result<(), ReplayError> {
+        encoding().serialize_into(&mut self.writer, frame)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl ReplayRecorder {
+    /// Starts a new replay file in `dir`, named after the time and seed of the match.
+    pub fn create(dir: &Path, header: &ReplayHeader) -> Result<Self, ReplayError> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "{}-{:016x}.{REPLAY_EXTENSION}",
+            header.recorded_at, header.seed
+        ));
+        let writer = ReplayWriter::new(BufWriter::new(File::create(&path)?), header)?;
+        Ok(Self { path, writer })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Hashes of everything besides orders that decides how a match plays out. A replay recorded with
+/// different content will not simulate the same.
+pub fn content_hashes() -> BTreeMap<String, String> {
+    let rules = (
+        MAP_HALF_SIZE,
+        HERO_MOVE_SPEED,
+        HERO_HEALTH,
+        HERO_DAMAGE,
+        HERO_ATTACK_RANGE,
+        HERO_ATTACK_COOLDOWN,
+        (HERO_DAY_VISION, HERO_NIGHT_VISION, DAY_NIGHT_SECS),
+        Team::Radiant.fountain(),
+        Team::Dire.fountain(),
+    );
+    let runes = (
+        RUNE_SPOTS,
+        BOUNTY_SPOT_COUNT,
+        BOUNTY_RUNE_INTERVAL_SECS,
+        POWER_UP_RUNE_INTERVAL_SECS,
+        BOUNTY_RUNE_GOLD,
+        RUNE_PICKUP_RANGE,
+        HASTE_MOVE_SPEED,
+        DOUBLE_DAMAGE_MULTIPLIER,
+        REGENERATION_PER_SEC,
+        POWER_UPS.map(|kind| (kind, kind.duration_secs())),
+        ILLUSION_OFFSETS,
+        ILLUSION_DAMAGE_DEALT,
+        ILLUSION_DAMAGE_TAKEN,
+        INVISIBILITY_FADE_SECS,
+        GEM_TRUE_SIGHT_RANGE,
+    );
+    let couriers = (
+        COURIER_MOVE_SPEED,
+        COURIER_HEALTH,
+        COURIER_VISION,
+        COURIER_BURST_MOVE_SPEED,
+        COURIER_BURST_SECS,
+        COURIER_BURST_COOLDOWN_SECS,
+        COURIER_RESPAWN_SECS,
+        COURIER_TRANSFER_RANGE,
+        STASH_RANGE,
+    );
+    let deaths = (
+        (
+            RESPAWN_SECS,
+            RESPAWN_SECS_PER_LEVEL,
+            DEATH_GOLD_LOSS_PER_LEVEL,
+        ),
+        (HERO_BOUNTY_GOLD, HERO_BOUNTY_GOLD_PER_LEVEL),
+        (HERO_BOUNTY_EXPERIENCE, HERO_BOUNTY_EXPERIENCE_PER_LEVEL),
+        (ASSIST_GOLD, ASSIST_GOLD_PER_LEVEL, ASSIST_RANGE),
+        (BUYBACK_COST, BUYBACK_COST_PER_LEVEL, BUYBACK_COOLDOWN_SECS),
+        LEVEL_EXPERIENCE,
+    );
+    let wards = (
+        WARD_PLACE_RANGE,
+        WARD_HITS,
+        WARD_BOUNTY_GOLD,
+        OBSERVER_WARD_VISION,
+        SENTRY_WARD_VISION,
+        SENTRY_WARD_TRUE_SIGHT,
+        WARD_KINDS.map(|kind| {
+            (
+                kind,
+                kind.cost(),
+                kind.duration_secs(),
+                kind.initial_stock(),
+                kind.max_stock(),
+                kind.restock_secs(),
+            )
+        }),
+    );
+    let hash = |bytes: &[u8]| encode_hex(&Sha256::digest(bytes));
+    BTreeMap::from([
+        (
+            "rules".to_string(),
+            hash(&encoding().serialize(&rules).unwrap()),
+        ),
+        ("neutrals".to_string(), hash(NEUTRAL_TABLE.as_bytes())),
+        (
+            "trees".to_string(),
+            hash(
+                &encoding()
+                    .serialize(&(TREE_LAYOUT, TREE_CUT_RANGE))
+                    .unwrap(),
+            ),
+        ),
+        (
+            "runes".to_string(),
+            hash(&encoding().serialize(&runes).unwrap()),
+        ),
+        (
+            "couriers".to_string(),
+            hash(&encoding().serialize(&couriers).unwrap()),
+        ),
+        (
+            "wards".to_string(),
+            hash(&encoding().serialize(&wards).unwrap()),
+        ),
+        (
+            "deaths".to_string(),
+            hash(&encoding().serialize(&deaths).unwrap()),
+        ),
+    ])
+}
+
+pub fn start_recording(mut commands: Commands, config: Res<ServerConfig>, seed: Res<MatchSeed>) {
+    let Some(dir) = &config.replay_dir else {
+        return;
+    };
+    let header = ReplayHeader::new(&config, seed.0, SystemTime::now());
+    match ReplayRecorder::create(dir, &header) {
+        Ok(recorder) => {
+            info!("Recording replay to {:?}", recorder.path());
+            commands.insert_resource(recorder);
+        }
+        Err(err) => error!("Failed to start recording a replay in {dir:?}: {err}"),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn record_replay(
+    mut commands: Commands,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut order_events: EventReader<OrderApplied>,
+    mut despawned_heroes: RemovedComponents<Hero>,
+    spawned_query: Query<(Entity, &Owner), Added<Hero>>,
+    neutral_query: Query<(Entity, &Neutral), Added<Neutral>>,
+    boss_query: Query<Entity, Added<Boss>>,
+    courier_query: Query<(Entity, &Team), Added<Courier>>,
+    illusion_query: Query<(Entity, &Illusion, &Owner, &Team, &Position), Added<Illusion>>,
+    ward_query: Query<(Entity, &Ward, &Team, &Position), Added<Ward>>,
+    players: Res<Players>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    state_hash: Res<StateHash>,
+) {
+    let mut events = order_events
+        .iter()
+        .map(|OrderApplied(order)| ReplayEvent::Order {
+            player: order.player,
+            unit: order.unit,
+            order: order.order,
+        })
+        .collect::<Vec<_>>();
+    for (entity, owner) in &spawned_query {
+        let Some(player) = players.get(owner.0) else {
+            continue;
+        };
+        events.push(ReplayEvent::HeroSpawned {
+            player: owner.0,
+            identity: player.identity,
+            name: player.name.clone(),
+            team: player.team,
+            unit: entity.into(),
+        });
+    }
+    events.extend(
+        neutral_query
+            .iter()
+            .map(|(entity, neutral)| ReplayEvent::NeutralSpawned {
+                unit: entity.into(),
+                camp: neutral.camp,
+                slot: neutral.slot,
+            }),
+    );
+    events.extend(boss_query.iter().map(|entity| ReplayEvent::BossSpawned {
+        unit: entity.into(),
+    }));
+    events.extend(
+        courier_query
+            .iter()
+            .map(|(entity, team)| ReplayEvent::CourierSpawned {
+                unit: entity.into(),
+                team: *team,
+            }),
+    );
+    events.extend(
+        illusion_query
+            .iter()
+            .map(
+                |(entity, illusion, owner, team, position)| ReplayEvent::IllusionSpawned {
+                    unit: entity.into(),
+                    owner: owner.0,
+                    team: *team,
+                    position: position.0,
+                    expires_at: illusion.expires_at,
+                },
+            ),
+    );
+    events.extend(ward_query.iter().map(|(entity, ward, team, position)| {
+        ReplayEvent::WardSpawned {
+            unit: entity.into(),
+            team: *team,
+            kind: ward.kind,
+            position: position.0,
+            expires_at: ward.expires_at,
+        }
+    }));
+    events.extend(
+        despawned_heroes
+            .iter()
+            .map(|entity| ReplayEvent::HeroDespawned {
+                unit: entity.into(),
+            }),
+    );
+    let state_hash = tick
+        .0
+        .is_multiple_of(tick_rate.0 as u64)
+        .then_some(state_hash.0);
+    if events.is_empty() && state_hash.is_none() {
+        return;
+    }
+
+    let frame = ReplayFrame {
+        tick: tick.0,
+        events,
+        state_hash,
+    };
+    if let Err(err) = recorder.writer.write_frame(&frame) {
+        error!("Stopped recording replay {:?}: {err}", recorder.path());
+        commands.remove_resource::<ReplayRecorder>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::Vec2;
+
+    #[test]
+    fn test_replay_round_trip() {
+        let header = ReplayHeader::new(&ServerConfig::default(), 42, UNIX_EPOCH);
+        let frames = vec![
+            ReplayFrame {
+                tick: 3,
+                events: vec![ReplayEvent::HeroSpawned {
+                    player: PlayerId(1),
+                    identity: Identity([1; 32]),
+                    name: "alice".to_string(),
+                    team: Team::Radiant,
+                    unit: UnitId(7),
+                }],
+                state_hash: None,
+            },
+            ReplayFrame {
+                tick: 250,
+                events: vec![
+                    ReplayEvent::Order {
+                        player: PlayerId(1),
+                        unit: UnitId(7),
+                        order: Order::Move {
+                            target: Vec2::new(10.0, -4.5),
+                        },
+                    },
+                    ReplayEvent::HeroDespawned { unit: UnitId(7) },
+                ],
+                state_hash: Some(0xdead_beef),
+            },
+        ];
+        let mut writer = ReplayWriter::new(Vec::new(), &header).unwrap();
+        for frame in &frames {
+            writer.write_frame(frame).unwrap();
+        }
+        let bytes = writer.into_inner();
+
+        let replay = Replay::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(replay.header, header);
+        assert_eq!(replay.frames, frames);
+        assert_eq!(replay.last_tick(), 250);
+
+        assert!(matches!(
+            Replay::read_from(&b"PNG\0"[..]),
+            Err(ReplayError::NotAReplay)
+        ));
+        assert!(Replay::read_from(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_versions_and_content() {
+        let mut header = ReplayHeader::new(&ServerConfig::default(), 42, UNIX_EPOCH);
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        encoding()
+            .serialize_into(&mut bytes, &(REPLAY_VERSION - 1))
+            .unwrap();
+        encoding().serialize_into(&mut bytes, &header).unwrap();
+        assert!(matches!(
+            Replay::read_from(bytes.as_slice()),
+            Err(ReplayError::UnsupportedVersion(version)) if version == REPLAY_VERSION - 1
+        ));
+
+        header.content.insert("trees".to_string(), "0".to_string());
+        header.content.remove("rules");
+        let bytes = ReplayWriter::new(Vec::new(), &header).unwrap().into_inner();
+        assert!(matches!(
+            Replay::read_from(bytes.as_slice()),
+            Err(ReplayError::ContentMismatch(names)) if names == ["rules", "trees"]
+        ));
+    }
+}
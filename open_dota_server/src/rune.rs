@@ -0,0 +1,396 @@
+//! Runes spawn at fixed spots around the map. Bounty runes appear at every bounty spot as the
+//! match starts and every [`BOUNTY_RUNE_INTERVAL_SECS`] after, replacing any left untaken. A
+//! power-up rune appears every [`POWER_UP_RUNE_INTERVAL_SECS`] at one of the two power-up spots,
+//! the spots taking turns, and replaces the previous power-up.
+//!
+//! Heroes ordered to pick up a rune walk to it and take it once in range. Power-ups are stored in
+//! the hero's bottle if it is empty, to be activated later, anything else takes effect right away.
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::Health,
+    item::{Inventory, Item},
+    order::{Order, OrderApplied},
+    simulation::{MatchRng, Tick, TickRate},
+    unit::{Gold, Position},
+};
+
+/// The first [`BOUNTY_SPOT_COUNT`] spots get bounty runes, the rest power-ups.
+pub const RUNE_SPOTS: [Vec2; 6] = [
+    Vec2::new(-400.0, 100.0),
+    Vec2::new(400.0, -100.0),
+    Vec2::new(0.0, -420.0),
+    Vec2::new(0.0, 420.0),
+    Vec2::new(-150.0, 150.0),
+    Vec2::new(150.0, -150.0),
+];
+pub const BOUNTY_SPOT_COUNT: usize = 4;
+pub const BOUNTY_RUNE_INTERVAL_SECS: u64 = 180;
+pub const POWER_UP_RUNE_INTERVAL_SECS: u64 = 120;
+pub const BOUNTY_RUNE_GOLD: u32 = 40;
+/// How close a hero has to be to a rune to take it.
+pub const RUNE_PICKUP_RANGE: f32 = 50.0;
+pub const HASTE_MOVE_SPEED: f32 = 550.0;
+pub const DOUBLE_DAMAGE_MULTIPLIER: f32 = 2.0;
+/// Fraction of maximum health healed per second.
+pub const REGENERATION_PER_SEC: f32 = 0.06;
+pub const POWER_UPS: [RuneKind; 5] = [
+    RuneKind::Haste,
+    RuneKind::DoubleDamage,
+    RuneKind::Regeneration,
+    RuneKind::Invisibility,
+    RuneKind::Illusion,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RuneKind {
+    Bounty,
+    Haste,
+    DoubleDamage,
+    /// Heals until the hero is at full health or the rune runs out.
+    Regeneration,
+    Invisibility,
+    /// Conjures illusions of the hero, which last as long as the rune would.
+    Illusion,
+}
+
+/// The rune at each of the [`RUNE_SPOTS`], if any.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Runes {
+    pub spots: [Option<RuneKind>; RUNE_SPOTS.len()],
+    /// Which power-up spot the next power-up spawns at.
+    pub next_power_up: usize,
+}
+
+/// The rune spot a hero was ordered to pick up a rune from.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RuneTarget(pub Option<usize>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveRune {
+    pub kind: RuneKind,
+    pub expires_at: u64,
+}
+
+/// The power-up a hero is under. Activating another one replaces it.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RuneBuff(pub Option<ActiveRune>);
+
+/// A hero activated a rune, picked up or from their bottle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuneActivated {
+    pub unit: Entity,
+    pub kind: RuneKind,
+}
+
+impl RuneKind {
+    /// How long the power-up lasts, zero for runes that take effect at once.
+    pub fn duration_secs(self) -> u64 {
+        match self {
+            Self::Bounty => 0,
+            Self::Haste => 22,
+            Self::DoubleDamage | Self::Invisibility => 45,
+            Self::Regeneration => 30,
+            Self::Illusion => 75,
+        }
+    }
+
+    pub fn is_power_up(self) -> bool {
+        self != Self::Bounty
+    }
+}
+
+impl RuneBuff {
+    pub fn is(&self, kind: RuneKind) -> bool {
+        self.0.is_some_and(|active| active.kind == kind)
+    }
+}
+
+/// Spawns bounty runes from the first tick on and power-ups from their first interval on.
+pub fn spawn_runes(
+    mut runes: ResMut<Runes>,
+    mut rng: ResMut<MatchRng>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+) {
+    let ticks = |secs| secs * tick_rate.0 as u64;
+    if tick
+        .0
+        .saturating_sub(1)
+        .is_multiple_of(ticks(BOUNTY_RUNE_INTERVAL_SECS))
+    {
+        for spot in &mut runes.spots[..BOUNTY_SPOT_COUNT] {
+            *spot = Some(RuneKind::Bounty);
+        }
+    }
+    if tick.0 > 0 && tick.0.is_multiple_of(ticks(POWER_UP_RUNE_INTERVAL_SECS)) {
+        let kind = POWER_UPS[rng.gen_range(0..POWER_UPS.len())];
+        let power_up_spots = RUNE_SPOTS.len() - BOUNTY_SPOT_COUNT;
+        let spot = BOUNTY_SPOT_COUNT + runes.next_power_up;
+        runes.spots[BOUNTY_SPOT_COUNT..].fill(None);
+        runes.spots[spot] = Some(kind);
+        runes.next_power_up = (runes.next_power_up + 1) % power_up_spots;
+    }
+}
+
+fn activate(
+    kind: RuneKind,
+    unit: Entity,
+    gold: &mut Gold,
+    buff: &mut RuneBuff,
+    expires_at: u64,
+    activated_events: &mut EventWriter<RuneActivated>,
+) {
+    match kind {
+        RuneKind::Bounty => gold.reliable += BOUNTY_RUNE_GOLD,
+        // Illusions get their own lifetime when they are conjured.
+        RuneKind::Illusion => (),
+        _ => buff.0 = Some(ActiveRune { kind, expires_at }),
+    }
+    activated_events.send(RuneActivated { unit, kind });
+}
+
+/// Takes runes heroes reached and activates bottled runes heroes were ordered to use.
+pub fn pick_up_runes(
+    mut runes: ResMut<Runes>,
+    mut order_events: EventReader<OrderApplied>,
+    mut activated_events: EventWriter<RuneActivated>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    mut hero_query: Query<(
+        Entity,
+        &Position,
+        &mut RuneTarget,
+        &mut Inventory,
+        &mut Gold,
+        &mut RuneBuff,
+    )>,
+) {
+    let expires_at = |kind: RuneKind| tick.0 + kind.duration_secs() * tick_rate.0 as u64;
+    for OrderApplied(order) in order_events.iter() {
+        if order.order != Order::UseBottle {
+            continue;
+        }
+        let Ok((entity, _, _, mut inventory, mut gold, mut buff)) =
+            hero_query.get_mut(order.unit.entity())
+        else {
+            continue;
+        };
+        let bottled = inventory
+            .slots
+            .iter_mut()
+            .flatten()
+            .find_map(|item| match item {
+                Item::Bottle(rune) => rune.take(),
+                _ => None,
+            });
+        let Some(kind) = bottled else {
+            continue;
+        };
+        activate(
+            kind,
+            entity,
+            &mut gold,
+            &mut buff,
+            expires_at(kind),
+            &mut activated_events,
+        );
+    }
+
+    for (entity, position, mut rune_target, mut inventory, mut gold, mut buff) in &mut hero_query {
+        let Some(spot) = rune_target.0 else {
+            continue;
+        };
+        if position.0.distance(RUNE_SPOTS[spot]) > RUNE_PICKUP_RANGE {
+            continue;
+        }
+        rune_target.0 = None;
+        let Some(kind) = runes.spots[spot].take() else {
+            continue;
+        };
+        if kind.is_power_up() {
+            let empty_bottle = inventory
+                .slots
+                .iter_mut()
+                .flatten()
+                .find_map(|item| match item {
+                    Item::Bottle(rune @ None) => Some(rune),
+                    _ => None,
+                });
+            if let Some(bottle) = empty_bottle {
+                *bottle = Some(kind);
+                continue;
+            }
+        }
+        activate(
+            kind,
+            entity,
+            &mut gold,
+            &mut buff,
+            expires_at(kind),
+            &mut activated_events,
+        );
+    }
+}
+
+/// Heals heroes under regeneration and ends power-ups that ran out.
+pub fn update_rune_buffs(
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    mut buff_query: Query<(&mut RuneBuff, &mut Health)>,
+) {
+    for (mut buff, mut health) in &mut buff_query {
+        let Some(active) = buff.0 else {
+            continue;
+        };
+        if active.kind == RuneKind::Regeneration {
+            health.current = (health.current
+                + health.max * REGENERATION_PER_SEC * tick_rate.delta_seconds())
+            .min(health.max);
+        }
+        let healed = active.kind == RuneKind::Regeneration && health.is_full();
+        if tick.0 >= active.expires_at || healed {
+            buff.0 = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        order::OrderIssued,
+        player::{PlayerId, Team},
+        simulation::MatchSeed,
+        unit::HeroBundle,
+    };
+
+    const TICK_RATE: u32 = 10;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(TickRate(TICK_RATE));
+        world.insert_resource(MatchRng::new(MatchSeed(7)));
+        world.init_resource::<Tick>();
+        world.init_resource::<Runes>();
+        world.init_resource::<Events<OrderApplied>>();
+        world.init_resource::<Events<RuneActivated>>();
+        world
+    }
+
+    fn run_at(world: &mut World, schedule: &mut Schedule, tick: u64) {
+        world.resource_mut::<Tick>().0 = tick;
+        schedule.run(world);
+        world.resource_mut::<Events<OrderApplied>>().update();
+        world.resource_mut::<Events<RuneActivated>>().update();
+    }
+
+    #[test]
+    fn test_runes_spawn_on_schedule() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_system(spawn_runes);
+        let power_up_ticks = POWER_UP_RUNE_INTERVAL_SECS * TICK_RATE as u64;
+
+        run_at(&mut world, &mut schedule, 1);
+        let runes = *world.resource::<Runes>();
+        assert!(runes.spots[..BOUNTY_SPOT_COUNT]
+            .iter()
+            .all(|spot| *spot == Some(RuneKind::Bounty)));
+        assert_eq!(runes.spots[BOUNTY_SPOT_COUNT..], [None, None]);
+
+        world.resource_mut::<Runes>().spots[0] = None;
+        run_at(&mut world, &mut schedule, 2);
+        assert_eq!(world.resource::<Runes>().spots[0], None);
+
+        // Power-ups take turns between their spots and replace each other.
+        let mut used_spots = Vec::new();
+        for interval in 1..=4 {
+            run_at(&mut world, &mut schedule, interval * power_up_ticks);
+            let power_ups = world.resource::<Runes>().spots[BOUNTY_SPOT_COUNT..].to_vec();
+            assert_eq!(power_ups.iter().flatten().count(), 1);
+            assert!(power_ups.iter().flatten().all(|kind| kind.is_power_up()));
+            used_spots.push(power_ups.iter().position(Option::is_some).unwrap());
+        }
+        assert_eq!(used_spots, [0, 1, 0, 1]);
+
+        run_at(
+            &mut world,
+            &mut schedule,
+            BOUNTY_RUNE_INTERVAL_SECS * 10 + 1,
+        );
+        assert_eq!(world.resource::<Runes>().spots[0], Some(RuneKind::Bounty));
+    }
+
+    #[test]
+    fn test_picking_up_bottles_power_ups() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((pick_up_runes, update_rune_buffs).chain());
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        let pick_up = |world: &mut World, spot: usize, kind| {
+            world.resource_mut::<Runes>().spots[spot] = Some(kind);
+            world.get_mut::<Position>(hero).unwrap().0 = RUNE_SPOTS[spot] + Vec2::X * 40.0;
+            world.get_mut::<RuneTarget>(hero).unwrap().0 = Some(spot);
+        };
+
+        pick_up(&mut world, 0, RuneKind::Bounty);
+        run_at(&mut world, &mut schedule, 10);
+        assert_eq!(world.get::<Gold>(hero).unwrap().reliable, BOUNTY_RUNE_GOLD);
+        assert_eq!(world.resource::<Runes>().spots[0], None);
+        assert_eq!(world.get::<RuneTarget>(hero).unwrap().0, None);
+
+        pick_up(&mut world, 4, RuneKind::Haste);
+        run_at(&mut world, &mut schedule, 11);
+        let inventory = world.get::<Inventory>(hero).unwrap();
+        assert!(inventory.contains(Item::Bottle(Some(RuneKind::Haste))));
+        assert_eq!(*world.get::<RuneBuff>(hero).unwrap(), RuneBuff(None));
+
+        // The bottle is full, so the next power-up is activated right away.
+        pick_up(&mut world, 5, RuneKind::DoubleDamage);
+        run_at(&mut world, &mut schedule, 12);
+        assert!(world
+            .get::<RuneBuff>(hero)
+            .unwrap()
+            .is(RuneKind::DoubleDamage));
+
+        world.send_event(OrderApplied(OrderIssued {
+            player: PlayerId(1),
+            team: Team::Radiant,
+            unit: hero.into(),
+            order: Order::UseBottle,
+        }));
+        run_at(&mut world, &mut schedule, 20);
+        let buff = *world.get::<RuneBuff>(hero).unwrap();
+        assert!(buff.is(RuneKind::Haste));
+        assert!(world
+            .get::<Inventory>(hero)
+            .unwrap()
+            .contains(Item::Bottle(None)));
+
+        run_at(&mut world, &mut schedule, buff.0.unwrap().expires_at);
+        assert_eq!(*world.get::<RuneBuff>(hero).unwrap(), RuneBuff(None));
+    }
+
+    #[test]
+    fn test_regeneration_ends_at_full_health() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_system(update_rune_buffs);
+        let hero = world.spawn(HeroBundle::new(PlayerId(1), Team::Dire)).id();
+        let max = world.get::<Health>(hero).unwrap().max;
+        world.get_mut::<Health>(hero).unwrap().current = max - 2.0;
+        world.get_mut::<RuneBuff>(hero).unwrap().0 = Some(ActiveRune {
+            kind: RuneKind::Regeneration,
+            expires_at: 1000,
+        });
+        run_at(&mut world, &mut schedule, 1);
+        assert!(world.get::<Health>(hero).unwrap().is_full());
+        assert_eq!(*world.get::<RuneBuff>(hero).unwrap(), RuneBuff(None));
+    }
+}
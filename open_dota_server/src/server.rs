@@ -0,0 +1,776 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+use bevy_quinnet::{
+    server::{
+        ConnectionEvent, ConnectionLostEvent, Endpoint, QuinnetServerPlugin, Server,
+        ServerConfiguration,
+    },
+    shared::ClientId,
+};
+
+use crate::{
+    access::{AccessControl, AdminCommand},
+    boss::{spawn_boss, BossKilled, BossPit},
+    clock::{update_clock, GameClock},
+    combat::{Damaged, Health, UnitDied},
+    config::ServerConfig,
+    courier::{spawn_couriers, CourierRespawns},
+    death::{respawn_timers, HeroKilled, Respawn, RespawnTimer},
+    identity::{is_valid_name, Identity, PendingChallenges},
+    illusion::conjure_illusions,
+    item::{Inventory, Stash},
+    limits::{MessageGuard, MessageMetrics, Verdict, MAX_MESSAGES_PER_TICK},
+    neutral::{respawn_camps, NeutralTable},
+    order::{OrderApplied, OrderIssued},
+    player::{JoinRejection, JoinRole, PlayerId, Players, Team},
+    replay::{record_replay, start_recording, ReplayRecorder},
+    rune::{RuneActivated, Runes},
+    simulation::{
+        add_simulation_systems, advance_tick, hash_state, MatchRng, MatchSeed, SimulationSet,
+        StateHash, Tick, TickRate,
+    },
+    snapshot::{Snapshot, UnitQuery},
+    spectator::{
+        record_spectator_feed, send_spectator_snapshots, SpectatorFeed, SpectatorView, Spectators,
+    },
+    tree::{spawn_trees, FellTree, Forest},
+    unit::{Experience, Gold, Hero, HeroBundle, Owner, Position, Unit},
+    vision::{update_vision, TeamVision},
+    ward::{spawn_wards, WardPlaced, WardStock},
+    ClientMessage, ServerMessage,
+};
+
+const METRICS_LOG_INTERVAL: Duration = Duration::from_secs(60);
+/// How long a rejected client keeps its connection, so the rejection reaches it first.
+const REJECTED_GRACE: Duration = Duration::from_secs(1);
+
+struct SendSnapshot(ClientId);
+
+/// Clients whose join was rejected, with the time their connection gets closed.
+#[derive(Resource, Debug, Default)]
+struct RejectedClients(Vec<(ClientId, Duration)>);
+
+type ChangedUnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (),
+    Or<(
+        Changed<Position>,
+        Changed<Health>,
+        Changed<Inventory>,
+        Changed<Stash>,
+        Changed<Gold>,
+        Changed<Experience>,
+    )>,
+>;
+
+/// Hosts a match. Expects [`ServerConfig`] and [`AccessControl`] to be inserted by the caller.
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app.world.resource::<ServerConfig>();
+        let tick_rate = TickRate(config.tick_rate);
+        let seed = MatchSeed(config.seed.unwrap_or_else(rand::random));
+        let spectator_feed = SpectatorFeed::new(config.spectator_delay * config.tick_rate as u64);
+        app.add_plugin(QuinnetServerPlugin::default())
+            .insert_resource(tick_rate)
+            .insert_resource(seed)
+            .insert_resource(MatchRng::new(seed))
+            .init_resource::<StateHash>()
+            .init_resource::<Tick>()
+            .init_resource::<GameClock>()
+            .init_resource::<TeamVision>()
+            .init_resource::<Players>()
+            .init_resource::<Spectators>()
+            .insert_resource(spectator_feed)
+            .init_resource::<PendingChallenges>()
+            .init_resource::<MessageGuard>()
+            .init_resource::<RejectedClients>()
+            .insert_resource(NeutralTable::builtin())
+            .init_resource::<BossPit>()
+            .init_resource::<Runes>()
+            .init_resource::<CourierRespawns>()
+            .init_resource::<WardStock>()
+            .insert_resource(Forest::builtin())
+            .add_event::<SendSnapshot>()
+            .add_event::<OrderIssued>()
+            .add_event::<OrderApplied>()
+            .add_event::<Damaged>()
+            .add_event::<UnitDied>()
+            .add_event::<BossKilled>()
+            .add_event::<RuneActivated>()
+            .add_event::<WardPlaced>()
+            .add_event::<FellTree>()
+            .add_event::<HeroKilled>()
+            .add_startup_system(startup)
+            .add_startup_system(spawn_trees)
+            .add_startup_system(start_recording)
+            .add_system(advance_tick.in_base_set(CoreSet::First))
+            .add_system(update_clock.after(advance_tick).in_base_set(CoreSet::First))
+            .add_system(send_challenges.before(handle_client_messages))
+            .add_system(handle_client_messages)
+            .add_system(disconnect_rejected_clients.after(handle_client_messages))
+            .edit_schedule(CoreSchedule::Main, add_simulation_systems)
+            .configure_set(SimulationSet.after(handle_client_messages))
+            .add_systems(
+                (
+                    respawn_camps,
+                    spawn_boss,
+                    spawn_couriers,
+                    conjure_illusions,
+                    spawn_wards,
+                )
+                    .after(SimulationSet),
+            )
+            .add_system(
+                queue_snapshots_on_changes
+                    .after(respawn_camps)
+                    .after(spawn_boss)
+                    .after(spawn_couriers)
+                    .after(conjure_illusions)
+                    .after(spawn_wards),
+            )
+            .add_system(announce_boss_kills.after(SimulationSet))
+            .add_system(announce_hero_kills.after(SimulationSet))
+            .add_system(send_respawn_timers.after(SimulationSet))
+            .add_system(handle_connection_lost)
+            .add_system(remove_abandoned_players)
+            .add_system(log_message_metrics)
+            .add_systems(
+                (
+                    update_vision,
+                    send_snapshots,
+                    record_spectator_feed,
+                    send_spectator_snapshots,
+                )
+                    .chain()
+                    .in_base_set(CoreSet::PostUpdate),
+            )
+            .add_system(hash_state.in_base_set(CoreSet::Last))
+            .add_system(
+                record_replay
+                    .after(hash_state)
+                    .in_base_set(CoreSet::Last)
+                    .run_if(resource_exists::<ReplayRecorder>()),
+            );
+    }
+}
+
+fn startup(mut server: ResMut<Server>, config: Res<ServerConfig>, seed: Res<MatchSeed>) {
+    info!(
+        "Hosting {:?} on '{}' for up to {} players at {} ticks per second with seed {}",
+        config.game_mode, config.map, config.max_players, config.tick_rate, seed.0
+    );
+    server
+        .start_endpoint(
+            ServerConfiguration::from_ip(config.bind_address, config.port),
+            config.certificate.retrieval_mode(),
+        )
+        .unwrap();
+}
+
+fn admit(
+    players: &Players,
+    access: &AccessControl,
+    config: &ServerConfig,
+    name: &str,
+    identity: &Identity,
+    password: Option<&str>,
+) -> Result<(), JoinRejection> {
+    if !is_valid_name(name) {
+        return Err(JoinRejection::InvalidName);
+    }
+    access.check(identity, password)?;
+    if players.find(identity).is_none() && players.len() >= config.max_players {
+        return Err(JoinRejection::ServerFull);
+    }
+    Ok(())
+}
+
+fn admit_spectator(
+    spectators: &Spectators,
+    access: &AccessControl,
+    config: &ServerConfig,
+    name: &str,
+    identity: &Identity,
+    password: Option<&str>,
+) -> Result<(), JoinRejection> {
+    if !is_valid_name(name) {
+        return Err(JoinRejection::InvalidName);
+    }
+    access.check(identity, password)?;
+    if spectators.find(identity).is_none() && spectators.len() >= config.max_spectators {
+        return Err(JoinRejection::SpectatorsFull);
+    }
+    Ok(())
+}
+
+fn disconnect_rejected_clients(
+    mut server: ResMut<Server>,
+    mut rejected: ResMut<RejectedClients>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+    rejected.0.retain(|&(client_id, disconnect_at)| {
+        if now < disconnect_at {
+            return true;
+        }
+        server.endpoint_mut().try_disconnect_client(client_id);
+        false
+    });
+}
+
+fn send_challenges(
+    mut connection_events: EventReader<ConnectionEvent>,
+    mut server: ResMut<Server>,
+    mut challenges: ResMut<PendingChallenges>,
+) {
+    for event in connection_events.iter() {
+        let challenge = challenges.issue(event.id);
+        server
+            .endpoint_mut()
+            .try_send_message(event.id, ServerMessage::Challenge(challenge));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_client_messages(
+    mut commands: Commands,
+    mut server: ResMut<Server>,
+    mut players: ResMut<Players>,
+    mut spectators: ResMut<Spectators>,
+    mut access: ResMut<AccessControl>,
+    mut challenges: ResMut<PendingChallenges>,
+    mut guard: ResMut<MessageGuard>,
+    mut rejected: ResMut<RejectedClients>,
+    mut snapshot_events: EventWriter<SendSnapshot>,
+    mut order_events: EventWriter<OrderIssued>,
+    spectator_feed: Res<SpectatorFeed>,
+    config: Res<ServerConfig>,
+    time: Res<Time>,
+) {
+    let endpoint = server.endpoint_mut();
+    for client_id in endpoint.clients() {
+        for _ in 0..MAX_MESSAGES_PER_TICK {
+            let Ok(Some(payload)) = endpoint.receive_payload_from(client_id) else {
+                break;
+            };
+            let message = match guard.inspect(client_id, &payload, time.elapsed()) {
+                Verdict::Accept(message) => message,
+                Verdict::Drop(reason) => {
+                    debug!("Dropped message from client {client_id}: {reason:?}");
+                    continue;
+                }
+                Verdict::Disconnect(reason) => {
+                    warn!("Disconnecting client {client_id} after repeated {reason:?} messages");
+                    endpoint.try_send_message(
+                        client_id,
+                        ServerMessage::Kicked {
+                            reason: "Too many invalid messages".to_string(),
+                        },
+                    );
+                    endpoint.try_disconnect_client(client_id);
+                    challenges.take(client_id);
+                    spectators.leave(client_id);
+                    if let Some(player_id) = players.disconnect(client_id, time.elapsed()) {
+                        endpoint.try_send_group_message(
+                            players.clients().iter(),
+                            ServerMessage::PlayerDisconnected { player: player_id },
+                        );
+                    }
+                    break;
+                }
+            };
+            match message {
+                ClientMessage::Join {
+                    name,
+                    identity,
+                    signature,
+                    password,
+                    role,
+                } => {
+                    // Its challenge is used up, rejecting it would drop the connection but keep
+                    // the slot.
+                    if players.player_id(client_id).is_some() || spectators.get(client_id).is_some()
+                    {
+                        continue;
+                    }
+                    let verified = challenges
+                        .take(client_id)
+                        .is_some_and(|challenge| identity.verify(&challenge, &signature));
+                    let admitted = match role {
+                        _ if !verified => Err(JoinRejection::InvalidSignature),
+                        JoinRole::Player => admit(
+                            &players,
+                            &access,
+                            &config,
+                            &name,
+                            &identity,
+                            password.as_deref(),
+                        ),
+                        JoinRole::Spectator => admit_spectator(
+                            &spectators,
+                            &access,
+                            &config,
+                            &name,
+                            &identity,
+                            password.as_deref(),
+                        ),
+                    };
+                    if let Err(reason) = admitted {
+                        info!("Rejected join from '{name}' ({identity}): {reason:?}");
+                        endpoint
+                            .try_send_message(client_id, ServerMessage::JoinRejected { reason });
+                        rejected
+                            .0
+                            .push((client_id, time.elapsed() + REJECTED_GRACE));
+                        continue;
+                    }
+
+                    if role == JoinRole::Spectator {
+                        info!("'{name}' ({identity}) is spectating");
+                        if let Some(replaced) = spectators.join(client_id, identity, name) {
+                            endpoint.try_disconnect_client(replaced);
+                        }
+                        endpoint.try_send_message(
+                            client_id,
+                            ServerMessage::InitSpectator {
+                                delay_secs: config.spectator_delay,
+                            },
+                        );
+                        endpoint.try_send_message(
+                            client_id,
+                            ServerMessage::Snapshot(
+                                spectator_feed.released().view(SpectatorView::Full).clone(),
+                            ),
+                        );
+                        continue;
+                    }
+
+                    if let Some((player_id, replaced)) = players.reconnect(client_id, &identity) {
+                        if let Some(replaced) = replaced {
+                            endpoint.try_disconnect_client(replaced);
+                        }
+                        let player = players.get(player_id).unwrap();
+                        endpoint.try_send_message(
+                            client_id,
+                            ServerMessage::InitClient {
+                                player_id,
+                                team: player.team,
+                            },
+                        );
+                        endpoint.try_send_group_message(
+                            players.clients().iter(),
+                            ServerMessage::PlayerReconnected { player: player_id },
+                        );
+                        snapshot_events.send(SendSnapshot(client_id));
+                        continue;
+                    }
+
+                    let player_id = players.join(client_id, identity, name);
+                    let player = players.get_mut(player_id).unwrap();
+                    player.hero =
+                        Some(commands.spawn(HeroBundle::new(player_id, player.team)).id());
+                    endpoint.try_send_message(
+                        client_id,
+                        ServerMessage::InitClient {
+                            player_id,
+                            team: player.team,
+                        },
+                    );
+                    snapshot_events.send_batch(players.clients().into_iter().map(SendSnapshot));
+                }
+                ClientMessage::Leave => {
+                    spectators.leave(client_id);
+                    if let Some(player_id) = players.player_id(client_id) {
+                        let player = players.leave(player_id).unwrap();
+                        abandon(&mut commands, endpoint, &players, player_id, player.hero);
+                    }
+                    endpoint.disconnect_client(client_id).unwrap()
+                }
+                ClientMessage::ChatMessage { message } if message.starts_with('/') => {
+                    let admin = players.player_id(client_id).is_some_and(|player_id| {
+                        access.is_admin(&players.get(player_id).unwrap().identity)
+                    });
+                    let response = if !admin {
+                        "Only server admins can run commands".to_string()
+                    } else {
+                        match AdminCommand::parse(&message) {
+                            Ok(command) => run_admin_command(
+                                command,
+                                &mut commands,
+                                endpoint,
+                                &mut players,
+                                &mut spectators,
+                                &mut access,
+                                &guard.metrics,
+                            ),
+                            Err(usage) => usage,
+                        }
+                    };
+                    endpoint.try_send_message(
+                        client_id,
+                        ServerMessage::ChatMessage { message: response },
+                    );
+                }
+                // Spectators only talk among themselves, the players would hear about the match
+                // from them before the delay is up.
+                ClientMessage::ChatMessage { message } if spectators.get(client_id).is_some() => {
+                    endpoint.try_send_group_message(
+                        spectators.clients().iter(),
+                        ServerMessage::ChatMessage { message },
+                    );
+                }
+                ClientMessage::SpectatorView { view } => {
+                    if !spectators.set_view(client_id, view) {
+                        continue;
+                    }
+                    endpoint.try_send_message(
+                        client_id,
+                        ServerMessage::Snapshot(spectator_feed.released().view(view).clone()),
+                    );
+                }
+                ClientMessage::ChatMessage { message } => {
+                    if players.player_id(client_id).is_none() {
+                        continue;
+                    }
+                    endpoint.try_send_group_message(
+                        players.clients().iter(),
+                        ServerMessage::ChatMessage { message },
+                    );
+                }
+                ClientMessage::ChatWheel { phrase } => {
+                    let Some(player_id) = players.player_id(client_id) else {
+                        continue;
+                    };
+                    let team = players.get(player_id).unwrap().team;
+                    endpoint.try_send_group_message(
+                        players.teammates(team).iter(),
+                        ServerMessage::ChatWheel {
+                            sender: player_id,
+                            phrase,
+                        },
+                    );
+                }
+                ClientMessage::Order { unit, order } => {
+                    if let Some(player_id) = players.player_id(client_id) {
+                        order_events.send(OrderIssued {
+                            player: player_id,
+                            team: players.get(player_id).unwrap().team,
+                            unit,
+                            order,
+                        });
+                    }
+                }
+                ClientMessage::Ping { kind, position } => {
+                    let Some(player_id) = players.player_id(client_id) else {
+                        continue;
+                    };
+                    let player = players.get_mut(player_id).unwrap();
+                    if !player.ping_cooldown.try_ping(time.elapsed()) {
+                        continue;
+                    }
+                    let team = player.team;
+                    endpoint.try_send_group_message(
+                        players.teammates(team).iter(),
+                        ServerMessage::Ping {
+                            sender: player_id,
+                            kind,
+                            position,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn run_admin_command(
+    command: AdminCommand,
+    commands: &mut Commands,
+    endpoint: &mut Endpoint,
+    players: &mut Players,
+    spectators: &mut Spectators,
+    access: &mut AccessControl,
+    metrics: &MessageMetrics,
+) -> String {
+    let response = match command {
+        AdminCommand::Ban { identity, reason } => {
+            if !access.ban(identity, reason.clone(), SystemTime::now()) {
+                return format!("'{identity}' is already banned");
+            }
+            if let Some(player_id) = players.find(&identity) {
+                let player = players.leave(player_id).unwrap();
+                if let Some(client_id) = player.client_id() {
+                    let reason = match reason.as_str() {
+                        "" => "Banned from the server".to_string(),
+                        reason => format!("Banned from the server: {reason}"),
+                    };
+                    endpoint.try_send_message(client_id, ServerMessage::Kicked { reason });
+                    endpoint.try_disconnect_client(client_id);
+                }
+                abandon(commands, endpoint, players, player_id, player.hero);
+            }
+            if let Some(client_id) = spectators.find(&identity) {
+                spectators.leave(client_id);
+                endpoint.try_send_message(
+                    client_id,
+                    ServerMessage::Kicked {
+                        reason: "Banned from the server".to_string(),
+                    },
+                );
+                endpoint.try_disconnect_client(client_id);
+            }
+            format!("Banned '{identity}'")
+        }
+        AdminCommand::Unban { identity } => {
+            if !access.unban(&identity) {
+                return format!("'{identity}' is not banned");
+            }
+            format!("Unbanned '{identity}'")
+        }
+        AdminCommand::Bans if access.bans().is_empty() => return "No bans".to_string(),
+        AdminCommand::Bans => {
+            return access
+                .bans()
+                .iter()
+                .map(|ban| format!("'{}': {}", ban.identity, ban.reason))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        AdminCommand::Metrics => return metrics.summary(),
+        AdminCommand::Players if players.is_empty() => return "No players".to_string(),
+        AdminCommand::Players => {
+            let mut lines = players
+                .iter()
+                .map(|(player_id, player)| {
+                    let status = match player.client_id() {
+                        Some(_) => "connected",
+                        None => "disconnected",
+                    };
+                    format!(
+                        "{} '{}' {:?} {status}: {}",
+                        player_id.0, player.name, player.team, player.identity
+                    )
+                })
+                .collect::<Vec<_>>();
+            lines.sort();
+            return lines.join("\n");
+        }
+    };
+    if let Err(err) = access.save() {
+        error!("{err}");
+    }
+    response
+}
+
+fn handle_connection_lost(
+    mut connection_lost_events: EventReader<ConnectionLostEvent>,
+    mut server: ResMut<Server>,
+    mut players: ResMut<Players>,
+    mut spectators: ResMut<Spectators>,
+    mut challenges: ResMut<PendingChallenges>,
+    mut guard: ResMut<MessageGuard>,
+    time: Res<Time>,
+) {
+    let endpoint = server.endpoint_mut();
+    for event in connection_lost_events.iter() {
+        challenges.take(event.id);
+        guard.forget(event.id);
+        spectators.leave(event.id);
+        let Some(player_id) = players.disconnect(event.id, time.elapsed()) else {
+            continue;
+        };
+        info!("Player {player_id:?} disconnected, holding their slot for reconnection");
+        endpoint.try_send_group_message(
+            players.clients().iter(),
+            ServerMessage::PlayerDisconnected { player: player_id },
+        );
+    }
+}
+
+fn log_message_metrics(
+    guard: Res<MessageGuard>,
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+    mut logged: Local<u64>,
+) {
+    let timer = timer.get_or_insert_with(|| Timer::new(METRICS_LOG_INTERVAL, TimerMode::Repeating));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let dropped = guard.metrics.dropped.values().sum::<u64>();
+    if dropped > *logged {
+        info!("Messages: {}", guard.metrics.summary());
+        *logged = dropped;
+    }
+}
+
+fn remove_abandoned_players(
+    mut commands: Commands,
+    mut server: ResMut<Server>,
+    mut players: ResMut<Players>,
+    time: Res<Time>,
+) {
+    let endpoint = server.endpoint_mut();
+    for (player_id, player) in players.remove_abandoned(time.elapsed()) {
+        info!("Player {player_id:?} abandoned the match");
+        abandon(&mut commands, endpoint, &players, player_id, player.hero);
+    }
+}
+
+fn queue_snapshots_on_changes(
+    changed_query: ChangedUnitQuery,
+    mut removed_units: RemovedComponents<Unit>,
+    clock: Res<GameClock>,
+    runes: Res<Runes>,
+    forest: Res<Forest>,
+    players: Res<Players>,
+    mut snapshot_events: EventWriter<SendSnapshot>,
+) {
+    if !changed_query.is_empty()
+        || removed_units.iter().count() > 0
+        || clock.is_changed()
+        || runes.is_changed()
+        || forest.is_changed()
+    {
+        snapshot_events.send_batch(players.clients().into_iter().map(SendSnapshot));
+    }
+}
+
+/// Tells every player who killed the boss. Spectators learn about it from their delayed
+/// snapshots instead.
+fn announce_boss_kills(
+    mut killed_events: EventReader<BossKilled>,
+    server: Res<Server>,
+    players: Res<Players>,
+    killer_query: Query<(&Owner, &Team)>,
+) {
+    for event in killed_events.iter() {
+        let killer = killer_query.get(event.killer).ok();
+        info!(
+            "The boss was killed by {:?}",
+            killer.map(|(owner, _)| owner.0)
+        );
+        server.endpoint().try_send_group_message(
+            players.clients().iter(),
+            ServerMessage::BossKilled {
+                killer: killer.map(|(owner, _)| owner.0),
+                team: killer.map(|(_, team)| *team),
+                item: event.item,
+            },
+        );
+    }
+}
+
+/// Tells every player whose hero died, who killed it and when it respawns.
+fn announce_hero_kills(
+    mut killed_events: EventReader<HeroKilled>,
+    server: Res<Server>,
+    players: Res<Players>,
+    tick_rate: Res<TickRate>,
+    hero_query: Query<(&Owner, &Respawn)>,
+    killer_query: Query<(&Owner, &Team)>,
+) {
+    for event in killed_events.iter() {
+        let Ok((owner, respawn)) = hero_query.get(event.hero) else {
+            continue;
+        };
+        let killer = killer_query
+            .get(event.killer)
+            .ok()
+            .filter(|(_, team)| **team != event.team)
+            .map(|(killer, _)| killer.0);
+        let respawn_at = respawn.at.unwrap_or_default();
+        server.endpoint().try_send_group_message(
+            players.clients().iter(),
+            ServerMessage::HeroKilled {
+                player: owner.0,
+                killer,
+                respawn_at: respawn_at.div_ceil(tick_rate.0 as u64),
+            },
+        );
+    }
+}
+
+/// Sends every player the respawn timers whenever they change, and again when players join.
+fn send_respawn_timers(
+    mut sent: Local<Vec<RespawnTimer>>,
+    server: Res<Server>,
+    players: Res<Players>,
+    tick_rate: Res<TickRate>,
+    hero_query: Query<(&Owner, &Respawn), With<Hero>>,
+) {
+    let timers = respawn_timers(hero_query.iter(), *tick_rate);
+    if timers == *sent && !players.is_changed() {
+        return;
+    }
+    server.endpoint().try_send_group_message(
+        players.clients().iter(),
+        ServerMessage::RespawnTimers {
+            timers: timers.clone(),
+        },
+    );
+    *sent = timers;
+}
+
+/// Sends every client the units their team sees.
+#[allow(clippy::too_many_arguments)]
+fn send_snapshots(
+    mut snapshot_events: EventReader<SendSnapshot>,
+    server: Res<Server>,
+    players: Res<Players>,
+    vision: Res<TeamVision>,
+    clock: Res<GameClock>,
+    runes: Res<Runes>,
+    forest: Res<Forest>,
+    units: UnitQuery,
+) {
+    if snapshot_events.is_empty() {
+        return;
+    }
+    let clients = snapshot_events
+        .iter()
+        .map(|SendSnapshot(client_id)| *client_id)
+        .collect::<HashSet<_>>();
+    for team in [Team::Radiant, Team::Dire] {
+        let team_clients = clients
+            .iter()
+            .filter(|client_id| {
+                players
+                    .player_id(**client_id)
+                    .and_then(|player_id| players.get(player_id))
+                    .is_some_and(|player| player.team == team)
+            })
+            .collect::<Vec<_>>();
+        if team_clients.is_empty() {
+            continue;
+        }
+        let snapshot =
+            Snapshot::build_visible(&players, &units, *clock, &runes, &forest, &vision, team);
+        server
+            .endpoint()
+            .try_send_group_message(team_clients.into_iter(), ServerMessage::Snapshot(snapshot));
+    }
+}
+
+fn abandon(
+    commands: &mut Commands,
+    endpoint: &mut Endpoint,
+    players: &Players,
+    player_id: PlayerId,
+    hero: Option<Entity>,
+) {
+    if let Some(hero) = hero {
+        commands.entity(hero).despawn();
+    }
+    endpoint.try_send_group_message(
+        players.clients().iter(),
+        ServerMessage::PlayerAbandoned { player: player_id },
+    );
+}
@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bincode::Options;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    boss::{boss_ai, reward_boss_kill, scale_boss},
+    combat::{apply_damage, attack_targets, remove_dead_units, Health},
+    courier::{run_couriers, schedule_courier_respawns},
+    death::{buy_back, kill_heroes, respawn_heroes, reward_hero_kills},
+    illusion::expire_illusions,
+    invisibility::update_invisibility,
+    item::{collect_stashes, Inventory, Stash},
+    neutral::neutral_ai,
+    order::{apply_orders, move_units},
+    player::{PlayerId, Team},
+    rune::{pick_up_runes, spawn_runes, update_rune_buffs},
+    tree::{cut_trees, fell_trees, regrow_trees, sync_forest, Forest, TreeBits},
+    unit::{Experience, Gold, MoveTarget, Owner, Position, Unit, UnitId},
+    vision::{lose_hidden_targets, update_vision},
+    ward::{buy_wards, expire_wards, place_wards, restock_wards, reward_ward_kills},
+};
+
+/// Number of the tick being simulated, the first update is tick 1.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Tick(pub u64);
+
+/// Simulation ticks per second. Every tick advances the game by exactly one tick's duration,
+/// however long the update actually took, so that a match can be simulated again from its
+/// orders.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickRate(pub u32);
+
+/// Seed everything random in a match derives from.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchSeed(pub u64);
+
+/// The only source of randomness gameplay may use. Seeded from the [`MatchSeed`] and drawn from
+/// in system order, so a match simulated again from its orders rolls the same numbers.
+#[derive(Resource, Debug, Clone, Deref, DerefMut)]
+pub struct MatchRng(ChaCha8Rng);
+
+/// The systems simulating a tick, see [`add_simulation_systems`].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationSet;
+
+/// Hash of the simulation state at the end of the current tick, see [`state_hash`].
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StateHash(pub u64);
+
+/// The parts of a unit that decide how the match plays out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnitState {
+    pub id: UnitId,
+    pub owner: Option<PlayerId>,
+    pub team: Option<Team>,
+    pub position: Vec2,
+    pub move_target: Option<Vec2>,
+    pub health: Option<f32>,
+    pub inventory: Option<Inventory>,
+    pub stash: Option<Inventory>,
+    pub gold: Option<Gold>,
+    pub experience: Option<Experience>,
+}
+
+pub type UnitStateQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Position,
+        Option<&'static MoveTarget>,
+        Option<&'static Owner>,
+        Option<&'static Team>,
+        Option<&'static Health>,
+        Option<&'static Inventory>,
+        Option<&'static Stash>,
+        Option<&'static Gold>,
+        Option<&'static Experience>,
+    ),
+    With<Unit>,
+>;
+
+impl TickRate {
+    pub fn duration(self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.0 as f64)
+    }
+
+    pub fn delta_seconds(self) -> f32 {
+        1.0 / self.0 as f32
+    }
+}
+
+impl MatchRng {
+    pub fn new(seed: MatchSeed) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed.0))
+    }
+
+    /// How many numbers have been drawn, in 32 bit words.
+    pub fn word_pos(&self) -> u128 {
+        self.0.get_word_pos()
+    }
+}
+
+/// Hashes everything the rest of the match depends on. Two simulations of the same match agree
+/// on the hash of every tick until they diverge.
+pub fn state_hash(tick: u64, rng: &MatchRng, mut units: Vec<UnitState>, trees: &TreeBits) -> u64 {
+    units.sort_by_key(|unit| unit.id.0);
+    let bytes = bincode::DefaultOptions::new()
+        .serialize(&(tick, rng.word_pos(), units, trees))
+        .unwrap();
+    let digest = Sha256::digest(bytes);
+    u64::from_le_bytes(digest[..8].try_into().unwrap())
+}
+
+/// Adds everything that plays out within a tick to `schedule`, in order and in
+/// [`SimulationSet`]. Replays are simulated with the same systems.
+pub fn add_simulation_systems(schedule: &mut Schedule) {
+    schedule
+        .add_systems(
+            (
+                update_vision,
+                apply_orders,
+                buy_back,
+                respawn_heroes,
+                spawn_runes,
+                restock_wards,
+                neutral_ai,
+                boss_ai,
+                scale_boss,
+                run_couriers,
+                collect_stashes,
+                lose_hidden_targets,
+                attack_targets,
+                move_units,
+            )
+                .chain()
+                .in_set(SimulationSet),
+        )
+        .add_systems(
+            (
+                pick_up_runes,
+                buy_wards,
+                place_wards,
+                update_rune_buffs,
+                apply_damage,
+                update_invisibility,
+                reward_boss_kill,
+                reward_ward_kills,
+                kill_heroes,
+                reward_hero_kills,
+                schedule_courier_respawns,
+                remove_dead_units,
+                expire_illusions,
+                expire_wards,
+            )
+                .chain()
+                .after(move_units)
+                .in_set(SimulationSet),
+        )
+        .add_systems(
+            (cut_trees, fell_trees, regrow_trees, sync_forest)
+                .chain()
+                .after(expire_wards)
+                .in_set(SimulationSet),
+        );
+}
+
+pub fn advance_tick(mut tick: ResMut<Tick>) {
+    tick.0 += 1;
+}
+
+pub fn hash_state(
+    mut hash: ResMut<StateHash>,
+    tick: Res<Tick>,
+    rng: Res<MatchRng>,
+    forest: Res<Forest>,
+    units: UnitStateQuery,
+) {
+    let units = units
+        .iter()
+        .map(
+            |(
+                entity,
+                position,
+                move_target,
+                owner,
+                team,
+                health,
+                inventory,
+                stash,
+                gold,
+                experience,
+            )| {
+                UnitState {
+                    id: entity.into(),
+                    owner: owner.map(|owner| owner.0),
+                    team: team.copied(),
+                    position: position.0,
+                    move_target: move_target.and_then(|move_target| move_target.0),
+                    health: health.map(|health| health.current),
+                    inventory: inventory.cloned(),
+                    stash: stash.map(|stash| stash.0.clone()),
+                    gold: gold.copied(),
+                    experience: experience.copied(),
+                }
+            },
+        )
+        .collect();
+    hash.0 = state_hash(tick.0, &rng, units, &forest.standing);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn unit(id: u64, x: f32) -> UnitState {
+        UnitState {
+            id: UnitId(id),
+            owner: Some(PlayerId(1)),
+            team: Some(Team::Radiant),
+            position: Vec2::new(x, 0.0),
+            move_target: None,
+            health: Some(100.0),
+            inventory: None,
+            stash: None,
+            gold: None,
+            experience: None,
+        }
+    }
+
+    #[test]
+    fn test_rng_is_seeded() {
+        let roll = |seed| {
+            let mut rng = MatchRng::new(MatchSeed(seed));
+            (0..8).map(|_| rng.gen::<u32>()).collect::<Vec<_>>()
+        };
+        assert_eq!(roll(3), roll(3));
+        assert_ne!(roll(3), roll(4));
+    }
+
+    #[test]
+    fn test_state_hash() {
+        let rng = MatchRng::new(MatchSeed(1));
+        let trees = TreeBits::new(3);
+        let hash = state_hash(10, &rng, vec![unit(1, 0.0), unit(2, 5.0)], &trees);
+        assert_eq!(
+            hash,
+            state_hash(10, &rng, vec![unit(2, 5.0), unit(1, 0.0)], &trees)
+        );
+        assert_ne!(
+            hash,
+            state_hash(11, &rng, vec![unit(1, 0.0), unit(2, 5.0)], &trees)
+        );
+        assert_ne!(
+            hash,
+            state_hash(10, &rng, vec![unit(1, 0.0), unit(2, 5.5)], &trees)
+        );
+        let mut felled = trees.clone();
+        felled.set(1, false);
+        assert_ne!(
+            hash,
+            state_hash(10, &rng, vec![unit(1, 0.0), unit(2, 5.0)], &felled)
+        );
+
+        let mut rolled = rng.clone();
+        rolled.gen::<u32>();
+        assert_ne!(
+            hash,
+            state_hash(10, &rolled, vec![unit(1, 0.0), unit(2, 5.0)], &trees)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::GameClock,
+    combat::Health,
+    courier::Courier,
+    identity::Identity,
+    item::{Inventory, Stash},
+    player::{PlayerId, Players, Team},
+    rune::{RuneKind, Runes, RUNE_SPOTS},
+    tree::{Forest, TreeBits},
+    unit::{Experience, Gold, Hero, Owner, Position, Unit, UnitId},
+    vision::TeamVision,
+};
+
+pub type UnitQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Position,
+        Option<&'static Owner>,
+        Option<&'static Team>,
+        Option<&'static Hero>,
+        Option<&'static Health>,
+        Option<&'static Inventory>,
+        Option<&'static Stash>,
+        Option<&'static Gold>,
+        Option<&'static Experience>,
+        Option<&'static Courier>,
+    ),
+    With<Unit>,
+>;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub players: Vec<PlayerSnapshot>,
+    pub units: Vec<UnitSnapshot>,
+    pub clock: GameClock,
+    /// The rune at each of the [`RUNE_SPOTS`], `None` for spots hidden from the receiving team.
+    pub runes: [Option<RuneKind>; RUNE_SPOTS.len()],
+    /// Which trees of the [`Forest`] stand.
+    pub trees: TreeBits,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+    pub id: PlayerId,
+    pub identity: Identity,
+    pub name: String,
+    pub team: Team,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnitSnapshot {
+    pub id: UnitId,
+    pub owner: Option<PlayerId>,
+    pub team: Option<Team>,
+    pub hero: bool,
+    pub position: Vec2,
+    /// Current and maximum health, `None` for units that can't be hurt.
+    pub health: Option<(f32, f32)>,
+    pub inventory: Option<Inventory>,
+    pub stash: Option<Inventory>,
+    pub gold: Option<Gold>,
+    /// The level of heroes.
+    pub level: Option<u32>,
+    pub courier: bool,
+}
+
+impl Snapshot {
+    /// The whole match, as seen by spectators and replays.
+    pub fn build(
+        players: &Players,
+        units: &UnitQuery,
+        clock: GameClock,
+        runes: &Runes,
+        forest: &Forest,
+    ) -> Self {
+        Self::build_with(
+            players,
+            units,
+            clock,
+            runes,
+            forest,
+            None,
+            |_| true,
+            |_| true,
+        )
+    }
+
+    /// The match as `team` sees it, without the units and runes hidden from it and without the
+    /// gold and stashes of the other team.
+    pub fn build_visible(
+        players: &Players,
+        units: &UnitQuery,
+        clock: GameClock,
+        runes: &Runes,
+        forest: &Forest,
+        vision: &TeamVision,
+        team: Team,
+    ) -> Self {
+        Self::build_with(
+            players,
+            units,
+            clock,
+            runes,
+            forest,
+            Some(team),
+            |entity| vision.sees(team, entity),
+            |spot| vision.sees_rune_spot(team, spot),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build_with(
+        players: &Players,
+        units: &UnitQuery,
+        clock: GameClock,
+        runes: &Runes,
+        forest: &Forest,
+        viewer: Option<Team>,
+        visible: impl Fn(Entity) -> bool,
+        sees_rune_spot: impl Fn(usize) -> bool,
+    ) -> Self {
+        let mut players = players
+            .iter()
+            .map(|(player_id, player)| PlayerSnapshot {
+                id: *player_id,
+                identity: player.identity,
+                name: player.name.clone(),
+                team: player.team,
+                connected: player.client_id().is_some(),
+            })
+            .collect::<Vec<_>>();
+        players.sort_by_key(|player| player.id);
+        let units = units
+            .iter()
+            .filter(|(entity, ..)| visible(*entity))
+            .map(
+                |(
+                    entity,
+                    position,
+                    owner,
+                    team,
+                    hero,
+                    health,
+                    inventory,
+                    stash,
+                    gold,
+                    experience,
+                    courier,
+                )| {
+                    let own = viewer.is_none_or(|viewer| team == Some(&viewer));
+                    UnitSnapshot {
+                        id: entity.into(),
+                        owner: owner.map(|owner| owner.0),
+                        team: team.copied(),
+                        hero: hero.is_some(),
+                        position: position.0,
+                        health: health.map(|health| (health.current, health.max)),
+                        inventory: inventory.cloned(),
+                        stash: stash.filter(|_| own).map(|stash| stash.0.clone()),
+                        gold: gold.filter(|_| own).copied(),
+                        level: experience.map(|experience| experience.level()),
+                        courier: courier.is_some(),
+                    }
+                },
+            )
+            .collect();
+        Self {
+            players,
+            units,
+            clock,
+            runes: std::array::from_fn(|spot| runes.spots[spot].filter(|_| sees_rune_spot(spot))),
+            trees: forest.standing.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{unit::HeroBundle, vision::update_vision};
+
+    #[derive(Resource, Default)]
+    struct Built {
+        full: Snapshot,
+        radiant: Snapshot,
+    }
+
+    fn build(
+        mut built: ResMut<Built>,
+        players: Res<Players>,
+        units: UnitQuery,
+        clock: Res<GameClock>,
+        runes: Res<Runes>,
+        forest: Res<Forest>,
+        vision: Res<TeamVision>,
+    ) {
+        built.full = Snapshot::build(&players, &units, *clock, &runes, &forest);
+        built.radiant = Snapshot::build_visible(
+            &players,
+            &units,
+            *clock,
+            &runes,
+            &forest,
+            &vision,
+            Team::Radiant,
+        );
+    }
+
+    #[test]
+    fn test_teams_only_see_their_own_gold_and_stashes() {
+        let mut world = World::new();
+        world.init_resource::<Built>();
+        world.init_resource::<Players>();
+        world.init_resource::<GameClock>();
+        world.init_resource::<Runes>();
+        world.init_resource::<Forest>();
+        world.init_resource::<TeamVision>();
+        let ally = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        let enemy = world.spawn(HeroBundle::new(PlayerId(2), Team::Dire)).id();
+        world.get_mut::<Position>(enemy).unwrap().0 = Team::Radiant.fountain();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((update_vision, build).chain());
+        schedule.run(&mut world);
+
+        let built = world.resource::<Built>();
+        let unit = |snapshot: &Snapshot, entity: Entity| {
+            snapshot
+                .units
+                .iter()
+                .find(|unit| unit.id == entity.into())
+                .cloned()
+                .unwrap()
+        };
+        assert!(unit(&built.radiant, ally).gold.is_some());
+        assert!(unit(&built.radiant, enemy).gold.is_none());
+        assert!(unit(&built.full, enemy).gold.is_some());
+        assert!(unit(&built.radiant, ally).stash.is_some());
+        assert!(unit(&built.radiant, enemy).stash.is_none());
+        assert!(unit(&built.full, enemy).stash.is_some());
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_quinnet::{server::Server, shared::ClientId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::GameClock,
+    config::ServerConfig,
+    identity::Identity,
+    player::{Players, Team},
+    rune::Runes,
+    simulation::{Tick, TickRate},
+    snapshot::{Snapshot, UnitQuery},
+    tree::Forest,
+    vision::TeamVision,
+    ServerMessage,
+};
+
+/// Whose vision a spectator watches the match with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpectatorView {
+    #[default]
+    Full,
+    Team(Team),
+}
+
+/// Someone watching the match. Spectators don't take a player slot and never see the match
+/// sooner than the configured delay.
+#[derive(Debug, Clone)]
+pub struct Spectator {
+    pub identity: Identity,
+    pub name: String,
+    pub view: SpectatorView,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Spectators {
+    spectators: HashMap<ClientId, Spectator>,
+}
+
+/// The match at one tick, in every [`SpectatorView`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpectatorSnapshots {
+    pub full: Snapshot,
+    pub radiant: Snapshot,
+    pub dire: Snapshot,
+}
+
+/// Snapshots of the match, held back until they are old enough to show to spectators so that
+/// they can't tell players what the other team is doing.
+#[derive(Resource, Debug)]
+pub struct SpectatorFeed {
+    delay: u64,
+    pending: VecDeque<(u64, SpectatorSnapshots)>,
+    released: SpectatorSnapshots,
+}
+
+impl SpectatorView {
+    pub const ALL: [Self; 3] = [
+        Self::Full,
+        Self::Team(Team::Radiant),
+        Self::Team(Team::Dire),
+    ];
+}
+
+impl SpectatorSnapshots {
+    pub fn view(&self, view: SpectatorView) -> &Snapshot {
+        match view {
+            SpectatorView::Full => &self.full,
+            SpectatorView::Team(Team::Radiant) => &self.radiant,
+            SpectatorView::Team(Team::Dire) => &self.dire,
+        }
+    }
+}
+
+impl Spectators {
+    /// Adds a spectator, returning the client previously watching under the same identity.
+    pub fn join(
+        &mut self,
+        client_id: ClientId,
+        identity: Identity,
+        name: String,
+    ) -> Option<ClientId> {
+        let replaced = self.find(&identity);
+        if let Some(replaced) = replaced {
+            self.spectators.remove(&replaced);
+        }
+        self.spectators.insert(
+            client_id,
+            Spectator {
+                identity,
+                name,
+                view: SpectatorView::Full,
+            },
+        );
+        replaced.filter(|replaced| *replaced != client_id)
+    }
+
+    pub fn leave(&mut self, client_id: ClientId) -> Option<Spectator> {
+        self.spectators.remove(&client_id)
+    }
+
+    pub fn find(&self, identity: &Identity) -> Option<ClientId> {
+        self.spectators
+            .iter()
+            .find(|(_, spectator)| &spectator.identity == identity)
+            .map(|(client_id, _)| *client_id)
+    }
+
+    pub fn get(&self, client_id: ClientId) -> Option<&Spectator> {
+        self.spectators.get(&client_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spectators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spectators.is_empty()
+    }
+
+    pub fn clients(&self) -> Vec<ClientId> {
+        self.spectators.keys().copied().collect()
+    }
+
+    /// The spectators watching with `view`.
+    pub fn watching(&self, view: SpectatorView) -> Vec<ClientId> {
+        self.spectators
+            .iter()
+            .filter(|(_, spectator)| spectator.view == view)
+            .map(|(client_id, _)| *client_id)
+            .collect()
+    }
+
+    /// Switches a spectator to `view`, returning false if `client_id` is not spectating.
+    pub fn set_view(&mut self, client_id: ClientId, view: SpectatorView) -> bool {
+        let Some(spectator) = self.spectators.get_mut(&client_id) else {
+            return false;
+        };
+        spectator.view = view;
+        true
+    }
+}
+
+impl SpectatorFeed {
+    /// Holds snapshots back for `delay` ticks.
+    pub fn new(delay: u64) -> Self {
+        Self {
+            delay,
+            pending: VecDeque::new(),
+            released: SpectatorSnapshots::default(),
+        }
+    }
+
+    /// Records the match as it was at `tick`, unless nothing changed since the last snapshot.
+    pub fn record(&mut self, tick: u64, snapshot: SpectatorSnapshots) {
+        let latest = self
+            .pending
+            .back()
+            .map_or(&self.released, |(_, latest)| latest);
+        if *latest != snapshot {
+            self.pending.push_back((tick, snapshot));
+        }
+    }
+
+    /// Releases every snapshot that is at least the delay old at `tick`, returning the newest of
+    /// them if there were any.
+    pub fn release(&mut self, tick: u64) -> Option<&SpectatorSnapshots> {
+        let mut released = None;
+        while let Some((recorded, _)) = self.pending.front() {
+            if recorded + self.delay > tick {
+                break;
+            }
+            released = self.pending.pop_front().map(|(_, snapshot)| snapshot);
+        }
+        self.released = released?;
+        Some(&self.released)
+    }
+
+    /// The newest snapshots spectators may see.
+    pub fn released(&self) -> &SpectatorSnapshots {
+        &self.released
+    }
+}
+
+/// Records the match for spectators once a second, if the server takes any.
+#[allow(clippy::too_many_arguments)]
+pub fn record_spectator_feed(
+    mut feed: ResMut<SpectatorFeed>,
+    config: Res<ServerConfig>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    players: Res<Players>,
+    units: UnitQuery,
+    clock: Res<GameClock>,
+    runes: Res<Runes>,
+    forest: Res<Forest>,
+    vision: Res<TeamVision>,
+) {
+    if config.max_spectators == 0 || !tick.0.is_multiple_of(tick_rate.0 as u64) {
+        return;
+    }
+    let team =
+        |team| Snapshot::build_visible(&players, &units, *clock, &runes, &forest, &vision, team);
+    let snapshots = SpectatorSnapshots {
+        full: Snapshot::build(&players, &units, *clock, &runes, &forest),
+        radiant: team(Team::Radiant),
+        dire: team(Team::Dire),
+    };
+    feed.record(tick.0, snapshots);
+}
+
+pub fn send_spectator_snapshots(
+    mut feed: ResMut<SpectatorFeed>,
+    server: Res<Server>,
+    spectators: Res<Spectators>,
+    tick: Res<Tick>,
+) {
+    let Some(snapshots) = feed.release(tick.0) else {
+        return;
+    };
+    for view in SpectatorView::ALL {
+        let clients = spectators.watching(view);
+        if clients.is_empty() {
+            continue;
+        }
+        server.endpoint().try_send_group_message(
+            clients.iter(),
+            ServerMessage::Snapshot(snapshots.view(view).clone()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player::PlayerId, snapshot::UnitSnapshot, unit::UnitId};
+
+    fn snapshot(x: f32) -> SpectatorSnapshots {
+        let full = Snapshot {
+            players: Vec::new(),
+            units: vec![UnitSnapshot {
+                id: UnitId(1),
+                owner: Some(PlayerId(1)),
+                team: Some(Team::Radiant),
+                hero: true,
+                position: Vec2::new(x, 0.0),
+                health: None,
+                inventory: None,
+                gold: None,
+                stash: None,
+                level: None,
+                courier: false,
+            }],
+            ..Default::default()
+        };
+        // Only Radiant sees the hero.
+        SpectatorSnapshots {
+            radiant: full.clone(),
+            full,
+            dire: Snapshot::default(),
+        }
+    }
+
+    #[test]
+    fn test_feed_delays_snapshots() {
+        let mut feed = SpectatorFeed::new(10);
+        feed.record(1, snapshot(1.0));
+        feed.record(2, snapshot(1.0));
+        feed.record(5, snapshot(5.0));
+        assert_eq!(feed.pending.len(), 2);
+
+        assert_eq!(feed.release(10), None);
+        assert_eq!(feed.released(), &SpectatorSnapshots::default());
+        assert_eq!(feed.release(11), Some(&snapshot(1.0)));
+        assert_eq!(feed.release(12), None);
+        assert_eq!(feed.release(20), Some(&snapshot(5.0)));
+        assert_eq!(feed.released(), &snapshot(5.0));
+    }
+
+    #[test]
+    fn test_feed_without_delay_is_live() {
+        let mut feed = SpectatorFeed::new(0);
+        feed.record(3, snapshot(3.0));
+        assert_eq!(feed.release(3), Some(&snapshot(3.0)));
+    }
+
+    #[test]
+    fn test_spectator_rejoin_replaces_client() {
+        let mut spectators = Spectators::default();
+        let identity = Identity([1; 32]);
+        assert_eq!(spectators.join(1, identity, "alice".to_string()), None);
+        assert_eq!(spectators.join(2, identity, "alice".to_string()), Some(1));
+        assert_eq!(spectators.clients(), vec![2]);
+        assert_eq!(spectators.join(2, identity, "alice".to_string()), None);
+        assert!(spectators.leave(2).is_some());
+        assert!(spectators.is_empty());
+    }
+
+    #[test]
+    fn test_spectators_choose_their_view() {
+        let mut spectators = Spectators::default();
+        spectators.join(1, Identity([1; 32]), "alice".to_string());
+        spectators.join(2, Identity([2; 32]), "bob".to_string());
+        assert!(spectators.set_view(2, SpectatorView::Team(Team::Dire)));
+        assert!(!spectators.set_view(3, SpectatorView::Full));
+        assert_eq!(spectators.watching(SpectatorView::Full), vec![1]);
+        assert_eq!(
+            spectators.watching(SpectatorView::Team(Team::Dire)),
+            vec![2]
+        );
+        assert!(spectators
+            .watching(SpectatorView::Team(Team::Radiant))
+            .is_empty());
+
+        let snapshots = snapshot(1.0);
+        assert_eq!(snapshots.view(SpectatorView::Full).units.len(), 1);
+        assert_eq!(
+            snapshots
+                .view(SpectatorView::Team(Team::Radiant))
+                .units
+                .len(),
+            1
+        );
+        assert!(snapshots
+            .view(SpectatorView::Team(Team::Dire))
+            .units
+            .is_empty());
+    }
+
+    #[test]
+    fn test_feed_is_recorded_once_a_second() {
+        let mut world = World::new();
+        world.insert_resource(ServerConfig::default());
+        world.insert_resource(TickRate(10));
+        world.insert_resource(SpectatorFeed::new(100));
+        world.init_resource::<Tick>();
+        world.init_resource::<Players>();
+        world.init_resource::<GameClock>();
+        world.init_resource::<Runes>();
+        world.init_resource::<Forest>();
+        world.init_resource::<TeamVision>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(record_spectator_feed);
+        let mut run = |world: &mut World, tick| {
+            world.resource_mut::<Tick>().0 = tick;
+            world.resource_mut::<GameClock>().seconds = tick;
+            schedule.run(world);
+            world.resource::<SpectatorFeed>().pending.len()
+        };
+
+        assert_eq!(run(&mut world, 10), 1);
+        assert_eq!(run(&mut world, 11), 1);
+        assert_eq!(run(&mut world, 20), 2);
+
+        world.resource_mut::<ServerConfig>().max_spectators = 0;
+        assert_eq!(run(&mut world, 30), 2);
+    }
+}
@@ -0,0 +1,363 @@
+//! Trees block movement and vision. The forest is defined in `data/trees.toml`; felled trees grow
+//! back after a while. Clients know the forest too, so snapshots only carry which trees stand.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    item::{Inventory, Item},
+    simulation::{Tick, TickRate},
+    unit::{MoveTarget, Position, Unit},
+};
+
+/// The forest every match is played with.
+pub const TREE_LAYOUT: &str = include_str!("../data/trees.toml");
+
+/// How close to a tree a unit has to get to cut it down.
+pub const TREE_CUT_RANGE: f32 = 50.0;
+
+#[derive(Debug, Error)]
+pub enum ForestError {
+    #[error("failed to parse tree layout: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("groves need a positive spacing, got {0}")]
+    Spacing(f32),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Grove {
+    from: Vec2,
+    to: Vec2,
+    spacing: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TreeLayout {
+    radius: f32,
+    regrow_secs: u64,
+    groves: Vec<Grove>,
+}
+
+/// One bit per tree, set while it stands.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TreeBits(Vec<u64>);
+
+/// The trees of the map and which of them stand, kept in sync with the [`Tree`] entities.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct Forest {
+    pub radius: f32,
+    pub regrow_secs: u64,
+    /// Where each tree grows, by index.
+    pub trees: Vec<Vec2>,
+    pub standing: TreeBits,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tree {
+    /// Index of the tree in the [`Forest`].
+    pub index: usize,
+    /// When a felled tree grows back, `None` while it stands.
+    pub regrows_at: Option<u64>,
+}
+
+#[derive(Bundle)]
+pub struct TreeBundle {
+    pub tree: Tree,
+    pub position: Position,
+}
+
+/// The tree a unit walks to and cuts down with its Quelling Blade, by index in the [`Forest`].
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TreeTarget(pub Option<usize>);
+
+/// Destroys a tree, sent by items and abilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FellTree {
+    pub tree: usize,
+}
+
+impl TreeBits {
+    /// `len` trees, all standing.
+    pub fn new(len: usize) -> Self {
+        let mut words = vec![u64::MAX; len.div_ceil(64)];
+        let unused = words.len() * 64 - len;
+        if let Some(last) = words.last_mut() {
+            *last >>= unused;
+        }
+        Self(words)
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.0
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    pub fn set(&mut self, index: usize, standing: bool) {
+        let Some(word) = self.0.get_mut(index / 64) else {
+            return;
+        };
+        if standing {
+            *word |= 1 << (index % 64);
+        } else {
+            *word &= !(1 << (index % 64));
+        }
+    }
+}
+
+impl Forest {
+    pub fn parse(text: &str) -> Result<Self, ForestError> {
+        let layout: TreeLayout = toml::from_str(text)?;
+        let mut trees = Vec::new();
+        for grove in &layout.groves {
+            if grove.spacing.is_nan() || grove.spacing <= 0.0 {
+                return Err(ForestError::Spacing(grove.spacing));
+            }
+            let count = (grove.from.distance(grove.to) / grove.spacing) as usize + 1;
+            let step = (grove.to - grove.from).normalize_or_zero() * grove.spacing;
+            trees.extend((0..count).map(|index| grove.from + step * index as f32));
+        }
+        Ok(Self {
+            radius: layout.radius,
+            regrow_secs: layout.regrow_secs,
+            standing: TreeBits::new(trees.len()),
+            trees,
+        })
+    }
+
+    /// The forest built into the server, see [`TREE_LAYOUT`].
+    pub fn builtin() -> Self {
+        Self::parse(TREE_LAYOUT).expect("built-in tree layout is valid")
+    }
+
+    fn standing_trees(&self) -> impl Iterator<Item = (usize, Vec2)> + '_ {
+        self.trees
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.standing.get(*index))
+            .map(|(index, position)| (index, *position))
+    }
+
+    /// The standing tree covering `position`, if any.
+    pub fn tree_at(&self, position: Vec2) -> Option<usize> {
+        self.standing_trees()
+            .find(|(_, tree)| tree.distance(position) < self.radius)
+            .map(|(index, _)| index)
+    }
+
+    /// The standing trees within `radius` of `center`, for abilities that fell trees in an area.
+    pub fn trees_within(&self, center: Vec2, radius: f32) -> Vec<usize> {
+        self.standing_trees()
+            .filter(|(_, tree)| tree.distance(center) <= radius)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Whether a standing tree is in the way of seeing from `from` to `to`.
+    pub fn blocks_sight(&self, from: Vec2, to: Vec2) -> bool {
+        self.standing_trees()
+            .any(|(_, tree)| distance_to_segment(tree, from, to) < self.radius)
+    }
+}
+
+fn distance_to_segment(point: Vec2, from: Vec2, to: Vec2) -> f32 {
+    let segment = to - from;
+    let length_squared = segment.length_squared();
+    if length_squared == 0.0 {
+        return point.distance(from);
+    }
+    let along = ((point - from).dot(segment) / length_squared).clamp(0.0, 1.0);
+    point.distance(from + segment * along)
+}
+
+impl TreeBundle {
+    pub fn new(forest: &Forest, index: usize) -> Self {
+        Self {
+            tree: Tree {
+                index,
+                regrows_at: None,
+            },
+            position: Position(forest.trees[index]),
+        }
+    }
+}
+
+pub fn spawn_trees(mut commands: Commands, forest: Res<Forest>) {
+    let trees = (0..forest.trees.len())
+        .map(|index| TreeBundle::new(&forest, index))
+        .collect::<Vec<_>>();
+    commands.spawn_batch(trees);
+}
+
+/// Units with a Quelling Blade cut down the tree they were ordered to, once they are close enough.
+pub fn cut_trees(
+    mut felled_events: EventWriter<FellTree>,
+    forest: Res<Forest>,
+    mut unit_query: Query<(
+        &Position,
+        &mut TreeTarget,
+        &mut MoveTarget,
+        Option<&Inventory>,
+    )>,
+) {
+    for (position, mut tree_target, mut move_target, inventory) in &mut unit_query {
+        let Some(tree) = tree_target.0 else {
+            continue;
+        };
+        if position.0.distance(forest.trees[tree]) > TREE_CUT_RANGE {
+            continue;
+        }
+        tree_target.0 = None;
+        move_target.0 = None;
+        if inventory.is_some_and(|inventory| inventory.contains(Item::QuellingBlade)) {
+            felled_events.send(FellTree { tree });
+        }
+    }
+}
+
+pub fn fell_trees(
+    mut felled_events: EventReader<FellTree>,
+    forest: Res<Forest>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+    mut tree_query: Query<&mut Tree>,
+) {
+    for event in felled_events.iter() {
+        for mut tree in &mut tree_query {
+            if tree.index == event.tree && tree.regrows_at.is_none() {
+                tree.regrows_at = Some(tick.0 + forest.regrow_secs * tick_rate.0 as u64);
+            }
+        }
+    }
+}
+
+/// Felled trees grow back once their time is up and no unit stands where they grow.
+pub fn regrow_trees(
+    forest: Res<Forest>,
+    tick: Res<Tick>,
+    mut tree_query: Query<(&mut Tree, &Position)>,
+    unit_query: Query<&Position, With<Unit>>,
+) {
+    for (mut tree, position) in &mut tree_query {
+        if !tree
+            .regrows_at
+            .is_some_and(|regrows_at| tick.0 >= regrows_at)
+        {
+            continue;
+        }
+        let occupied = unit_query
+            .iter()
+            .any(|unit| unit.0.distance(position.0) < forest.radius);
+        if !occupied {
+            tree.regrows_at = None;
+        }
+    }
+}
+
+pub fn sync_forest(mut forest: ResMut<Forest>, tree_query: Query<&Tree, Changed<Tree>>) {
+    for tree in &tree_query {
+        forest.standing.set(tree.index, tree.regrows_at.is_none());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        player::{PlayerId, Team},
+        unit::HeroBundle,
+    };
+
+    #[test]
+    fn test_tree_bits() {
+        let mut bits = TreeBits::new(70);
+        assert!(bits.get(0) && bits.get(69));
+        assert!(!bits.get(70));
+        bits.set(65, false);
+        assert!(!bits.get(65));
+        assert_eq!(bits.0, [u64::MAX, 0b111101]);
+        bits.set(65, true);
+        assert_eq!(bits, TreeBits::new(70));
+    }
+
+    #[test]
+    fn test_builtin_forest() {
+        let forest = Forest::builtin();
+        assert_eq!(forest.trees.len(), 48);
+        assert!(forest.standing.get(forest.trees.len() - 1));
+        assert_eq!(forest.tree_at(forest.trees[3] + Vec2::X), Some(3));
+        assert_eq!(forest.tree_at(Vec2::ZERO), None);
+
+        let broken = TREE_LAYOUT.replace("spacing = 30.0", "spacing = 0.0");
+        assert!(matches!(
+            Forest::parse(&broken),
+            Err(ForestError::Spacing(_))
+        ));
+    }
+
+    #[test]
+    fn test_trees_block_sight() {
+        let mut forest = Forest {
+            radius: 20.0,
+            trees: vec![Vec2::new(50.0, 0.0)],
+            standing: TreeBits::new(1),
+            ..Default::default()
+        };
+        assert!(forest.blocks_sight(Vec2::ZERO, Vec2::new(100.0, 10.0)));
+        assert!(!forest.blocks_sight(Vec2::ZERO, Vec2::new(100.0, 50.0)));
+        assert!(!forest.blocks_sight(Vec2::ZERO, Vec2::new(20.0, 0.0)));
+        forest.standing.set(0, false);
+        assert!(!forest.blocks_sight(Vec2::ZERO, Vec2::new(100.0, 10.0)));
+    }
+
+    #[test]
+    fn test_quelling_blade_fells_trees_that_regrow() {
+        let mut world = World::new();
+        world.insert_resource(TickRate(10));
+        world.insert_resource(Tick(1));
+        world.insert_resource(Forest {
+            radius: 20.0,
+            regrow_secs: 5,
+            trees: vec![Vec2::new(100.0, 0.0)],
+            standing: TreeBits::new(1),
+        });
+        world.init_resource::<Events<FellTree>>();
+        let tree = world
+            .spawn(TreeBundle::new(world.resource::<Forest>(), 0))
+            .id();
+        let hero = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        world.get_mut::<Position>(hero).unwrap().0 = Vec2::new(60.0, 0.0);
+        world.get_mut::<TreeTarget>(hero).unwrap().0 = Some(0);
+        let mut schedule = Schedule::new();
+        schedule.add_systems((cut_trees, fell_trees, regrow_trees, sync_forest).chain());
+
+        // Without a Quelling Blade the hero only walks up to the tree.
+        schedule.run(&mut world);
+        assert_eq!(world.get::<TreeTarget>(hero).unwrap().0, None);
+        assert!(world.resource::<Forest>().standing.get(0));
+
+        world.get_mut::<TreeTarget>(hero).unwrap().0 = Some(0);
+        world
+            .get_mut::<Inventory>(hero)
+            .unwrap()
+            .add(Item::QuellingBlade);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Tree>(tree).unwrap().regrows_at, Some(51));
+        assert!(!world.resource::<Forest>().standing.get(0));
+
+        // The tree waits for the hero standing in it to leave.
+        world.get_mut::<Position>(hero).unwrap().0 = Vec2::new(100.0, 0.0);
+        world.insert_resource(Tick(51));
+        schedule.run(&mut world);
+        assert!(!world.resource::<Forest>().standing.get(0));
+        world.get_mut::<Position>(hero).unwrap().0 = Vec2::new(60.0, 0.0);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Tree>(tree).unwrap().regrows_at, None);
+        assert!(world.resource::<Forest>().standing.get(0));
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::{Attack, AttackTarget, Health},
+    death::Respawn,
+    invisibility::Invisibility,
+    item::{Inventory, Item, Stash},
+    player::{PlayerId, Team},
+    rune::{RuneBuff, RuneTarget},
+    tree::TreeTarget,
+    vision::Vision,
+    ward::WardTarget,
+};
+
+/// Units can't leave the square from `-MAP_HALF_SIZE` to `MAP_HALF_SIZE`.
+pub const MAP_HALF_SIZE: Vec2 = Vec2::new(512.0, 512.0);
+pub const HERO_MOVE_SPEED: f32 = 300.0;
+pub const HERO_HEALTH: f32 = 600.0;
+pub const HERO_DAMAGE: f32 = 50.0;
+pub const HERO_ATTACK_RANGE: f32 = 100.0;
+/// Seconds between hero attacks.
+pub const HERO_ATTACK_COOLDOWN: f32 = 1.2;
+pub const HERO_DAY_VISION: f32 = 400.0;
+pub const HERO_NIGHT_VISION: f32 = 200.0;
+/// Total experience a hero needs for each level after the first.
+pub const LEVEL_EXPERIENCE: [u32; 9] = [200, 500, 900, 1400, 2000, 2700, 3500, 4400, 5400];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UnitId(pub u64);
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Position(pub Vec2);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Owner(pub PlayerId);
+
+/// Distance per second.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct MoveSpeed(pub f32);
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct MoveTarget(pub Option<Vec2>);
+
+#[derive(Component, Debug, Default)]
+pub struct Unit;
+
+/// Gold a hero holds. Reliable gold is never lost.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gold {
+    pub reliable: u32,
+    pub unreliable: u32,
+}
+
+/// Experience a hero gathered, which decides its level.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experience(pub u32);
+
+#[derive(Component, Debug, Default)]
+pub struct Hero;
+
+#[derive(Bundle)]
+pub struct HeroBundle {
+    pub unit: Unit,
+    pub hero: Hero,
+    pub owner: Owner,
+    pub team: Team,
+    pub position: Position,
+    pub move_speed: MoveSpeed,
+    pub move_target: MoveTarget,
+    pub health: Health,
+    pub attack: Attack,
+    pub attack_target: AttackTarget,
+    pub inventory: Inventory,
+    pub stash: Stash,
+    pub vision: Vision,
+    pub gold: Gold,
+    pub experience: Experience,
+    pub respawn: Respawn,
+    pub rune_target: RuneTarget,
+    pub rune_buff: RuneBuff,
+    pub invisibility: Invisibility,
+    pub ward_target: WardTarget,
+    pub tree_target: TreeTarget,
+}
+
+impl From<Entity> for UnitId {
+    fn from(entity: Entity) -> Self {
+        Self(entity.to_bits())
+    }
+}
+
+impl UnitId {
+    pub fn entity(self) -> Entity {
+        Entity::from_bits(self.0)
+    }
+}
+
+impl Gold {
+    pub fn total(&self) -> u32 {
+        self.reliable + self.unreliable
+    }
+
+    /// Pays `amount`, unreliable gold first, if there is enough.
+    pub fn spend(&mut self, amount: u32) -> bool {
+        if self.total() < amount {
+            return false;
+        }
+        let unreliable = amount.min(self.unreliable);
+        self.unreliable -= unreliable;
+        self.reliable -= amount - unreliable;
+        true
+    }
+}
+
+impl Experience {
+    /// The level, starting at 1.
+    pub fn level(self) -> u32 {
+        1 + LEVEL_EXPERIENCE
+            .iter()
+            .take_while(|needed| self.0 >= **needed)
+            .count() as u32
+    }
+}
+
+impl HeroBundle {
+    /// A hero at its fountain, carrying an empty bottle.
+    pub fn new(owner: PlayerId, team: Team) -> Self {
+        let mut inventory = Inventory::default();
+        inventory.add(Item::Bottle(None));
+        Self {
+            unit: Unit,
+            hero: Hero,
+            owner: Owner(owner),
+            team,
+            position: Position(team.fountain()),
+            move_speed: MoveSpeed(HERO_MOVE_SPEED),
+            move_target: Default::default(),
+            health: Health::full(HERO_HEALTH),
+            attack: Attack::new(HERO_DAMAGE, HERO_ATTACK_RANGE, HERO_ATTACK_COOLDOWN),
+            attack_target: Default::default(),
+            inventory,
+            stash: Default::default(),
+            vision: Vision {
+                day: HERO_DAY_VISION,
+                night: HERO_NIGHT_VISION,
+            },
+            gold: Default::default(),
+            experience: Default::default(),
+            respawn: Default::default(),
+            rune_target: Default::default(),
+            rune_buff: Default::default(),
+            invisibility: Default::default(),
+            ward_target: Default::default(),
+            tree_target: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! What each team can see. Teams always see their own units, and others only within the vision
+//! range of one of their living units, which is shorter at night. Invisible units also need to be
+//! within range of one of the team's true sight sources. Standing trees block vision. Players are
+//! only sent what their team sees.
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    clock::{DayPhase, GameClock},
+    combat::AttackTarget,
+    death::Respawn,
+    invisibility::{true_sight_range, Invisibility, TrueSight},
+    item::Inventory,
+    player::Team,
+    rune::RUNE_SPOTS,
+    tree::Forest,
+    unit::{Position, Unit},
+};
+
+/// How far a unit sees by day and by night.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Vision {
+    pub day: f32,
+    pub night: f32,
+}
+
+/// The units and rune spots each team sees, as of the last [`update_vision`]. Updated at the start
+/// of every tick and again before snapshots are sent.
+#[derive(Resource, Debug, Default)]
+pub struct TeamVision {
+    visible: HashMap<Team, HashSet<Entity>>,
+    rune_spots: HashMap<Team, [bool; RUNE_SPOTS.len()]>,
+}
+
+pub type SightingQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Position,
+        Option<&'static Team>,
+        Option<&'static Vision>,
+        Option<&'static Invisibility>,
+        Option<&'static TrueSight>,
+        Option<&'static Inventory>,
+        Option<&'static Respawn>,
+    ),
+    With<Unit>,
+>;
+
+/// A unit as far as vision is concerned.
+#[derive(Debug, Clone, Copy)]
+pub struct Sighting {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub team: Option<Team>,
+    pub vision: Option<Vision>,
+    pub invisible: bool,
+    /// How far the unit detects invisible units, if at all.
+    pub true_sight: Option<f32>,
+}
+
+impl Vision {
+    pub fn range(&self, phase: DayPhase) -> f32 {
+        match phase {
+            DayPhase::Day => self.day,
+            DayPhase::Night => self.night,
+        }
+    }
+}
+
+impl TeamVision {
+    pub fn sees(&self, team: Team, entity: Entity) -> bool {
+        self.visible
+            .get(&team)
+            .is_some_and(|visible| visible.contains(&entity))
+    }
+
+    /// Whether `team` sees the rune spot with index `spot` in [`RUNE_SPOTS`].
+    pub fn sees_rune_spot(&self, team: Team, spot: usize) -> bool {
+        self.rune_spots
+            .get(&team)
+            .is_some_and(|spots| spots.get(spot).copied().unwrap_or(false))
+    }
+}
+
+/// Where `team` sees from during `phase`, and how far.
+fn viewers(team: Team, phase: DayPhase, units: &[Sighting]) -> Vec<(Vec2, f32)> {
+    units
+        .iter()
+        .filter(|unit| unit.team == Some(team))
+        .filter_map(|unit| Some((unit.position, unit.vision?.range(phase))))
+        .collect()
+}
+
+/// Whether any of `sources` reaches `position` without a tree in the way.
+fn within(sources: &[(Vec2, f32)], position: Vec2, forest: &Forest) -> bool {
+    sources.iter().any(|(source, range)| {
+        source.distance(position) <= *range && !forest.blocks_sight(*source, position)
+    })
+}
+
+/// Whether `team` sees `position` during `phase`.
+pub fn sees_position(
+    team: Team,
+    phase: DayPhase,
+    units: &[Sighting],
+    forest: &Forest,
+    position: Vec2,
+) -> bool {
+    within(&viewers(team, phase, units), position, forest)
+}
+
+/// The units `team` sees during `phase`. Invisible units are hidden from the other team unless it
+/// has true sight of them.
+pub fn visible_units(
+    team: Team,
+    phase: DayPhase,
+    units: &[Sighting],
+    forest: &Forest,
+) -> HashSet<Entity> {
+    let viewers = viewers(team, phase, units);
+    let detectors = units
+        .iter()
+        .filter(|unit| unit.team == Some(team))
+        .filter_map(|unit| Some((unit.position, unit.true_sight?)))
+        .collect::<Vec<_>>();
+    units
+        .iter()
+        .filter(|unit| {
+            unit.team == Some(team)
+                || within(&viewers, unit.position, forest)
+                    && (!unit.invisible || within(&detectors, unit.position, forest))
+        })
+        .map(|unit| unit.entity)
+        .collect()
+}
+
+pub fn update_vision(
+    mut vision: ResMut<TeamVision>,
+    clock: Res<GameClock>,
+    forest: Res<Forest>,
+    unit_query: SightingQuery,
+) {
+    let units = unit_query
+        .iter()
+        .map(
+            |(entity, position, team, vision, invisibility, true_sight, inventory, respawn)| {
+                Sighting {
+                    entity,
+                    position: position.0,
+                    team: team.copied(),
+                    vision: vision
+                        .filter(|_| !respawn.is_some_and(Respawn::is_dead))
+                        .copied(),
+                    invisible: invisibility.is_some_and(Invisibility::is_invisible),
+                    true_sight: true_sight_range(true_sight, inventory),
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    for team in [Team::Radiant, Team::Dire] {
+        vision
+            .visible
+            .insert(team, visible_units(team, clock.phase, &units, &forest));
+        vision.rune_spots.insert(
+            team,
+            RUNE_SPOTS.map(|spot| sees_position(team, clock.phase, &units, &forest, spot)),
+        );
+    }
+}
+
+/// Units stop attacking what their team no longer sees.
+pub fn lose_hidden_targets(
+    vision: Res<TeamVision>,
+    mut attacker_query: Query<(&Team, &mut AttackTarget)>,
+) {
+    for (team, mut attack_target) in &mut attacker_query {
+        if attack_target
+            .0
+            .is_some_and(|target| !vision.sees(*team, target))
+        {
+            attack_target.0 = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{combat::Attack, tree::TreeBits, unit::MoveTarget};
+
+    #[test]
+    fn test_night_invisibility_and_trees_limit_vision() {
+        let entity = |index| Entity::from_raw(index);
+        let mut units = [
+            Sighting {
+                entity: entity(0),
+                position: Vec2::ZERO,
+                team: Some(Team::Radiant),
+                vision: Some(Vision {
+                    day: 300.0,
+                    night: 100.0,
+                }),
+                invisible: false,
+                true_sight: None,
+            },
+            Sighting {
+                entity: entity(1),
+                position: Vec2::new(200.0, 0.0),
+                team: Some(Team::Dire),
+                vision: None,
+                invisible: false,
+                true_sight: None,
+            },
+            Sighting {
+                entity: entity(2),
+                position: Vec2::new(50.0, 0.0),
+                team: None,
+                vision: None,
+                invisible: false,
+                true_sight: None,
+            },
+            Sighting {
+                entity: entity(3),
+                position: Vec2::new(10.0, 0.0),
+                team: Some(Team::Dire),
+                vision: None,
+                invisible: true,
+                true_sight: None,
+            },
+        ];
+        let mut forest = Forest::default();
+        let day = visible_units(Team::Radiant, DayPhase::Day, &units, &forest);
+        assert_eq!(day, HashSet::from_iter([entity(0), entity(1), entity(2)]));
+        let night = visible_units(Team::Radiant, DayPhase::Night, &units, &forest);
+        assert_eq!(night, HashSet::from_iter([entity(0), entity(2)]));
+        // Units without vision of their own still show up for their team.
+        let dire = visible_units(Team::Dire, DayPhase::Day, &units, &forest);
+        assert_eq!(dire, HashSet::from_iter([entity(1), entity(3)]));
+
+        // True sight only reveals what is also within vision.
+        units[0].true_sight = Some(100.0);
+        let revealed = visible_units(Team::Radiant, DayPhase::Night, &units, &forest);
+        assert_eq!(
+            revealed,
+            HashSet::from_iter([entity(0), entity(2), entity(3)])
+        );
+        units[3].position = Vec2::new(150.0, 0.0);
+        let hidden = visible_units(Team::Radiant, DayPhase::Day, &units, &forest);
+        assert!(!hidden.contains(&entity(3)));
+
+        // Trees block the view of the unit behind them.
+        forest = Forest {
+            radius: 20.0,
+            trees: vec![Vec2::new(120.0, 10.0)],
+            standing: TreeBits::new(1),
+            ..Default::default()
+        };
+        let blocked = visible_units(Team::Radiant, DayPhase::Day, &units, &forest);
+        assert_eq!(blocked, HashSet::from_iter([entity(0), entity(2)]));
+    }
+
+    #[test]
+    fn test_rune_spots_need_vision() {
+        let mut world = World::new();
+        world.init_resource::<GameClock>();
+        world.init_resource::<TeamVision>();
+        world.init_resource::<Forest>();
+        world.spawn((
+            Unit,
+            Team::Radiant,
+            Position(RUNE_SPOTS[0] + Vec2::new(50.0, 0.0)),
+            Vision {
+                day: 100.0,
+                night: 100.0,
+            },
+        ));
+        let mut schedule = Schedule::new();
+        schedule.add_system(update_vision);
+        schedule.run(&mut world);
+
+        let vision = world.resource::<TeamVision>();
+        assert!(vision.sees_rune_spot(Team::Radiant, 0));
+        assert!(!vision.sees_rune_spot(Team::Radiant, 1));
+        assert!(!vision.sees_rune_spot(Team::Dire, 0));
+        assert!(!vision.sees_rune_spot(Team::Radiant, RUNE_SPOTS.len()));
+    }
+
+    #[test]
+    fn test_attackers_lose_hidden_targets() {
+        let mut world = World::new();
+        world.init_resource::<GameClock>();
+        world.init_resource::<TeamVision>();
+        world.init_resource::<Forest>();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((update_vision, lose_hidden_targets).chain());
+
+        let target = world
+            .spawn((
+                Unit,
+                Team::Radiant,
+                Position(Vec2::ZERO),
+                Invisibility::Visible,
+            ))
+            .id();
+        let attacker = world
+            .spawn((
+                Unit,
+                Team::Dire,
+                Position(Vec2::new(50.0, 0.0)),
+                Vision {
+                    day: 100.0,
+                    night: 100.0,
+                },
+                Attack::new(10.0, 100.0, 1.0),
+                AttackTarget(Some(target)),
+                MoveTarget::default(),
+            ))
+            .id();
+        schedule.run(&mut world);
+        assert_eq!(world.get::<AttackTarget>(attacker).unwrap().0, Some(target));
+
+        // Out of vision.
+        world.get_mut::<Position>(target).unwrap().0 = Vec2::new(500.0, 0.0);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<AttackTarget>(attacker).unwrap().0, None);
+
+        // Within vision but invisible, and nothing on the team has true sight.
+        world.get_mut::<AttackTarget>(attacker).unwrap().0 = Some(target);
+        world.get_mut::<Position>(target).unwrap().0 = Vec2::ZERO;
+        *world.get_mut::<Invisibility>(target).unwrap() = Invisibility::Invisible;
+        schedule.run(&mut world);
+        assert_eq!(world.get::<AttackTarget>(attacker).unwrap().0, None);
+
+        world.get_mut::<AttackTarget>(attacker).unwrap().0 = Some(target);
+        world
+            .entity_mut(attacker)
+            .insert(TrueSight { range: 100.0 });
+        schedule.run(&mut world);
+        assert_eq!(world.get::<AttackTarget>(attacker).unwrap().0, Some(target));
+    }
+}
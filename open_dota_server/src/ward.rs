@@ -0,0 +1,448 @@
+//! Wards are bought at the shop, from a stock each team shares that refills over time, and placed
+//! by heroes. Observer wards give their team vision, sentry wards true sight. Both are invisible,
+//! last a fixed time and die to a few hits from anyone; enemies get a bounty for killing one,
+//! denying your own gives nothing.
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::{Health, UnitDied},
+    invisibility::{Invisibility, TrueSight},
+    item::{Inventory, Item, Stash, STASH_RANGE},
+    order::{Order, OrderApplied},
+    player::Team,
+    simulation::{Tick, TickRate},
+    unit::{Gold, Hero, MoveTarget, Owner, Position, Unit},
+    vision::Vision,
+};
+
+/// How close heroes have to get to where they place a ward.
+pub const WARD_PLACE_RANGE: f32 = 100.0;
+/// Hits it takes to kill a ward, however hard they are.
+pub const WARD_HITS: f32 = 4.0;
+pub const WARD_BOUNTY_GOLD: u32 = 50;
+pub const OBSERVER_WARD_VISION: f32 = 450.0;
+pub const SENTRY_WARD_VISION: f32 = 150.0;
+pub const SENTRY_WARD_TRUE_SIGHT: f32 = 450.0;
+pub const WARD_KINDS: [WardKind; 2] = [WardKind::Observer, WardKind::Sentry];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WardKind {
+    Observer,
+    Sentry,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ward {
+    pub kind: WardKind,
+    pub expires_at: u64,
+}
+
+/// Where a hero was ordered to place a ward.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct WardTarget(pub Option<(WardKind, Vec2)>);
+
+/// Wards left in the shop for each team.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct WardStock {
+    pub stock: HashMap<(Team, WardKind), u32>,
+}
+
+/// A hero placed a ward, to be spawned outside the simulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WardPlaced {
+    pub team: Team,
+    pub kind: WardKind,
+    pub position: Vec2,
+}
+
+#[derive(Bundle)]
+pub struct WardBundle {
+    pub unit: Unit,
+    pub ward: Ward,
+    pub team: Team,
+    pub position: Position,
+    pub health: Health,
+    pub vision: Vision,
+    pub invisibility: Invisibility,
+}
+
+impl WardKind {
+    pub fn cost(self) -> u32 {
+        match self {
+            Self::Observer => 0,
+            Self::Sentry => 50,
+        }
+    }
+
+    pub fn duration_secs(self) -> u64 {
+        match self {
+            Self::Observer => 360,
+            Self::Sentry => 420,
+        }
+    }
+
+    pub fn initial_stock(self) -> u32 {
+        match self {
+            Self::Observer => 2,
+            Self::Sentry => 3,
+        }
+    }
+
+    pub fn max_stock(self) -> u32 {
+        match self {
+            Self::Observer => 4,
+            Self::Sentry => 10,
+        }
+    }
+
+    /// Sentries detect invisible units around them.
+    pub fn true_sight(self) -> Option<TrueSight> {
+        match self {
+            Self::Observer => None,
+            Self::Sentry => Some(TrueSight {
+                range: SENTRY_WARD_TRUE_SIGHT,
+            }),
+        }
+    }
+
+    /// Seconds until the shop gets another one.
+    pub fn restock_secs(self) -> u64 {
+        match self {
+            Self::Observer => 135,
+            Self::Sentry => 70,
+        }
+    }
+}
+
+impl Default for WardStock {
+    fn default() -> Self {
+        let stock = [Team::Radiant, Team::Dire]
+            .into_iter()
+            .flat_map(|team| WARD_KINDS.map(|kind| ((team, kind), kind.initial_stock())))
+            .collect();
+        Self { stock }
+    }
+}
+
+impl WardStock {
+    pub fn get(&self, team: Team, kind: WardKind) -> u32 {
+        self.stock.get(&(team, kind)).copied().unwrap_or_default()
+    }
+}
+
+impl WardBundle {
+    pub fn new(team: Team, kind: WardKind, position: Vec2, expires_at: u64) -> Self {
+        let vision = match kind {
+            WardKind::Observer => OBSERVER_WARD_VISION,
+            WardKind::Sentry => SENTRY_WARD_VISION,
+        };
+        Self {
+            unit: Unit,
+            ward: Ward { kind, expires_at },
+            team,
+            position: Position(position),
+            health: Health::full(WARD_HITS),
+            vision: Vision {
+                day: vision,
+                night: vision,
+            },
+            invisibility: Invisibility::Invisible,
+        }
+    }
+}
+
+/// Adds one of each ward to every team's stock whenever its restock time comes around.
+pub fn restock_wards(mut stock: ResMut<WardStock>, tick: Res<Tick>, tick_rate: Res<TickRate>) {
+    if tick.0 == 0 {
+        return;
+    }
+    for kind in WARD_KINDS {
+        if !tick
+            .0
+            .is_multiple_of(kind.restock_secs() * tick_rate.0 as u64)
+        {
+            continue;
+        }
+        for team in [Team::Radiant, Team::Dire] {
+            let count = stock.stock.entry((team, kind)).or_default();
+            *count = (*count + 1).min(kind.max_stock());
+        }
+    }
+}
+
+/// Sells wards heroes were ordered to buy. They go to the inventory at the fountain and to the
+/// stash anywhere else.
+pub fn buy_wards(
+    mut order_events: EventReader<OrderApplied>,
+    mut stock: ResMut<WardStock>,
+    mut hero_query: Query<(&Team, &Position, &mut Gold, &mut Inventory, &mut Stash), With<Hero>>,
+) {
+    for OrderApplied(order) in order_events.iter() {
+        let Order::BuyWard { kind } = order.order else {
+            continue;
+        };
+        let Ok((team, position, mut gold, mut inventory, mut stash)) =
+            hero_query.get_mut(order.unit.entity())
+        else {
+            continue;
+        };
+        let Some(count) = stock
+            .stock
+            .get_mut(&(*team, kind))
+            .filter(|count| **count > 0)
+        else {
+            continue;
+        };
+        if gold.total() < kind.cost() {
+            continue;
+        }
+        let at_base = position.0.distance(team.fountain()) <= STASH_RANGE;
+        let item = Item::Ward(kind);
+        if at_base && inventory.add(item) || stash.0.add(item) {
+            gold.spend(kind.cost());
+            *count -= 1;
+        }
+    }
+}
+
+/// Places the wards heroes reached the spot for, if they still hold one.
+pub fn place_wards(
+    mut placed_events: EventWriter<WardPlaced>,
+    mut hero_query: Query<(
+        &Team,
+        &Position,
+        &mut MoveTarget,
+        &mut WardTarget,
+        &mut Inventory,
+    )>,
+) {
+    for (team, position, mut move_target, mut ward_target, mut inventory) in &mut hero_query {
+        let Some((kind, target)) = ward_target.0 else {
+            continue;
+        };
+        if position.0.distance(target) > WARD_PLACE_RANGE {
+            continue;
+        }
+        ward_target.0 = None;
+        move_target.0 = None;
+        if inventory.take(|item| item == Item::Ward(kind)).is_none() {
+            continue;
+        }
+        placed_events.send(WardPlaced {
+            team: *team,
+            kind,
+            position: target,
+        });
+    }
+}
+
+/// Spawns placed wards.
+pub fn spawn_wards(
+    mut commands: Commands,
+    mut placed_events: EventReader<WardPlaced>,
+    tick: Res<Tick>,
+    tick_rate: Res<TickRate>,
+) {
+    for event in placed_events.iter() {
+        let expires_at = tick.0 + event.kind.duration_secs() * tick_rate.0 as u64;
+        let mut ward = commands.spawn(WardBundle::new(
+            event.team,
+            event.kind,
+            event.position,
+            expires_at,
+        ));
+        if let Some(true_sight) = event.kind.true_sight() {
+            ward.insert(true_sight);
+        }
+    }
+}
+
+/// Pays the bounty for wards killed by the enemy team to the killer's owner.
+pub fn reward_ward_kills(
+    mut died_events: EventReader<UnitDied>,
+    ward_query: Query<&Team, With<Ward>>,
+    killer_query: Query<(&Owner, &Team)>,
+    mut hero_query: Query<(&Owner, &mut Gold), With<Hero>>,
+) {
+    for event in died_events.iter() {
+        let Ok(ward_team) = ward_query.get(event.unit) else {
+            continue;
+        };
+        let Ok((killer, killer_team)) = killer_query.get(event.killer) else {
+            continue;
+        };
+        if killer_team == ward_team {
+            continue;
+        }
+        if let Some((_, mut gold)) = hero_query.iter_mut().find(|(owner, _)| owner.0 == killer.0) {
+            gold.unreliable += WARD_BOUNTY_GOLD;
+        }
+    }
+}
+
+pub fn expire_wards(mut commands: Commands, tick: Res<Tick>, ward_query: Query<(Entity, &Ward)>) {
+    for (entity, ward) in &ward_query {
+        if tick.0 >= ward.expires_at {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combat::{apply_damage, Damaged},
+        order::OrderIssued,
+        player::PlayerId,
+        unit::HeroBundle,
+    };
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(TickRate(10));
+        world.init_resource::<Tick>();
+        world.init_resource::<WardStock>();
+        world.init_resource::<Events<OrderApplied>>();
+        world.init_resource::<Events<WardPlaced>>();
+        world.init_resource::<Events<Damaged>>();
+        world.init_resource::<Events<UnitDied>>();
+        world
+    }
+
+    #[test]
+    fn test_wards_are_bought_from_the_stock_and_placed() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((buy_wards, place_wards).chain());
+        let mut hero = HeroBundle::new(PlayerId(1), Team::Radiant);
+        hero.gold = Gold {
+            reliable: 100,
+            unreliable: 20,
+        };
+        let hero = world.spawn(hero).id();
+        let buy = |world: &mut World, schedule: &mut Schedule| {
+            world.send_event(OrderApplied(OrderIssued {
+                player: PlayerId(1),
+                team: Team::Radiant,
+                unit: hero.into(),
+                order: Order::BuyWard {
+                    kind: WardKind::Sentry,
+                },
+            }));
+            schedule.run(world);
+            world.resource_mut::<Events<OrderApplied>>().update();
+        };
+
+        for _ in 0..3 {
+            buy(&mut world, &mut schedule);
+        }
+        // Two are all the gold pays for.
+        let sentries = |world: &World| {
+            world
+                .get::<Inventory>(hero)
+                .unwrap()
+                .items()
+                .filter(|item| *item == Item::Ward(WardKind::Sentry))
+                .count()
+        };
+        assert_eq!(sentries(&world), 2);
+        assert_eq!(
+            *world.get::<Gold>(hero).unwrap(),
+            Gold {
+                reliable: 20,
+                unreliable: 0,
+            }
+        );
+        let stock = world.resource::<WardStock>();
+        assert_eq!(stock.get(Team::Radiant, WardKind::Sentry), 1);
+        assert_eq!(stock.get(Team::Dire, WardKind::Sentry), 3);
+
+        let target = Team::Radiant.fountain() + Vec2::new(50.0, 0.0);
+        world.get_mut::<WardTarget>(hero).unwrap().0 = Some((WardKind::Sentry, target));
+        schedule.run(&mut world);
+        assert_eq!(sentries(&world), 1);
+        assert_eq!(world.get::<WardTarget>(hero).unwrap().0, None);
+        let placed = world
+            .resource_mut::<Events<WardPlaced>>()
+            .drain()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            placed,
+            [WardPlaced {
+                team: Team::Radiant,
+                kind: WardKind::Sentry,
+                position: target,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_stock_refills_up_to_its_maximum() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_system(restock_wards);
+        let ticks = WardKind::Observer.restock_secs() * 10;
+        for restock in 1..=4 {
+            world.resource_mut::<Tick>().0 = restock * ticks;
+            schedule.run(&mut world);
+        }
+        let stock = world.resource::<WardStock>();
+        assert_eq!(
+            stock.get(Team::Dire, WardKind::Observer),
+            WardKind::Observer.max_stock()
+        );
+    }
+
+    #[test]
+    fn test_only_enemies_get_a_bounty_for_wards() {
+        let mut world = world();
+        let mut schedule = Schedule::new();
+        schedule.add_systems((apply_damage, reward_ward_kills).chain());
+        let radiant = world
+            .spawn(HeroBundle::new(PlayerId(1), Team::Radiant))
+            .id();
+        let dire = world.spawn(HeroBundle::new(PlayerId(2), Team::Dire)).id();
+        let ward = |world: &mut World| {
+            world
+                .spawn(WardBundle::new(
+                    Team::Radiant,
+                    WardKind::Observer,
+                    Vec2::ZERO,
+                    u64::MAX,
+                ))
+                .id()
+        };
+        let hit = |world: &mut World, schedule: &mut Schedule, source, target| {
+            world.send_event(Damaged {
+                source,
+                target,
+                amount: 1000.0,
+            });
+            schedule.run(world);
+            world.resource_mut::<Events<Damaged>>().update();
+            world.resource_mut::<Events<UnitDied>>().update();
+        };
+
+        let denied = ward(&mut world);
+        for _ in 0..WARD_HITS as usize {
+            hit(&mut world, &mut schedule, radiant, denied);
+        }
+        assert_eq!(world.get::<Health>(denied).unwrap().current, 0.0);
+        assert_eq!(world.get::<Gold>(radiant).unwrap().total(), 0);
+
+        let killed = ward(&mut world);
+        for hits in 1..=WARD_HITS as usize {
+            hit(&mut world, &mut schedule, dire, killed);
+            let gold = world.get::<Gold>(dire).unwrap().unreliable;
+            let expected = if hits == WARD_HITS as usize {
+                WARD_BOUNTY_GOLD
+            } else {
+                0
+            };
+            assert_eq!(gold, expected);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+mod harness;
+
+use bevy::prelude::*;
+
+use harness::Harness;
+use open_dota_server::{
+    boss::Boss,
+    combat::Health,
+    item::{Inventory, Item},
+    order::Order,
+    unit::Position,
+    ClientMessage, ServerMessage,
+};
+
+#[test]
+fn test_boss_kill_is_announced_to_everyone() {
+    let mut harness = Harness::new();
+    let killer = harness.join("killer");
+    let other = harness.join("other");
+    // The boss spawned as the match started.
+    let boss = harness
+        .server
+        .world
+        .query_filtered::<Entity, With<Boss>>()
+        .single(&harness.server.world);
+    harness
+        .server
+        .world
+        .get_mut::<Health>(boss)
+        .unwrap()
+        .current = 1.0;
+
+    let player = harness.client(killer).player_id.unwrap();
+    let hero = harness.players().get(player).unwrap().hero.unwrap();
+    // Heroes can only attack what their team sees.
+    let boss_position = harness.server.world.get::<Position>(boss).unwrap().0;
+    harness.server.world.get_mut::<Position>(hero).unwrap().0 =
+        boss_position + Vec2::new(60.0, 0.0);
+    harness.step();
+    harness.send(
+        killer,
+        ClientMessage::Order {
+            unit: hero.into(),
+            order: Order::Attack {
+                target: boss.into(),
+            },
+        },
+    );
+    let announced = |harness: &Harness, handle| {
+        harness.inbox(handle).iter().any(|message| {
+            matches!(
+                message,
+                ServerMessage::BossKilled {
+                    killer: Some(killer),
+                    item: Some(Item::Aegis),
+                    ..
+                } if *killer == player
+            )
+        })
+    };
+    harness.run_until(|harness| announced(harness, killer) && announced(harness, other));
+    assert!(harness.server.world.get_entity(boss).is_none());
+    assert!(harness
+        .server
+        .world
+        .get::<Inventory>(hero)
+        .unwrap()
+        .contains(Item::Aegis));
+}
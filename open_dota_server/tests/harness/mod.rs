@@ -0,0 +1,233 @@
+//! Runs a server and any number of clients in one process, connected over loopback.
+//!
+//! Every app advances its clock by exactly [`TICK`] per update and the apps are always updated in
+//! the same order, server first. Only message delivery between the apps is asynchronous, so tests
+//! wait for it with [`Harness::run_until`] instead of sleeping for a fixed time.
+
+// Not every test file uses every helper.
+#![allow(dead_code)]
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_quinnet::client::{
+    certificate::CertificateVerificationMode, connection::ConnectionConfiguration, Client,
+    QuinnetClientPlugin,
+};
+
+use open_dota_server::{
+    access::AccessControl,
+    config::ServerConfig,
+    identity::{generate_key, Identity, SigningKey},
+    player::{JoinRole, PlayerId, Players},
+    server::ServerPlugin,
+    ClientMessage, ServerMessage,
+};
+
+pub const TICK: Duration = Duration::from_millis(33);
+/// How long [`Harness::run_until`] waits for a condition before failing the test.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Index of a client added with [`Harness::add_client`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientHandle(usize);
+
+/// State of one test client, kept as a resource in its app.
+#[derive(Resource)]
+pub struct TestClient {
+    pub name: String,
+    pub key: SigningKey,
+    pub password: Option<String>,
+    pub role: JoinRole,
+    pub player_id: Option<PlayerId>,
+    /// Every message received from the server, in order.
+    pub inbox: Vec<ServerMessage>,
+}
+
+pub struct Harness {
+    pub server: App,
+    pub address: SocketAddr,
+    pub clients: Vec<App>,
+}
+
+impl TestClient {
+    pub fn identity(&self) -> Identity {
+        Identity::of(&self.key)
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        Self::with_config(ServerConfig::default())
+    }
+
+    /// Starts a server with `config`, overriding its address and ban file so tests can run in
+    /// parallel.
+    pub fn with_config(mut config: ServerConfig) -> Self {
+        config.bind_address = Ipv4Addr::LOCALHOST.into();
+        config.port = free_port();
+        config.ban_file = std::env::temp_dir().join(format!("open_dota_bans_{}.toml", config.port));
+        let address = SocketAddr::new(config.bind_address, config.port);
+        let access = AccessControl::load(&config).unwrap();
+
+        let mut server = App::new();
+        server
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+            .insert_resource(config)
+            .insert_resource(access)
+            .add_plugin(ServerPlugin);
+        // Runs the startup system that opens the endpoint.
+        server.update();
+        Self {
+            server,
+            address,
+            clients: Vec::new(),
+        }
+    }
+
+    /// Connects a new client, which joins as soon as the server sends its challenge.
+    pub fn add_client(&mut self, name: &str) -> ClientHandle {
+        self.add_client_with_password(name, None)
+    }
+
+    pub fn add_client_with_password(&mut self, name: &str, password: Option<&str>) -> ClientHandle {
+        self.add_client_with_role(name, password, JoinRole::Player)
+    }
+
+    /// Connects a client that joins as a spectator.
+    pub fn add_spectator(&mut self, name: &str) -> ClientHandle {
+        self.add_client_with_role(name, None, JoinRole::Spectator)
+    }
+
+    fn add_client_with_role(
+        &mut self,
+        name: &str,
+        password: Option<&str>,
+        role: JoinRole,
+    ) -> ClientHandle {
+        let mut client = App::new();
+        client
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+            .add_plugin(QuinnetClientPlugin::default())
+            .insert_resource(TestClient {
+                name: name.to_string(),
+                key: generate_key(),
+                password: password.map(str::to_string),
+                role,
+                player_id: None,
+                inbox: Vec::new(),
+            })
+            .add_system(receive_messages);
+        client
+            .world
+            .resource_mut::<Client>()
+            .open_connection(
+                ConnectionConfiguration::from_addrs(
+                    self.address,
+                    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+                ),
+                CertificateVerificationMode::SkipVerification,
+            )
+            .unwrap();
+        self.clients.push(client);
+        ClientHandle(self.clients.len() - 1)
+    }
+
+    /// Adds a client and waits until the server has admitted it.
+    pub fn join(&mut self, name: &str) -> ClientHandle {
+        let handle = self.add_client(name);
+        self.run_until(|harness| harness.client(handle).player_id.is_some());
+        handle
+    }
+
+    /// Updates the server once, then every client in the order they were added.
+    pub fn step(&mut self) {
+        self.server.update();
+        for client in &mut self.clients {
+            client.update();
+        }
+    }
+
+    /// Steps until `condition` holds, panicking if it does not within a few seconds.
+    pub fn run_until(&mut self, mut condition: impl FnMut(&Self) -> bool) {
+        let started = Instant::now();
+        while !condition(self) {
+            assert!(
+                started.elapsed() < TIMEOUT,
+                "condition not met within {TIMEOUT:?}"
+            );
+            self.step();
+            // Gives the network tasks a chance to deliver messages between steps.
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Steps a fixed number of times, e.g. to check that something does *not* happen.
+    pub fn run_steps(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.step();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    pub fn send(&self, handle: ClientHandle, message: ClientMessage) {
+        self.clients[handle.0]
+            .world
+            .resource::<Client>()
+            .connection()
+            .send_message(message)
+            .unwrap();
+    }
+
+    pub fn client(&self, handle: ClientHandle) -> &TestClient {
+        self.clients[handle.0].world.resource::<TestClient>()
+    }
+
+    pub fn inbox(&self, handle: ClientHandle) -> &[ServerMessage] {
+        &self.client(handle).inbox
+    }
+
+    pub fn players(&self) -> &Players {
+        self.server.world.resource::<Players>()
+    }
+}
+
+fn receive_messages(mut client: ResMut<Client>, mut test_client: ResMut<TestClient>) {
+    let Some(connection) = client.get_connection_mut() else {
+        return;
+    };
+    while let Ok(Some(message)) = connection.receive_message::<ServerMessage>() {
+        match &message {
+            ServerMessage::Challenge(challenge) => {
+                connection
+                    .send_message(ClientMessage::Join {
+                        name: test_client.name.clone(),
+                        identity: test_client.identity(),
+                        signature: challenge.sign(&test_client.key),
+                        password: test_client.password.clone(),
+                        role: test_client.role,
+                    })
+                    .unwrap();
+            }
+            ServerMessage::InitClient { player_id, .. } => {
+                test_client.player_id = Some(*player_id);
+            }
+            _ => (),
+        }
+        test_client.inbox.push(message);
+    }
+}
+
+/// Asks the OS for a port nothing is listening on.
+fn free_port() -> u16 {
+    UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
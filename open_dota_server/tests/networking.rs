@@ -0,0 +1,192 @@
+mod harness;
+
+use bevy::prelude::*;
+
+use harness::{Harness, TICK};
+use open_dota_server::{
+    communication::ChatWheelPhrase,
+    config::ServerConfig,
+    order::Order,
+    player::{JoinRejection, JoinRole, Team},
+    unit::Position,
+    ClientMessage, ServerMessage,
+};
+
+fn received_chat(harness: &Harness, handle: harness::ClientHandle, text: &str) -> bool {
+    harness
+        .inbox(handle)
+        .iter()
+        .any(|message| matches!(message, ServerMessage::ChatMessage { message } if message == text))
+}
+
+#[test]
+fn test_chat_reaches_all_clients() {
+    let mut harness = Harness::new();
+    let clients = [
+        harness.join("alice"),
+        harness.join("bob"),
+        harness.join("carol"),
+    ];
+    assert_eq!(harness.players().len(), 3);
+
+    harness.send(
+        clients[0],
+        ClientMessage::ChatMessage {
+            message: "glhf".to_string(),
+        },
+    );
+    harness.run_until(|harness| {
+        clients
+            .iter()
+            .all(|client| received_chat(harness, *client, "glhf"))
+    });
+}
+
+#[test]
+fn test_chat_wheel_reaches_only_teammates() {
+    let mut harness = Harness::new();
+    let radiant = harness.join("radiant1");
+    let dire = harness.join("dire1");
+    let teammate = harness.join("radiant2");
+    let team = |harness: &Harness, handle| {
+        let player_id = harness.client(handle).player_id.unwrap();
+        harness.players().get(player_id).unwrap().team
+    };
+    assert_eq!(team(&harness, radiant), Team::Radiant);
+    assert_eq!(team(&harness, dire), Team::Dire);
+    assert_eq!(team(&harness, teammate), Team::Radiant);
+
+    harness.send(
+        radiant,
+        ClientMessage::ChatWheel {
+            phrase: ChatWheelPhrase::Push,
+        },
+    );
+    let is_push = |message: &ServerMessage| {
+        matches!(
+            message,
+            ServerMessage::ChatWheel {
+                phrase: ChatWheelPhrase::Push,
+                ..
+            }
+        )
+    };
+    harness.run_until(|harness| harness.inbox(teammate).iter().any(is_push));
+    // The sender is its own teammate, and the message has had time to reach everyone.
+    assert!(harness.inbox(radiant).iter().any(is_push));
+    harness.run_steps(10);
+    assert!(!harness.inbox(dire).iter().any(is_push));
+}
+
+#[test]
+fn test_wrong_password_is_rejected() {
+    let mut harness = Harness::with_config(ServerConfig {
+        password: Some("hunter2".to_string()),
+        ..Default::default()
+    });
+    let intruder = harness.add_client_with_password("intruder", Some("guess"));
+    let guest = harness.add_client_with_password("guest", Some("hunter2"));
+    harness.run_until(|harness| {
+        harness.client(guest).player_id.is_some()
+            && harness.inbox(intruder).iter().any(|message| {
+                matches!(
+                    message,
+                    ServerMessage::JoinRejected {
+                        reason: JoinRejection::WrongPassword
+                    }
+                )
+            })
+    });
+    assert_eq!(harness.client(intruder).player_id, None);
+    assert_eq!(harness.players().len(), 1);
+
+    // Rejected clients can't chat, and lose their connection shortly after.
+    harness.send(
+        intruder,
+        ClientMessage::ChatMessage {
+            message: "let me in".to_string(),
+        },
+    );
+    harness.run_steps(20);
+    assert!(!received_chat(&harness, guest, "let me in"));
+    harness.run_until(|harness| {
+        harness
+            .server
+            .world
+            .resource::<bevy_quinnet::server::Server>()
+            .endpoint()
+            .clients()
+            .len()
+            == 1
+    });
+}
+
+#[test]
+fn test_joining_twice_is_ignored() {
+    let mut harness = Harness::new();
+    let alice = harness.join("alice");
+    let bob = harness.join("bob");
+    let challenge = harness
+        .inbox(alice)
+        .iter()
+        .find_map(|message| match message {
+            ServerMessage::Challenge(challenge) => Some(*challenge),
+            _ => None,
+        })
+        .unwrap();
+    let client = harness.client(alice);
+    harness.send(
+        alice,
+        ClientMessage::Join {
+            name: client.name.clone(),
+            identity: client.identity(),
+            signature: challenge.sign(&client.key),
+            password: None,
+            role: JoinRole::Player,
+        },
+    );
+    harness.run_steps(20);
+    assert!(!harness
+        .inbox(alice)
+        .iter()
+        .any(|message| matches!(message, ServerMessage::JoinRejected { .. })));
+
+    // Still connected and in the match.
+    harness.send(
+        alice,
+        ClientMessage::ChatMessage {
+            message: "still here".to_string(),
+        },
+    );
+    harness.run_until(|harness| received_chat(harness, bob, "still here"));
+    assert_eq!(harness.players().len(), 2);
+}
+
+#[test]
+fn test_move_order_moves_hero() {
+    let mut harness = Harness::new();
+    let client = harness.join("mover");
+    let player_id = harness.client(client).player_id.unwrap();
+    let hero = harness.players().get(player_id).unwrap().hero.unwrap();
+    let start = harness.server.world.get::<Position>(hero).unwrap().0;
+    let target = start + Vec2::new(TICK.as_secs_f32() * 100.0, 0.0);
+
+    harness.send(
+        client,
+        ClientMessage::Order {
+            unit: hero.into(),
+            order: Order::Move { target },
+        },
+    );
+    harness.run_until(|harness| harness.server.world.get::<Position>(hero).unwrap().0 == target);
+    // The client sees the hero arrive in a snapshot.
+    harness.run_until(|harness| {
+        harness.inbox(client).iter().any(|message| match message {
+            ServerMessage::Snapshot(snapshot) => snapshot
+                .units
+                .iter()
+                .any(|unit| unit.id == hero.into() && unit.position == target),
+            _ => false,
+        })
+    });
+}
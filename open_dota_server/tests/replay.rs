@@ -0,0 +1,145 @@
+mod harness;
+
+use bevy::prelude::*;
+
+use harness::Harness;
+use open_dota_server::{
+    config::ServerConfig,
+    order::Order,
+    playback::ReplaySimulation,
+    player::Team,
+    replay::{Replay, ReplayEvent, ReplayRecorder},
+    simulation::{StateHash, Tick},
+    unit::{Position, UnitId},
+    ClientMessage,
+};
+
+#[test]
+fn test_records_orders_and_heroes() {
+    let replay_dir = std::env::temp_dir().join(format!("open_dota_replays_{}", std::process::id()));
+    let mut harness = Harness::with_config(ServerConfig {
+        seed: Some(7),
+        replay_dir: Some(replay_dir.clone()),
+        ..Default::default()
+    });
+    let client = harness.join("recorded");
+    let player = harness.client(client).player_id.unwrap();
+    let hero = harness.players().get(player).unwrap().hero.unwrap();
+    let target = Vec2::new(0.0, 0.0);
+    harness.send(
+        client,
+        ClientMessage::Order {
+            unit: hero.into(),
+            order: Order::Move { target },
+        },
+    );
+    harness.run_until(|harness| harness.server.world.get::<Position>(hero).unwrap().0 == target);
+
+    let path = harness
+        .server
+        .world
+        .resource::<ReplayRecorder>()
+        .path()
+        .to_owned();
+    let replay = Replay::load(&path).unwrap();
+    std::fs::remove_dir_all(replay_dir).unwrap();
+    assert_eq!(replay.header.seed, 7);
+    let events = replay
+        .frames
+        .iter()
+        .flat_map(|frame| {
+            frame
+                .events
+                .iter()
+                .map(move |event| (frame.tick, event.clone()))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(events.len(), 5, "unexpected replay events {events:?}");
+    // The boss and both couriers spawn as the match starts.
+    assert!(events[..3].iter().all(|(tick, _)| *tick == 1));
+    assert!(matches!(events[0].1, ReplayEvent::BossSpawned { .. }));
+    assert!(matches!(
+        events[1].1,
+        ReplayEvent::CourierSpawned {
+            team: Team::Radiant,
+            ..
+        }
+    ));
+    assert!(matches!(
+        events[2].1,
+        ReplayEvent::CourierSpawned {
+            team: Team::Dire,
+            ..
+        }
+    ));
+    assert!(events[3].0 < events[4].0);
+    assert!(matches!(
+        &events[3].1,
+        ReplayEvent::HeroSpawned { player: spawned, name, .. } if *spawned == player && name == "recorded"
+    ));
+    assert_eq!(
+        events[4].1,
+        ReplayEvent::Order {
+            player,
+            unit: UnitId::from(hero),
+            order: Order::Move { target },
+        }
+    );
+}
+
+#[test]
+fn test_replay_simulates_like_server() {
+    let replay_dir = std::env::temp_dir().join(format!(
+        "open_dota_replays_{}_simulation",
+        std::process::id()
+    ));
+    let mut harness = Harness::with_config(ServerConfig {
+        replay_dir: Some(replay_dir.clone()),
+        ..Default::default()
+    });
+    let client = harness.join("simulated");
+    let player = harness.client(client).player_id.unwrap();
+    let hero = harness.players().get(player).unwrap().hero.unwrap();
+    for target in [Vec2::new(100.0, 50.0), Vec2::new(-20.0, 300.0)] {
+        harness.send(
+            client,
+            ClientMessage::Order {
+                unit: hero.into(),
+                order: Order::Move { target },
+            },
+        );
+        harness.run_steps(5);
+    }
+    harness.run_steps(20);
+    let live_tick = harness.server.world.resource::<Tick>().0;
+    let live_position = harness.server.world.get::<Position>(hero).unwrap().0;
+    let live_hash = harness.server.world.resource::<StateHash>().0;
+
+    let path = harness
+        .server
+        .world
+        .resource::<ReplayRecorder>()
+        .path()
+        .to_owned();
+    let mut simulation = ReplaySimulation::new(Replay::load(&path).unwrap());
+    std::fs::remove_dir_all(replay_dir).unwrap();
+    // Nothing is recorded for ticks without events, so keep simulating up to the live tick.
+    while simulation.tick() < live_tick {
+        simulation.step();
+    }
+    let snapshot = simulation.snapshot();
+    let unit = snapshot
+        .units
+        .iter()
+        .find(|unit| unit.id == UnitId::from(hero))
+        .unwrap();
+    assert_eq!(unit.position, live_position);
+    assert_eq!(simulation.state_hash(), live_hash);
+    // The server recorded a hash every second and playback agreed with all of them.
+    assert!(simulation
+        .replay()
+        .frames
+        .iter()
+        .any(|frame| frame.state_hash.is_some()));
+    assert_eq!(simulation.diverged_at(), None);
+}
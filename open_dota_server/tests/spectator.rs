@@ -0,0 +1,106 @@
+mod harness;
+
+use bevy::prelude::*;
+
+use harness::Harness;
+use open_dota_server::{
+    config::ServerConfig, order::Order, player::JoinRejection, simulation::Tick,
+    spectator::Spectators, unit::Position, ClientMessage, ServerMessage,
+};
+
+fn server_tick(harness: &Harness) -> u64 {
+    harness.server.world.resource::<Tick>().0
+}
+
+#[test]
+fn test_spectators_see_the_match_late() {
+    let mut harness = Harness::with_config(ServerConfig {
+        spectator_delay: 1,
+        ..Default::default()
+    });
+    let player = harness.join("player");
+    let spectator = harness.add_spectator("watcher");
+    harness.run_until(|harness| {
+        harness
+            .inbox(spectator)
+            .iter()
+            .any(|message| matches!(message, ServerMessage::InitSpectator { delay_secs: 1 }))
+    });
+    assert_eq!(harness.client(spectator).player_id, None);
+    assert_eq!(harness.players().len(), 1);
+    assert_eq!(harness.server.world.resource::<Spectators>().len(), 1);
+
+    let player_id = harness.client(player).player_id.unwrap();
+    let hero = harness.players().get(player_id).unwrap().hero.unwrap();
+    let start = harness.server.world.get::<Position>(hero).unwrap().0;
+    harness.send(
+        player,
+        ClientMessage::Order {
+            unit: hero.into(),
+            order: Order::Move {
+                target: start + Vec2::new(100.0, 0.0),
+            },
+        },
+    );
+    harness.run_until(|harness| harness.server.world.get::<Position>(hero).unwrap().0 != start);
+    let moved_at = server_tick(&harness);
+
+    let spectator_saw_move = |harness: &Harness| {
+        harness
+            .inbox(spectator)
+            .iter()
+            .any(|message| match message {
+                ServerMessage::Snapshot(snapshot) => snapshot
+                    .units
+                    .iter()
+                    .any(|unit| unit.id == hero.into() && unit.position != start),
+                _ => false,
+            })
+    };
+    harness.run_until(spectator_saw_move);
+    let tick_rate = ServerConfig::default().tick_rate as u64;
+    assert!(server_tick(&harness) >= moved_at + tick_rate);
+}
+
+#[test]
+fn test_spectator_chat_stays_with_spectators() {
+    let mut harness = Harness::new();
+    let player = harness.join("player");
+    let spectator = harness.add_spectator("watcher");
+    let other_spectator = harness.add_spectator("lurker");
+    harness.run_until(|harness| harness.server.world.resource::<Spectators>().len() == 2);
+
+    harness.send(
+        spectator,
+        ClientMessage::ChatMessage {
+            message: "gg".to_string(),
+        },
+    );
+    let received = |harness: &Harness, handle| {
+        harness.inbox(handle).iter().any(
+            |message| matches!(message, ServerMessage::ChatMessage { message } if message == "gg"),
+        )
+    };
+    harness.run_until(|harness| received(harness, other_spectator));
+    harness.run_steps(10);
+    assert!(!received(&harness, player));
+}
+
+#[test]
+fn test_spectators_can_be_turned_off() {
+    let mut harness = Harness::with_config(ServerConfig {
+        max_spectators: 0,
+        ..Default::default()
+    });
+    let spectator = harness.add_spectator("watcher");
+    harness.run_until(|harness| {
+        harness.inbox(spectator).iter().any(|message| {
+            matches!(
+                message,
+                ServerMessage::JoinRejected {
+                    reason: JoinRejection::SpectatorsFull
+                }
+            )
+        })
+    });
+}